@@ -0,0 +1,7 @@
+// Generates the gRPC service and messages from proto/snapshots.proto, with a
+// protoc that ships as a crate so building needs nothing installed
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::compile_protos("proto/snapshots.proto")?;
+    Ok(())
+}
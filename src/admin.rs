@@ -0,0 +1,163 @@
+// Admin endpoints under `/-/`, for operators rather than scrapers. They change
+// what the exporter does, so they answer only to clients on the same host, or
+// to anyone once `server.auth` is configured (the request has been
+// authenticated by then).
+
+use crate::config::LogLevel;
+use crate::limits::read_body;
+use crate::{logging, HttpState};
+use clap::ValueEnum;
+use hyper::header::ALLOW;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use log::info;
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use std::time::Instant;
+use tokio::sync::broadcast::error::RecvError;
+
+pub async fn handle(req: Request<Body>, peer: SocketAddr, state: &HttpState, cluster: Option<&str>) -> Response<Body> {
+    if !allowed(peer, state) {
+        return forbidden();
+    }
+    match req.uri().path() {
+        "/-/reload-data" if req.method() == Method::POST => reload_data(state, cluster).await,
+        "/-/reload-data" => method_not_allowed("POST"),
+        "/-/pause" if req.method() == Method::POST => set_paused(state, cluster, true).await,
+        "/-/resume" if req.method() == Method::POST => set_paused(state, cluster, false).await,
+        "/-/pause" | "/-/resume" => method_not_allowed("POST"),
+        "/-/loglevel" if req.method() == Method::GET => text(StatusCode::OK, format!("{}\n", level_name(logging::level()))),
+        "/-/loglevel" if req.method() == Method::PUT => set_log_level(req).await,
+        "/-/loglevel" => method_not_allowed("GET, PUT"),
+        _ => text(StatusCode::NOT_FOUND, "404 Not Found\n".to_string()),
+    }
+}
+
+pub fn allowed(peer: SocketAddr, state: &HttpState) -> bool {
+    peer.ip().is_loopback() || state.config.borrow().server.auth.is_enabled()
+}
+
+pub fn forbidden() -> Response<Body> {
+    text(StatusCode::FORBIDDEN, "403 Forbidden: admin endpoints need server.auth or a local client\n".to_string())
+}
+
+// Poll the clusters (or `cluster`) right away and answer once a poll started
+// after the request has written the cache; 502 when it failed, so the data is
+// still the old one
+async fn reload_data(state: &HttpState, cluster: Option<&str>) -> Response<Body> {
+    let Some(wanted) = clusters(state, cluster) else {
+        return text(StatusCode::NOT_FOUND, "404 Not Found: unknown cluster\n".to_string());
+    };
+
+    // Subscribe before asking, so no update can slip through in between
+    let mut updates = state.updates.subscribe();
+    let requested = Instant::now();
+    for name in &wanted {
+        let _ = state.refresh.send(name.clone());
+    }
+    let mut pending: BTreeSet<&str> = wanted.iter().map(String::as_str).collect();
+    // Polls already running when the request came in do not count
+    let since_request = |polled: Option<Instant>| polled.is_some_and(|polled| polled >= requested);
+    while !pending.is_empty() {
+        match updates.recv().await {
+            Ok(name) => {
+                if state.cache.lock().await.get(&name).is_some_and(|cluster| since_request(cluster.attempted)) {
+                    pending.remove(name.as_str());
+                }
+            }
+            // Missed updates come around again with the next regular poll
+            Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => break,
+        }
+    }
+
+    let cache = state.cache.lock().await;
+    let mut failed = false;
+    let mut body = String::new();
+    for name in &wanted {
+        if cache.get(name).is_some_and(|cluster| since_request(cluster.polled)) {
+            body.push_str(&format!("{}: refreshed\n", name));
+        } else {
+            failed = true;
+            body.push_str(&format!("{}: poll failed, serving the previous data\n", name));
+        }
+    }
+    let status = if failed { StatusCode::BAD_GATEWAY } else { StatusCode::OK };
+    text(status, body)
+}
+
+// Stop or restart the scheduled polls of every cluster (or `cluster`); the
+// cached data keeps being served and goes stale. A resumed cluster is polled
+// right away.
+async fn set_paused(state: &HttpState, cluster: Option<&str>, paused: bool) -> Response<Body> {
+    let Some(wanted) = clusters(state, cluster) else {
+        return text(StatusCode::NOT_FOUND, "404 Not Found: unknown cluster\n".to_string());
+    };
+    let action = if paused { "paused" } else { "resumed" };
+    let mut cache = state.cache.lock().await;
+    let mut body = String::new();
+    for name in &wanted {
+        let Some(cluster_cache) = cache.get_mut(name) else {
+            continue;
+        };
+        if cluster_cache.paused != paused {
+            cluster_cache.paused = paused;
+            info!("Polling of cluster {} {}", name, action);
+            if !paused {
+                let _ = state.refresh.send(name.clone());
+            }
+        }
+        body.push_str(&format!("{}: {}\n", name, action));
+    }
+    text(StatusCode::OK, body)
+}
+
+// The configured clusters, or just `cluster`; None when it is not configured
+fn clusters(state: &HttpState, cluster: Option<&str>) -> Option<Vec<String>> {
+    let config = state.config.borrow();
+    match cluster {
+        Some(cluster) => config.cluster_rpc(cluster).map(|_| vec![cluster.to_string()]),
+        None => Some(config.clusters().iter().map(|(name, _)| name.to_string()).collect()),
+    }
+}
+
+// The new level is the request body, bare ("debug") or form encoded
+// ("level=debug"); it applies until the next restart, or until a reload
+// changes `log_level` in the config file
+async fn set_log_level(req: Request<Body>) -> Response<Body> {
+    let body = match read_body(req.into_body(), 64).await {
+        Ok(body) => String::from_utf8_lossy(&body).into_owned(),
+        Err(e) => return text(StatusCode::BAD_REQUEST, format!("400 Bad Request: {}\n", e)),
+    };
+    let value = body.trim();
+    let value = value.strip_prefix("level=").unwrap_or(value);
+    let Ok(level) = LogLevel::from_str(value, true) else {
+        return text(
+            StatusCode::BAD_REQUEST,
+            format!("400 Bad Request: unknown log level {:?}, expected error, warn, info or debug\n", value),
+        );
+    };
+    logging::set_level(level);
+    info!("Log level set to {}", level_name(level));
+    text(StatusCode::OK, format!("{}\n", level_name(level)))
+}
+
+fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Error => "error",
+        LogLevel::Warn => "warn",
+        LogLevel::Info => "info",
+        LogLevel::Debug => "debug",
+    }
+}
+
+fn method_not_allowed(allow: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::METHOD_NOT_ALLOWED)
+        .header(ALLOW, allow)
+        .body(Body::from("405 Method Not Allowed\n"))
+        .unwrap()
+}
+
+fn text(status: StatusCode, body: String) -> Response<Body> {
+    Response::builder().status(status).body(Body::from(body)).unwrap()
+}
@@ -0,0 +1,76 @@
+// JSON and CSV views of the cached poll results, for tooling that does not
+// speak the Prometheus format (bots, dashboards, scripts, spreadsheets)
+
+use crate::{MetricsCache, ValidatorMetrics};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::UNIX_EPOCH;
+
+#[derive(Serialize)]
+struct ValidatorsResponse<'a> {
+    clusters: Vec<ClusterSnapshot<'a>>,
+}
+
+#[derive(Serialize)]
+struct ClusterSnapshot<'a> {
+    cluster: &'a str,
+    // Unix time of the last successful poll, null before the first one
+    updated_at: Option<f64>,
+    data_age_seconds: Option<f64>,
+    stale: bool,
+    epoch: Option<u64>,
+    validators: &'a [ValidatorMetrics],
+}
+
+// `/api/v1/validators`: the validators of every cluster, or of `cluster` only;
+// None when that cluster is not monitored
+pub fn validators(cache: &BTreeMap<String, MetricsCache>, cluster: Option<&str>, stale_after: f64) -> Option<String> {
+    let response = ValidatorsResponse {
+        clusters: select(cache, cluster)?
+            .into_iter()
+            .map(|(name, cluster_cache)| ClusterSnapshot {
+                cluster: name,
+                updated_at: cluster_cache
+                    .updated_at
+                    .map(|at| at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()),
+                data_age_seconds: cluster_cache.age(),
+                stale: cluster_cache.is_stale(stale_after),
+                epoch: cluster_cache.epoch_info.as_ref().map(|info| info.epoch),
+                validators: &cluster_cache.validators,
+            })
+            .collect(),
+    };
+    Some(serde_json::to_string(&response).expect("validator snapshot serializes"))
+}
+
+// `/export.csv`: one row per validator and cluster, ordered by rank. Cluster
+// names and pubkeys never need quoting.
+pub fn validators_csv(cache: &BTreeMap<String, MetricsCache>, cluster: Option<&str>) -> Option<String> {
+    let mut csv = String::from("cluster,rank,vote_pubkey,node_pubkey,credits_earned,root_distance,vote_distance,delinquent\n");
+    for (name, cluster_cache) in select(cache, cluster)? {
+        for validator in &cluster_cache.validators {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{}",
+                name,
+                validator.rank,
+                validator.vote_pubkey,
+                validator.node_pubkey,
+                validator.credits_earned,
+                validator.root_distance,
+                validator.vote_distance,
+                validator.delinquent
+            );
+        }
+    }
+    Some(csv)
+}
+
+// Every cluster, or only `cluster`; None when that one is not monitored
+fn select<'a>(cache: &'a BTreeMap<String, MetricsCache>, cluster: Option<&str>) -> Option<Vec<(&'a String, &'a MetricsCache)>> {
+    match cluster {
+        Some(name) => Some(vec![cache.get_key_value(name)?]),
+        None => Some(cache.iter().collect()),
+    }
+}
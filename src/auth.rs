@@ -0,0 +1,198 @@
+use crate::config::AuthConfig;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use constant_time_eq::constant_time_eq;
+use log::error;
+use sha1::{Digest, Sha1};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+// Users of an htpasswd file as (name, hash) pairs. Only `{SHA}` hashes
+// (`htpasswd -s`) are understood; other schemes are rejected when loading so
+// a user cannot be locked out silently.
+pub fn load_htpasswd(path: &Path) -> Result<Vec<(String, String)>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let mut users = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((user, hash)) = line.split_once(':') else {
+            return Err(format!("{} line {}: expected user:hash", path.display(), number + 1));
+        };
+        if !hash.starts_with("{SHA}") {
+            return Err(format!(
+                "{} line {}: unsupported hash for user {:?}, create it with `htpasswd -s`",
+                path.display(),
+                number + 1,
+                user
+            ));
+        }
+        users.push((user.to_string(), hash.to_string()));
+    }
+    Ok(users)
+}
+
+type Users = Arc<Vec<(String, String)>>;
+
+// Modification time and size of the file the users were read from
+type Version = Option<(SystemTime, u64)>;
+
+// The users of the htpasswd file, read again only when the file's modification
+// time or size changes (or another file is configured), so edits apply without
+// reading it on every request
+#[derive(Default)]
+pub struct Htpasswd {
+    loaded: Mutex<Option<(PathBuf, Version, Users)>>,
+}
+
+impl Htpasswd {
+    fn users(&self, path: &Path) -> Result<Users, String> {
+        let version = fs::metadata(path).ok().and_then(|meta| Some((meta.modified().ok()?, meta.len())));
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some((loaded_path, loaded_version, users)) = &*loaded {
+            if loaded_path == path && version.is_some() && *loaded_version == version {
+                return Ok(Arc::clone(users));
+            }
+        }
+        let users = Arc::new(load_htpasswd(path)?);
+        *loaded = Some((path.to_path_buf(), version, Arc::clone(&users)));
+        Ok(users)
+    }
+}
+
+// Whether the request's Authorization header satisfies the configured auth;
+// without any configured, every request is allowed
+pub fn authorized(auth: &AuthConfig, htpasswd: &Htpasswd, header: Option<&str>) -> bool {
+    if !auth.is_enabled() {
+        return true;
+    }
+    let Some(header) = header else {
+        return false;
+    };
+    let (scheme, credentials) = header.split_once(' ').unwrap_or((header, ""));
+    let credentials = credentials.trim();
+
+    if scheme.eq_ignore_ascii_case("bearer") {
+        return auth
+            .bearer_token
+            .as_ref()
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), credentials.as_bytes()));
+    }
+    if scheme.eq_ignore_ascii_case("basic") {
+        let Some(path) = &auth.htpasswd else {
+            return false;
+        };
+        let Some((user, password)) = STANDARD
+            .decode(credentials)
+            .ok()
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+            .and_then(|decoded| decoded.split_once(':').map(|(user, password)| (user.to_string(), password.to_string())))
+        else {
+            return false;
+        };
+        let users = match htpasswd.users(path) {
+            Ok(users) => users,
+            Err(e) => {
+                error!("Rejecting request, htpasswd file unusable: {}", e);
+                return false;
+            }
+        };
+        let expected = format!("{{SHA}}{}", STANDARD.encode(Sha1::digest(password.as_bytes())));
+        return users
+            .iter()
+            .any(|(name, hash)| *name == user && constant_time_eq(hash.as_bytes(), expected.as_bytes()));
+    }
+    false
+}
+
+// Value of the WWW-Authenticate header sent with a 401
+pub fn challenge(auth: &AuthConfig) -> &'static str {
+    if auth.htpasswd.is_some() {
+        "Basic realm=\"solana-credits-exporter\""
+    } else {
+        "Bearer realm=\"solana-credits-exporter\""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An htpasswd file under the temp directory, unique to the test
+    fn htpasswd_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("solana-credits-exporter-{}-{}.htpasswd", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn sha(password: &str) -> String {
+        format!("{{SHA}}{}", STANDARD.encode(Sha1::digest(password.as_bytes())))
+    }
+
+    fn basic(user: &str, password: &str) -> String {
+        format!("Basic {}", STANDARD.encode(format!("{}:{}", user, password)))
+    }
+
+    fn basic_auth(path: &Path) -> AuthConfig {
+        AuthConfig { bearer_token: None, htpasswd: Some(path.to_path_buf()) }
+    }
+
+    #[test]
+    fn loads_users_skipping_comments_and_blank_lines() {
+        let path = htpasswd_file("load", &format!("# users\n\nprometheus:{}\n", sha("secret")));
+        assert_eq!(load_htpasswd(&path).unwrap(), vec![("prometheus".to_string(), sha("secret"))]);
+    }
+
+    #[test]
+    fn rejects_malformed_lines_and_other_hashes() {
+        let path = htpasswd_file("malformed", &format!("prometheus:{}\nno-separator\n", sha("secret")));
+        assert!(load_htpasswd(&path).unwrap_err().ends_with("line 2: expected user:hash"));
+        let path = htpasswd_file("bcrypt", "prometheus:$2y$05$abcdefghijklmnopqrstuv\n");
+        assert!(load_htpasswd(&path).unwrap_err().contains("unsupported hash"));
+        assert!(load_htpasswd(Path::new("/nonexistent/htpasswd")).is_err());
+    }
+
+    #[test]
+    fn checks_basic_credentials() {
+        let path = htpasswd_file("basic", &format!("prometheus:{}\n", sha("secret")));
+        let (auth, htpasswd) = (basic_auth(&path), Htpasswd::default());
+        assert!(authorized(&auth, &htpasswd, Some(&basic("prometheus", "secret"))));
+        assert!(!authorized(&auth, &htpasswd, Some(&basic("prometheus", "wrong"))));
+        assert!(!authorized(&auth, &htpasswd, Some(&basic("grafana", "secret"))));
+        assert!(!authorized(&auth, &htpasswd, Some("Basic not-base64")));
+        assert!(!authorized(&auth, &htpasswd, Some("Bearer secret")));
+        assert!(!authorized(&auth, &htpasswd, None));
+    }
+
+    #[test]
+    fn rejects_basic_credentials_with_a_malformed_file() {
+        let path = htpasswd_file("unusable", "prometheus\n");
+        assert!(!authorized(&basic_auth(&path), &Htpasswd::default(), Some(&basic("prometheus", "secret"))));
+    }
+
+    #[test]
+    fn rereads_the_file_once_it_changes() {
+        let path = htpasswd_file("changed", &format!("prometheus:{}\n", sha("old")));
+        let (auth, htpasswd) = (basic_auth(&path), Htpasswd::default());
+        assert!(authorized(&auth, &htpasswd, Some(&basic("prometheus", "old"))));
+        fs::write(&path, format!("prometheus:{}\ngrafana:{}\n", sha("new"), sha("new"))).unwrap();
+        assert!(authorized(&auth, &htpasswd, Some(&basic("prometheus", "new"))));
+        assert!(!authorized(&auth, &htpasswd, Some(&basic("prometheus", "old"))));
+    }
+
+    #[test]
+    fn checks_bearer_tokens() {
+        let auth = AuthConfig { bearer_token: Some("token".to_string()), htpasswd: None };
+        let htpasswd = Htpasswd::default();
+        assert!(authorized(&auth, &htpasswd, Some("Bearer token")));
+        assert!(authorized(&auth, &htpasswd, Some("bearer token")));
+        assert!(!authorized(&auth, &htpasswd, Some("Bearer other")));
+        assert!(!authorized(&auth, &htpasswd, Some(&basic("prometheus", "token"))));
+        assert!(!authorized(&auth, &htpasswd, None));
+        assert!(authorized(&AuthConfig::default(), &htpasswd, None));
+    }
+}
@@ -0,0 +1,72 @@
+// Leader slots and blocks produced by every node identity in the current
+// epoch, from `getBlockProduction`. The request covers the whole epoch so far,
+// so it runs every `block_production.refresh_interval` rather than every poll,
+// and right away when the epoch changes so the last epoch's counts do not
+// linger.
+
+use crate::config::BlockProductionConfig;
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Produced {
+    pub leader_slots: u64,
+    pub blocks_produced: u64,
+}
+
+impl Produced {
+    // Share of the leader slots without a block, None before the first one
+    pub fn skip_rate(&self) -> Option<f64> {
+        (self.leader_slots > 0).then(|| (self.leader_slots - self.blocks_produced) as f64 / self.leader_slots as f64)
+    }
+}
+
+#[derive(Default)]
+pub struct BlockProduction {
+    // Epoch the counts are for
+    epoch: Option<u64>,
+    fetched: Option<Instant>,
+    // Counts of each node identity that was leader this epoch
+    pub by_identity: HashMap<String, Produced>,
+}
+
+impl BlockProduction {
+    // Fetch the counts when due for `epoch`; a failure is logged and keeps
+    // those of the same epoch
+    pub async fn refresh(&mut self, client: &RpcClient, config: &BlockProductionConfig, epoch: Option<u64>) {
+        if epoch.is_some() && epoch != self.epoch {
+            self.by_identity.clear();
+            self.fetched = None;
+        }
+        if self.fetched.is_some_and(|fetched| fetched.elapsed().as_secs_f64() < config.refresh_interval) {
+            return;
+        }
+        match client.get_block_production().await {
+            Ok(response) => {
+                self.by_identity = response
+                    .value
+                    .by_identity
+                    .into_iter()
+                    .map(|(identity, (leader_slots, blocks_produced))| {
+                        let produced = Produced {
+                            leader_slots: leader_slots as u64,
+                            blocks_produced: blocks_produced as u64,
+                        };
+                        (identity, produced)
+                    })
+                    .collect();
+                debug!(
+                    "Fetched block production of slots {} to {}: {} leaders",
+                    response.value.range.first_slot,
+                    response.value.range.last_slot,
+                    self.by_identity.len()
+                );
+                self.epoch = epoch;
+                self.fetched = Some(Instant::now());
+            }
+            Err(e) => warn!("Block production request failed: {}", e),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::config::{AccessLog, Config, EndpointConfig, LogLevel, RpcMode};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use std::collections::HashSet;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// Prometheus exporter for Solana validator vote credits.
+///
+/// Settings are read from the optional config file first. A flag on the
+/// command line overrides it; each option can also be set through its
+/// `SOLANA_EXPORTER_*` environment variable, which only applies where neither
+/// the flag nor the config file sets the option.
+#[derive(Debug, Clone, Parser)]
+#[command(version, about)]
+pub struct Args {
+    /// Path to a TOML configuration file
+    #[arg(long, env = "SOLANA_EXPORTER_CONFIG", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Solana JSON-RPC endpoint to fetch vote accounts from; repeat the flag (or
+    /// comma-separate the variable) to list failover endpoints in order
+    #[arg(long = "rpc-url", env = "SOLANA_EXPORTER_RPC_URL", value_name = "URL", value_delimiter = ',')]
+    pub rpc_urls: Vec<String>,
+
+    /// Name of the cluster the RPC URLs belong to, exported as the `cluster` label
+    #[arg(long, env = "SOLANA_EXPORTER_CLUSTER", value_name = "NAME")]
+    pub cluster: Option<String>,
+
+    /// How the endpoint for each poll is chosen among several RPC URLs
+    #[arg(long, env = "SOLANA_EXPORTER_RPC_MODE", value_name = "MODE")]
+    pub rpc_mode: Option<RpcMode>,
+
+    /// Proxy for RPC traffic (http://, https://, socks5:// or socks5h:// URL)
+    #[arg(long, env = "SOLANA_EXPORTER_RPC_PROXY", value_name = "URL")]
+    pub rpc_proxy: Option<String>,
+
+    /// Address to serve metrics on, e.g. 127.0.0.1:59872 or [::1]:59872; repeat the
+    /// flag (or comma-separate the variable) to listen on several
+    #[arg(long, env = "SOLANA_EXPORTER_LISTEN", value_name = "ADDR", value_delimiter = ',')]
+    pub listen: Vec<SocketAddr>,
+
+    /// HTTP path the metrics are served on
+    #[arg(long, env = "SOLANA_EXPORTER_METRICS_PATH", value_name = "PATH")]
+    pub metrics_path: Option<String>,
+
+    /// Which HTTP requests to log: none, only those that failed, or all
+    #[arg(long, env = "SOLANA_EXPORTER_ACCESS_LOG", value_name = "LEVEL")]
+    pub access_log: Option<AccessLog>,
+
+    /// Most verbose messages to log: error, warn, info or debug
+    #[arg(long, env = "SOLANA_EXPORTER_LOG_LEVEL", value_name = "LEVEL")]
+    pub log_level: Option<LogLevel>,
+
+    /// Seconds between the start of two polls
+    #[arg(long, env = "SOLANA_EXPORTER_POLL_INTERVAL", value_name = "SECONDS", value_parser = parse_seconds)]
+    pub poll_interval: Option<f64>,
+
+    /// Seconds to wait for the RPC response before the fetch counts as timed out
+    #[arg(long, env = "SOLANA_EXPORTER_TIMEOUT", value_name = "SECONDS", value_parser = parse_seconds)]
+    pub timeout: Option<f64>,
+
+    /// Validate the configuration, resolve the RPC hosts and exit; non-zero exit code on errors
+    #[arg(long)]
+    pub check_config: bool,
+
+    // Options whose value came from their environment variable
+    #[arg(skip)]
+    from_env: HashSet<String>,
+}
+
+impl Args {
+    // Parse the command line, noting which options only the environment set
+    pub fn from_command_line() -> Self {
+        let matches = Self::command().get_matches();
+        let mut args = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        args.from_env = matches
+            .ids()
+            .filter(|id| matches.value_source(id.as_str()) == Some(ValueSource::EnvVariable))
+            .map(|id| id.to_string())
+            .collect();
+        args
+    }
+
+    // Whether the value of option `id` goes over one the config file has;
+    // environment variables only replace values the file left at the default
+    fn overrides(&self, id: &str, file_default: bool) -> bool {
+        file_default || !self.from_env.contains(id)
+    }
+
+    // Load the config file (if any) and layer the flags on top; also used on reload
+    pub fn load_config(&self) -> Result<Config, Box<dyn Error + Send + Sync>> {
+        let mut config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        let rpc_flags = [("rpc_urls", !self.rpc_urls.is_empty()), ("cluster", self.cluster.is_some())];
+        if !config.clusters.is_empty() && rpc_flags.iter().any(|&(id, given)| given && !self.from_env.contains(id)) {
+            return Err("--rpc-url and --cluster only apply without [clusters] in the config file".into());
+        }
+        self.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Overwrite config values with the flags that were given, and with the
+    // environment variables where the config file kept the default; RPC mode,
+    // proxy and timeout apply to every cluster, the RPC URLs and cluster name
+    // only without `[clusters]`
+    pub fn apply(&self, config: &mut Config) {
+        let defaults = Config::default();
+        let single = config.clusters.is_empty();
+        if single && !self.rpc_urls.is_empty() && self.overrides("rpc_urls", config.rpc.endpoints == defaults.rpc.endpoints) {
+            config.rpc.endpoints = self.rpc_urls.iter().map(|url| EndpointConfig::new(url)).collect();
+        }
+        if let Some(cluster) = self.cluster.as_ref().filter(|_| single) {
+            if self.overrides("cluster", config.cluster == defaults.cluster) {
+                config.cluster = cluster.clone();
+            }
+        }
+        for rpc in std::iter::once(&mut config.rpc).chain(config.clusters.values_mut()) {
+            if let Some(mode) = self.rpc_mode.filter(|_| self.overrides("rpc_mode", rpc.mode == defaults.rpc.mode)) {
+                rpc.mode = mode;
+            }
+            if let Some(proxy) = self.rpc_proxy.as_ref().filter(|_| self.overrides("rpc_proxy", rpc.proxy == defaults.rpc.proxy)) {
+                rpc.proxy = Some(proxy.clone());
+            }
+            if let Some(timeout) = self.timeout.filter(|_| self.overrides("timeout", rpc.timeout == defaults.rpc.timeout)) {
+                rpc.timeout = timeout;
+            }
+        }
+        if !self.listen.is_empty() && self.overrides("listen", config.server.listen == defaults.server.listen) {
+            config.server.listen = self.listen.clone();
+        }
+        if let Some(path) = self.metrics_path.as_ref().filter(|_| self.overrides("metrics_path", config.metrics.path == defaults.metrics.path)) {
+            config.metrics.path = path.clone();
+        }
+        if let Some(access_log) = self.access_log.filter(|_| self.overrides("access_log", config.server.access_log == defaults.server.access_log)) {
+            config.server.access_log = access_log;
+        }
+        if let Some(log_level) = self.log_level.filter(|_| self.overrides("log_level", config.log_level == defaults.log_level)) {
+            config.log_level = log_level;
+        }
+        if let Some(interval) = self.poll_interval.filter(|_| self.overrides("poll_interval", config.poll.interval == defaults.poll.interval)) {
+            config.poll.interval = interval;
+        }
+    }
+}
+
+fn parse_seconds(value: &str) -> Result<f64, String> {
+    let seconds: f64 = value.parse().map_err(|_| format!("`{}` is not a number", value))?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(format!("`{}` must be a non-negative number of seconds", value));
+    }
+    Ok(seconds)
+}
@@ -0,0 +1,54 @@
+// Commission changes seen between two polls. Rewards are paid with the
+// commission at the end of the epoch, so a validator that raises it just
+// before and lowers it again after takes most of its delegators' rewards
+// without showing a high commission most of the time; changes in the last
+// part of an epoch are counted separately for that.
+
+use log::info;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct Changes {
+    // Changes since the exporter started
+    pub total: u64,
+    // Those made after `metrics.commission_late_epoch` of an epoch had passed
+    pub late: u64,
+    // Commission before the last change
+    pub previous: u8,
+}
+
+#[derive(Default)]
+pub struct Tracker {
+    // Commission of each vote account at the last poll
+    last: HashMap<String, u8>,
+    // Every vote account that changed its commission, by vote pubkey
+    pub changes: HashMap<String, Changes>,
+}
+
+impl Tracker {
+    // Compare the commissions of a poll with the last one; `progress` is the
+    // epoch progress, from 0 to 1, when known
+    pub fn observe(&mut self, commissions: &HashMap<String, u8>, progress: Option<f64>, late_from: f64) {
+        for (vote_pubkey, &commission) in commissions {
+            let Some(&previous) = self.last.get(vote_pubkey) else {
+                continue;
+            };
+            if previous == commission {
+                continue;
+            }
+            let late = progress.is_some_and(|progress| progress >= late_from);
+            info!(
+                "Commission of {} changed from {}% to {}%{}",
+                vote_pubkey,
+                previous,
+                commission,
+                if late { " near the end of the epoch" } else { "" }
+            );
+            let changes = self.changes.entry(vote_pubkey.clone()).or_default();
+            changes.total += 1;
+            changes.late += late as u64;
+            changes.previous = previous;
+        }
+        self.last = commissions.clone();
+    }
+}
@@ -0,0 +1,1251 @@
+use serde::{Deserialize, Deserializer};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::lookup_host;
+use url::Url;
+
+use crate::exposition::{is_label_name, is_metric_name};
+use crate::rpc::host_label;
+use crate::sender::header_map;
+use crate::watchlist::WatchedValidator;
+
+// Labels the exporter puts on its own series, which `metrics.labels` cannot override
+const RESERVED_LABELS: &[&str] = &[
+    "cluster",
+    "vote_pubkey",
+    "identity",
+    "name",
+    "website",
+    "details",
+    "data_center",
+    "keybase_username",
+    "country",
+    "asn",
+    "as_org",
+    "status",
+    "epoch",
+    "version",
+    "client",
+    "port",
+    "criterion",
+    "top",
+    "endpoint",
+    "le",
+    "quantile",
+];
+
+// Exporter configuration, loaded from a TOML file. Every section and field is
+// optional; anything left out falls back to the values the exporter always used.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Name of the cluster `rpc` points at, used as the `cluster` label
+    pub cluster: String,
+    pub rpc: RpcConfig,
+    // Clusters monitored side by side, each with its own RPC settings and fetch
+    // loop; when any are configured `cluster` and `rpc` are not used
+    pub clusters: BTreeMap<String, RpcConfig>,
+    pub server: ServerConfig,
+    pub poll: PollConfig,
+    pub metrics: MetricsConfig,
+    pub validator_info: ValidatorInfoConfig,
+    pub validators_app: ValidatorsAppConfig,
+    pub stakewiz: StakewizConfig,
+    pub keybase: KeybaseConfig,
+    pub geoip: GeoIpConfig,
+    pub versions: VersionsConfig,
+    pub probe: ProbeConfig,
+    pub vote_fees: VoteFeesConfig,
+    pub block_production: BlockProductionConfig,
+    pub leader_schedule: LeaderScheduleConfig,
+    pub inflation_rewards: InflationRewardsConfig,
+    pub apy: ApyConfig,
+    pub supply: SupplyConfig,
+    pub sfdp: SfdpConfig,
+    pub jito: JitoConfig,
+    pub performance: PerformanceConfig,
+    // Most verbose messages logged, changeable at runtime through `/-/loglevel`
+    pub log_level: LogLevel,
+    // Watched validators, exported as `solana_watched_validator_*`
+    pub watchlist: Vec<WatchedValidator>,
+    // More of them, managed through `/api/v1/watchlist`
+    pub watchlist_file: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cluster: "mainnet-beta".to_string(),
+            rpc: RpcConfig::default(),
+            clusters: BTreeMap::new(),
+            server: ServerConfig::default(),
+            poll: PollConfig::default(),
+            metrics: MetricsConfig::default(),
+            validator_info: ValidatorInfoConfig::default(),
+            validators_app: ValidatorsAppConfig::default(),
+            stakewiz: StakewizConfig::default(),
+            keybase: KeybaseConfig::default(),
+            geoip: GeoIpConfig::default(),
+            versions: VersionsConfig::default(),
+            probe: ProbeConfig::default(),
+            vote_fees: VoteFeesConfig::default(),
+            block_production: BlockProductionConfig::default(),
+            leader_schedule: LeaderScheduleConfig::default(),
+            inflation_rewards: InflationRewardsConfig::default(),
+            apy: ApyConfig::default(),
+            supply: SupplyConfig::default(),
+            sfdp: SfdpConfig::default(),
+            jito: JitoConfig::default(),
+            performance: PerformanceConfig::default(),
+            log_level: LogLevel::Info,
+            watchlist: Vec::new(),
+            watchlist_file: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    // Also every poll and RPC request
+    Debug,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcConfig {
+    // Either a plain URL or a table with per-endpoint settings
+    #[serde(deserialize_with = "endpoint_list")]
+    pub endpoints: Vec<EndpointConfig>,
+    pub mode: RpcMode,
+    // Seconds to wait for `getVoteAccounts` before the fetch counts as timed out
+    pub timeout: f64,
+    // Extra HTTP headers sent to every endpoint, e.g. an API key
+    pub headers: BTreeMap<String, String>,
+    // Proxy for all RPC traffic: http://, https://, socks5:// or socks5h:// URL,
+    // credentials go into the URL. Without it the usual HTTPS_PROXY/ALL_PROXY
+    // environment variables apply.
+    pub proxy: Option<String>,
+    // Seconds to stop using an endpoint that answered with a rate-limit error
+    // without a Retry-After header
+    pub rate_limit_pause: f64,
+    // Seconds between two `getHealth` requests to each endpoint, 0 for none
+    pub health_interval: f64,
+    // Seconds between two `getSlot` requests to all endpoints, 0 for none
+    pub slot_interval: f64,
+    // Endpoints whose vote accounts are fetched and reconciled every poll
+    pub quorum: usize,
+    // Credits by which the endpoints of a quorum may differ on a validator
+    // before it counts as a disagreement
+    pub quorum_tolerance: u64,
+    pub circuit_breaker: CircuitBreakerConfig,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: vec![EndpointConfig::new("https://api.mainnet-beta.solana.com")],
+            mode: RpcMode::Failover,
+            timeout: 4.5,
+            headers: BTreeMap::new(),
+            proxy: None,
+            rate_limit_pause: 10.0,
+            health_interval: 0.0,
+            slot_interval: 0.0,
+            quorum: 1,
+            quorum_tolerance: 1000,
+            circuit_breaker: CircuitBreakerConfig::default(),
+        }
+    }
+}
+
+// Stops requests to an endpoint that keeps failing for a while
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    // Consecutive failures that open the breaker, 0 disables it
+    pub failure_threshold: u32,
+    // Seconds an open breaker waits before letting a probe request through
+    pub cooldown: f64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: 30.0,
+        }
+    }
+}
+
+// How the endpoint for each poll is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RpcMode {
+    // Always start with the first endpoint, moving down the list on failure
+    Failover,
+    // Start with the next endpoint on every poll to spread the load, still
+    // moving on to the others on failure
+    RoundRobin,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointConfig {
+    pub url: String,
+    // Minimum seconds between two requests to this endpoint, for providers with
+    // rate limits; 0 means no limit
+    #[serde(default)]
+    pub min_interval: f64,
+    // Extra HTTP headers for this endpoint only, taking precedence over `rpc.headers`;
+    // providers that take the API key in the URL just need it in `url`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl EndpointConfig {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            min_interval: 0.0,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+fn endpoint_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<EndpointConfig>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Entry {
+        Url(String),
+        Table(EndpointConfig),
+    }
+
+    let entries = Vec::<Entry>::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            Entry::Url(url) => EndpointConfig::new(&url),
+            Entry::Table(endpoint) => endpoint,
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    // One address or a list, IPv4 or IPv6 ("[::1]:59872"); all serve the same metrics
+    #[serde(deserialize_with = "listen_list")]
+    pub listen: Vec<SocketAddr>,
+    // Serve HTTPS instead of plain HTTP
+    pub tls: Option<TlsConfig>,
+    pub auth: AuthConfig,
+    pub limits: LimitsConfig,
+    pub access_log: AccessLog,
+    // Address for the gRPC API (HTTP/2, over `tls` when set), off when not set
+    pub grpc_listen: Option<SocketAddr>,
+}
+
+// Which HTTP requests are logged, one line each with method, path, status,
+// latency and client address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccessLog {
+    #[default]
+    Off,
+    // Only requests answered with a 4xx or 5xx status
+    Errors,
+    All,
+}
+
+// Bounds on what clients can hold on to, so a misbehaving scraper or a slow
+// client cannot exhaust the exporter
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    // Open connections across all listeners; new ones beyond it are closed right away, 0 for no limit
+    pub max_connections: usize,
+    // Seconds a client gets to send the request headers and the exporter to answer
+    pub request_timeout: f64,
+    // Scrapes rendered at the same time; more get 503 until one finishes, 0 for no limit
+    pub max_in_flight_scrapes: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 100,
+            request_timeout: 10.0,
+            max_in_flight_scrapes: 10,
+        }
+    }
+}
+
+// Credentials required on the metrics paths; either one is accepted when both
+// are set. Health checks stay open for probes.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    // Expected in `Authorization: Bearer <token>`
+    pub bearer_token: Option<String>,
+    // htpasswd file of users for basic auth, `{SHA}` hashes only
+    pub htpasswd: Option<PathBuf>,
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.bearer_token.is_some() || self.htpasswd.is_some()
+    }
+}
+
+// PEM files of the HTTPS listener, reloaded when they change
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    // Certificate chain, leaf first
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    // CA bundle for client certificates; when set, clients without a valid one are rejected
+    #[serde(default)]
+    pub client_ca: Option<PathBuf>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen: vec![([127, 0, 0, 1], 59872).into()],
+            tls: None,
+            auth: AuthConfig::default(),
+            limits: LimitsConfig::default(),
+            access_log: AccessLog::Off,
+            grpc_listen: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PollConfig {
+    // Seconds between the start of two polls, independent of how long a poll takes
+    pub interval: f64,
+    pub backoff: BackoffConfig,
+    pub adaptive: AdaptivePollConfig,
+    // Seconds the fetch loop may overrun a step before the watchdog restarts it
+    pub watchdog_grace: f64,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            interval: 5.0,
+            backoff: BackoffConfig::default(),
+            adaptive: AdaptivePollConfig::default(),
+            watchdog_grace: 30.0,
+        }
+    }
+}
+
+impl PollConfig {
+    // Poll period for the given epoch progress (0.0 to 1.0); without adaptive
+    // polling, or before the epoch is known, it is always `interval`
+    pub fn period(&self, epoch_progress: Option<f64>) -> Duration {
+        let seconds = match epoch_progress {
+            Some(progress) if self.adaptive.enabled => {
+                if progress >= 1.0 - self.adaptive.epoch_end_fraction {
+                    self.adaptive.epoch_end_interval
+                } else {
+                    self.adaptive.mid_epoch_interval
+                }
+            }
+            _ => self.interval,
+        };
+        Duration::from_secs_f64(seconds)
+    }
+}
+
+// Poll faster towards the end of an epoch, where rank battles are decided,
+// and slower mid-epoch to save RPC requests
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptivePollConfig {
+    pub enabled: bool,
+    // Trailing fraction of the epoch polled with `epoch_end_interval` (0.1 = last 10%)
+    pub epoch_end_fraction: f64,
+    // Seconds between polls near the end of the epoch
+    pub epoch_end_interval: f64,
+    // Seconds between polls for the rest of the epoch
+    pub mid_epoch_interval: f64,
+}
+
+impl Default for AdaptivePollConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            epoch_end_fraction: 0.1,
+            epoch_end_interval: 2.0,
+            mid_epoch_interval: 30.0,
+        }
+    }
+}
+
+// Delay between polls while every RPC endpoint is failing
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackoffConfig {
+    // Seconds to wait after the first failed poll
+    pub initial: f64,
+    // Factor the delay grows by with every further failed poll
+    pub multiplier: f64,
+    // Upper bound for the delay, in seconds
+    pub max: f64,
+    // Random spread applied to every delay, as a fraction of it (0.2 = +/-20%)
+    pub jitter: f64,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial: 2.0,
+            multiplier: 2.0,
+            max: 60.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    // HTTP path the Prometheus metrics are served on
+    pub path: String,
+    // Upper bounds, in seconds, of the RPC response time histogram buckets
+    pub rpc_duration_buckets: Vec<f64>,
+    // Seconds after the last successful poll from which the data counts as stale, 0 to never
+    pub stale_after: f64,
+    pub stale_action: StaleAction,
+    // gzip level (1 = fastest, 9 = smallest) for scrapers that accept it, 0 to never compress
+    pub gzip_level: u32,
+    // Export the rank, credits, distances, commission and stake of every
+    // validator; false leaves only the cluster aggregates and the watched validators
+    pub per_validator: bool,
+    // Most validators exported one by one, the best ranked plus the watched ones; 0 for no limit
+    pub max_validators: usize,
+    // Replaces the `solana_validator_` at the start of metric names, so
+    // several exporters can share a Prometheus
+    pub prefix: String,
+    // Constant labels added to every series, e.g. `region = "eu"`
+    pub labels: BTreeMap<String, String>,
+    // Ranks whose credits are exported as `solana_validator_top_<rank>`
+    pub top_ranks: Vec<usize>,
+    // Ranks whose credits watched validators are compared against
+    pub gap_ranks: Vec<usize>,
+    // Share of the lowest and of the highest credits left out of the trimmed mean
+    pub trim_fraction: f64,
+    // Upper bounds of the buckets of the histogram of credits earned this epoch
+    pub credits_buckets: Vec<f64>,
+    // Credits above or below a watched validator within which others count as competitors
+    pub competitor_window: u64,
+    // Epoch progress (0 to 1) from which a commission change counts as late,
+    // when it still decides the commission the epoch's rewards are paid with
+    pub commission_late_epoch: f64,
+    // Count delinquent validators that earned credits this epoch in
+    // `solana_validator_active`
+    pub active_includes_delinquent: bool,
+    // Also export the validators that earned no credits this epoch, ranked
+    // after those that did
+    pub include_zero_credit: bool,
+    // Also export the credits of the earlier epochs `getVoteAccounts` returns
+    // (up to four), one series per validator and epoch
+    pub epoch_history: bool,
+}
+
+// What `/metrics` does once the data is stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StaleAction {
+    // Keep serving the data, with `solana_exporter_data_stale` set to 1
+    Gauge,
+    // Answer 503 Service Unavailable so the scrape fails
+    Unavailable,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            path: "/metrics".to_string(),
+            rpc_duration_buckets: vec![0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 4.5, 10.0],
+            stale_after: 300.0,
+            stale_action: StaleAction::Gauge,
+            gzip_level: 6,
+            per_validator: true,
+            max_validators: 0,
+            prefix: "solana_validator_".to_string(),
+            labels: BTreeMap::new(),
+            top_ranks: vec![1, 100, 200],
+            gap_ranks: vec![1, 100],
+            trim_fraction: 0.1,
+            competitor_window: 10_000,
+            commission_late_epoch: 0.9,
+            active_includes_delinquent: false,
+            include_zero_credit: false,
+            epoch_history: false,
+            credits_buckets: vec![100_000.0, 500_000.0, 1_000_000.0, 2_000_000.0, 3_000_000.0, 4_000_000.0, 5_000_000.0, 6_000_000.0, 7_000_000.0],
+        }
+    }
+}
+
+// Names published on chain, added to the validators' series as `name`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidatorInfoConfig {
+    pub enabled: bool,
+    // Seconds between two fetches of the validator info accounts
+    pub refresh_interval: f64,
+}
+
+impl Default for ValidatorInfoConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval: 3600.0,
+        }
+    }
+}
+
+// Names, data centers and scores from validators.app, which needs an API key
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidatorsAppConfig {
+    pub enabled: bool,
+    pub api_key: Option<String>,
+    // Base URL of the API
+    pub url: String,
+    // Seconds between two fetches of the validator list
+    pub refresh_interval: f64,
+    // Seconds a request may take
+    pub timeout: f64,
+    // Where the lists are kept between fetches and restarts
+    pub cache_dir: Option<PathBuf>,
+    // validators.app network of each cluster, for clusters not named
+    // mainnet-beta or testnet
+    pub networks: BTreeMap<String, String>,
+}
+
+impl Default for ValidatorsAppConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_key: None,
+            url: "https://www.validators.app/api/v1".to_string(),
+            refresh_interval: 21600.0,
+            timeout: 30.0,
+            cache_dir: None,
+            networks: BTreeMap::new(),
+        }
+    }
+}
+
+impl ValidatorsAppConfig {
+    pub fn network(&self, cluster: &str) -> Option<&str> {
+        match self.networks.get(cluster) {
+            Some(network) => Some(network),
+            None => match cluster {
+                "mainnet-beta" => Some("mainnet"),
+                "testnet" => Some("testnet"),
+                _ => None,
+            },
+        }
+    }
+}
+
+// Stakewiz scores of the watched validators
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StakewizConfig {
+    pub enabled: bool,
+    // Base URL of the API
+    pub url: String,
+    // The cluster whose watched validators are looked up; Stakewiz only covers mainnet
+    pub cluster: String,
+    // Seconds between two fetches of a validator's score
+    pub refresh_interval: f64,
+    // Seconds a request may take
+    pub timeout: f64,
+}
+
+impl Default for StakewizConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "https://api.stakewiz.com".to_string(),
+            cluster: "mainnet-beta".to_string(),
+            refresh_interval: 3600.0,
+            timeout: 10.0,
+        }
+    }
+}
+
+// Verification of the Keybase usernames in the validators' on-chain info
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeybaseConfig {
+    pub enabled: bool,
+    // Where users are looked up
+    pub api_url: String,
+    // Where the users' public folders are served
+    pub proof_url: String,
+    // Seconds until a username is checked again
+    pub refresh_interval: f64,
+    // Seconds a request may take
+    pub timeout: f64,
+}
+
+impl Default for KeybaseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_url: "https://keybase.io".to_string(),
+            proof_url: "https://keybase.pub".to_string(),
+            refresh_interval: 86400.0,
+            timeout: 10.0,
+        }
+    }
+}
+
+// Country and network of the validators' gossip addresses, from MaxMind
+// databases (GeoLite2 or GeoIP2); used when at least one is set
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeoIpConfig {
+    // A Country or City database
+    pub country_database: Option<PathBuf>,
+    // An ASN database
+    pub asn_database: Option<PathBuf>,
+    // Seconds between two fetches of the gossip addresses
+    pub refresh_interval: f64,
+}
+
+impl Default for GeoIpConfig {
+    fn default() -> Self {
+        Self {
+            country_database: None,
+            asn_database: None,
+            refresh_interval: 600.0,
+        }
+    }
+}
+
+impl GeoIpConfig {
+    pub fn enabled(&self) -> bool {
+        self.country_database.is_some() || self.asn_database.is_some()
+    }
+}
+
+// Validators and stake per software version their nodes advertise in gossip
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VersionsConfig {
+    pub enabled: bool,
+    // Seconds between two fetches of the cluster nodes
+    pub refresh_interval: f64,
+}
+
+impl Default for VersionsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval: 600.0,
+        }
+    }
+}
+
+// Reachability of the ports the watched validators advertise in gossip
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProbeConfig {
+    pub enabled: bool,
+    // Seconds between two rounds of probes, also of the cluster nodes fetches
+    pub interval: f64,
+    // Seconds a probe waits for a connection or an ICMP error
+    pub timeout: f64,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 60.0,
+            timeout: 2.0,
+        }
+    }
+}
+
+// Leader slots and blocks produced this epoch, from `getBlockProduction`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlockProductionConfig {
+    pub enabled: bool,
+    // Seconds between two fetches, the request covers the whole epoch so far
+    pub refresh_interval: f64,
+}
+
+impl Default for BlockProductionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval: 60.0,
+        }
+    }
+}
+
+// Leader slots of the watched validators, from `getLeaderSchedule` once per epoch
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeaderScheduleConfig {
+    pub enabled: bool,
+}
+
+// Rewards of the watched vote accounts, from `getInflationReward` once per epoch
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InflationRewardsConfig {
+    pub enabled: bool,
+}
+
+// Staking APY of the watched validators, from the inflation rate and supply
+// fetched once per epoch
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApyConfig {
+    pub enabled: bool,
+}
+
+// Supply and inflation rate of the cluster, from `getSupply` and `getInflationRate`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SupplyConfig {
+    pub enabled: bool,
+    // Seconds between two fetches, and after each epoch change
+    pub refresh_interval: f64,
+}
+
+impl Default for SupplyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval: 600.0,
+        }
+    }
+}
+
+// Whether the watched validators meet delegation program style criteria
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SfdpConfig {
+    pub enabled: bool,
+    // Quantile of the active validators' credits this epoch a validator must
+    // reach, 0 to 1
+    pub credits_quantile: f64,
+    // Highest commission that passes, in percent
+    pub max_commission: u8,
+    // Seconds a validator may have been delinquent for and still pass
+    pub max_delinquency: f64,
+}
+
+impl Default for SfdpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            credits_quantile: 0.1,
+            max_commission: 10,
+            max_delinquency: 0.0,
+        }
+    }
+}
+
+// MEV commission and tips of the watched validators, from their Jito tip
+// distribution accounts
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JitoConfig {
+    pub enabled: bool,
+    // Tip distribution program; the default is the one on mainnet
+    pub program_id: String,
+    // Seconds between two fetches of the accounts
+    pub refresh_interval: f64,
+}
+
+impl Default for JitoConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            program_id: "4R3gSG8BpU4t19KYj8CfnbtRpnT8gtk4dvTHxVRwc2r7".to_string(),
+            refresh_interval: 60.0,
+        }
+    }
+}
+
+// Transactions and slots per second of the cluster, from `getRecentPerformanceSamples`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PerformanceConfig {
+    pub enabled: bool,
+    // Seconds between two fetches; the RPC node takes a sample every minute
+    pub refresh_interval: f64,
+    // Samples averaged, the most recent ones (up to 720)
+    pub samples: usize,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval: 60.0,
+            samples: 5,
+        }
+    }
+}
+
+// What voting costs the watched validators' identities, from `getFeeForMessage`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VoteFeesConfig {
+    pub enabled: bool,
+    // Seconds between two fetches of the fee; it rarely changes
+    pub refresh_interval: f64,
+}
+
+impl Default for VoteFeesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval: 600.0,
+        }
+    }
+}
+
+fn listen_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<SocketAddr>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Listen {
+        One(SocketAddr),
+        Many(Vec<SocketAddr>),
+    }
+
+    Ok(match Listen::deserialize(deserializer)? {
+        Listen::One(addr) => vec![addr],
+        Listen::Many(addrs) => addrs,
+    })
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read config file {}: {}", path.display(), e))?;
+        let config = toml::from_str(&contents)
+            .map_err(|e| format!("failed to parse config file {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    // Reject settings the exporter cannot run with, listing every problem found
+    pub fn validate(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut problems = Vec::new();
+
+        for (name, rpc) in self.clusters() {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                problems.push(format!("clusters: {:?} is not a valid cluster name, use letters, digits, '-' and '_'", name));
+            }
+            let section = if self.clusters.is_empty() { "rpc".to_string() } else { format!("clusters.{}", name) };
+            validate_rpc(&section, rpc, &mut problems);
+        }
+        if self.server.listen.is_empty() {
+            problems.push("server.listen: at least one address is required".to_string());
+        }
+        for (i, addr) in self.server.listen.iter().enumerate() {
+            if self.server.listen[..i].contains(addr) {
+                problems.push(format!("server.listen: {} is listed twice", addr));
+            }
+        }
+        if let Some(addr) = self.server.grpc_listen {
+            if self.server.listen.contains(&addr) {
+                problems.push(format!("server.grpc_listen: {} is already in server.listen", addr));
+            }
+        }
+        if let Some(tls) = &self.server.tls {
+            if let Err(e) = crate::tls::load(tls) {
+                problems.push(format!("server.tls: {}", e));
+            }
+        }
+        if self.server.auth.bearer_token.as_deref().is_some_and(|token| token.trim().is_empty()) {
+            problems.push("server.auth.bearer_token: must not be empty".to_string());
+        }
+        if let Some(path) = &self.server.auth.htpasswd {
+            match crate::auth::load_htpasswd(path) {
+                Ok(users) if users.is_empty() => problems.push(format!("server.auth.htpasswd: {} has no users", path.display())),
+                Ok(_) => {}
+                Err(e) => problems.push(format!("server.auth.htpasswd: {}", e)),
+            }
+        }
+        if let Err(e) = crate::watchlist::check(&self.watchlist) {
+            problems.push(format!("watchlist: {}", e));
+        }
+        if let Some(path) = &self.watchlist_file {
+            if let Err(e) = crate::watchlist::load(path) {
+                problems.push(format!("watchlist_file: {}", e));
+            }
+        }
+        let request_timeout = self.server.limits.request_timeout;
+        if !request_timeout.is_finite() || request_timeout <= 0.0 {
+            problems.push(format!("server.limits.request_timeout: must be a positive number of seconds, got {}", request_timeout));
+        }
+        if !self.poll.interval.is_finite() || self.poll.interval <= 0.0 {
+            problems.push(format!("poll.interval: must be a positive number of seconds, got {}", self.poll.interval));
+        }
+        if !self.poll.watchdog_grace.is_finite() || self.poll.watchdog_grace <= 0.0 {
+            problems.push(format!("poll.watchdog_grace: must be a positive number of seconds, got {}", self.poll.watchdog_grace));
+        }
+        let adaptive = &self.poll.adaptive;
+        if !(0.0..=1.0).contains(&adaptive.epoch_end_fraction) {
+            problems.push(format!("poll.adaptive.epoch_end_fraction: must be in [0, 1], got {}", adaptive.epoch_end_fraction));
+        }
+        if !adaptive.epoch_end_interval.is_finite() || adaptive.epoch_end_interval <= 0.0 {
+            problems.push(format!("poll.adaptive.epoch_end_interval: must be a positive number of seconds, got {}", adaptive.epoch_end_interval));
+        }
+        if !adaptive.mid_epoch_interval.is_finite() || adaptive.mid_epoch_interval <= 0.0 {
+            problems.push(format!("poll.adaptive.mid_epoch_interval: must be a positive number of seconds, got {}", adaptive.mid_epoch_interval));
+        }
+        let backoff = &self.poll.backoff;
+        if !backoff.initial.is_finite() || backoff.initial <= 0.0 {
+            problems.push(format!("poll.backoff.initial: must be a positive number of seconds, got {}", backoff.initial));
+        }
+        if !backoff.multiplier.is_finite() || backoff.multiplier < 1.0 {
+            problems.push(format!("poll.backoff.multiplier: must be at least 1, got {}", backoff.multiplier));
+        }
+        if !backoff.max.is_finite() || backoff.max < backoff.initial {
+            problems.push(format!("poll.backoff.max: must be at least poll.backoff.initial, got {}", backoff.max));
+        }
+        if !(0.0..1.0).contains(&backoff.jitter) {
+            problems.push(format!("poll.backoff.jitter: must be in [0, 1), got {}", backoff.jitter));
+        }
+        if !self.metrics.path.starts_with('/') {
+            problems.push(format!("metrics.path: must start with '/', got {:?}", self.metrics.path));
+        }
+        if ["/healthz", "/readyz", "/status", "/stream", "/ws", "/graphql", "/export.csv", "/api"].contains(&self.metrics.path.as_str()) || self.metrics.path.starts_with("/api/") || self.metrics.path.starts_with("/-/") {
+            problems.push(format!("metrics.path: {} is reserved for another endpoint", self.metrics.path));
+        }
+        if let Err(e) = check_buckets(&self.metrics.rpc_duration_buckets) {
+            problems.push(format!("metrics.rpc_duration_buckets: {}", e));
+        }
+        if let Err(e) = check_buckets(&self.metrics.credits_buckets) {
+            problems.push(format!("metrics.credits_buckets: {}", e));
+        }
+        if !is_metric_name(&self.metrics.prefix) {
+            problems.push(format!("metrics.prefix: {:?} is not a valid start of a metric name", self.metrics.prefix));
+        }
+        for name in self.metrics.labels.keys() {
+            if !is_label_name(name) {
+                problems.push(format!("metrics.labels: {:?} is not a valid label name", name));
+            } else if RESERVED_LABELS.contains(&name.as_str()) {
+                problems.push(format!("metrics.labels: {} is already set by the exporter", name));
+            }
+        }
+        if self.metrics.gzip_level > 9 {
+            problems.push(format!("metrics.gzip_level: must be between 0 and 9, got {}", self.metrics.gzip_level));
+        }
+        if let Err(e) = check_ranks(&self.metrics.top_ranks) {
+            problems.push(format!("metrics.top_ranks: {}", e));
+        }
+        if let Err(e) = check_ranks(&self.metrics.gap_ranks) {
+            problems.push(format!("metrics.gap_ranks: {}", e));
+        }
+        if !(0.0..0.5).contains(&self.metrics.trim_fraction) {
+            problems.push(format!("metrics.trim_fraction: must be in [0, 0.5), got {}", self.metrics.trim_fraction));
+        }
+        if !(0.0..=1.0).contains(&self.metrics.commission_late_epoch) {
+            problems.push(format!("metrics.commission_late_epoch: must be in [0, 1], got {}", self.metrics.commission_late_epoch));
+        }
+        if !self.metrics.stale_after.is_finite() || self.metrics.stale_after < 0.0 {
+            problems.push(format!("metrics.stale_after: must be a non-negative number of seconds, got {}", self.metrics.stale_after));
+        }
+        if !self.validator_info.refresh_interval.is_finite() || self.validator_info.refresh_interval <= 0.0 {
+            problems.push(format!(
+                "validator_info.refresh_interval: must be a positive number of seconds, got {}",
+                self.validator_info.refresh_interval
+            ));
+        }
+        let app = &self.validators_app;
+        if app.enabled {
+            if app.api_key.as_deref().is_none_or(|key| key.trim().is_empty()) {
+                problems.push("validators_app.api_key: required when validators_app is enabled".to_string());
+            }
+            if !matches!(Url::parse(&app.url), Ok(url) if url.scheme() == "http" || url.scheme() == "https") {
+                problems.push(format!("validators_app.url: {:?} is not an http(s) URL", app.url));
+            }
+            if !app.refresh_interval.is_finite() || app.refresh_interval <= 0.0 {
+                problems.push(format!("validators_app.refresh_interval: must be a positive number of seconds, got {}", app.refresh_interval));
+            }
+            if !app.timeout.is_finite() || app.timeout <= 0.0 {
+                problems.push(format!("validators_app.timeout: must be a positive number of seconds, got {}", app.timeout));
+            }
+            for (cluster, _) in self.clusters() {
+                if app.network(cluster).is_none() {
+                    problems.push(format!("validators_app.networks: no validators.app network for cluster {}", cluster));
+                }
+            }
+        }
+        let stakewiz = &self.stakewiz;
+        if stakewiz.enabled {
+            if !matches!(Url::parse(&stakewiz.url), Ok(url) if url.scheme() == "http" || url.scheme() == "https") {
+                problems.push(format!("stakewiz.url: {:?} is not an http(s) URL", stakewiz.url));
+            }
+            if self.cluster_rpc(&stakewiz.cluster).is_none() {
+                problems.push(format!("stakewiz.cluster: {} is not a configured cluster", stakewiz.cluster));
+            }
+            if !stakewiz.refresh_interval.is_finite() || stakewiz.refresh_interval <= 0.0 {
+                problems.push(format!("stakewiz.refresh_interval: must be a positive number of seconds, got {}", stakewiz.refresh_interval));
+            }
+            if !stakewiz.timeout.is_finite() || stakewiz.timeout <= 0.0 {
+                problems.push(format!("stakewiz.timeout: must be a positive number of seconds, got {}", stakewiz.timeout));
+            }
+        }
+        let keybase = &self.keybase;
+        if keybase.enabled {
+            if !self.validator_info.enabled {
+                problems.push("keybase: needs validator_info enabled, the usernames come from there".to_string());
+            }
+            for (name, url) in [("api_url", &keybase.api_url), ("proof_url", &keybase.proof_url)] {
+                if !matches!(Url::parse(url), Ok(url) if url.scheme() == "http" || url.scheme() == "https") {
+                    problems.push(format!("keybase.{}: {:?} is not an http(s) URL", name, url));
+                }
+            }
+            if !keybase.refresh_interval.is_finite() || keybase.refresh_interval <= 0.0 {
+                problems.push(format!("keybase.refresh_interval: must be a positive number of seconds, got {}", keybase.refresh_interval));
+            }
+            if !keybase.timeout.is_finite() || keybase.timeout <= 0.0 {
+                problems.push(format!("keybase.timeout: must be a positive number of seconds, got {}", keybase.timeout));
+            }
+        }
+        let geoip = &self.geoip;
+        for (name, path) in [("country_database", &geoip.country_database), ("asn_database", &geoip.asn_database)] {
+            if let Some(path) = path.as_ref().filter(|path| !path.is_file()) {
+                problems.push(format!("geoip.{}: {} is not a file", name, path.display()));
+            }
+        }
+        if geoip.enabled() && (!geoip.refresh_interval.is_finite() || geoip.refresh_interval <= 0.0) {
+            problems.push(format!("geoip.refresh_interval: must be a positive number of seconds, got {}", geoip.refresh_interval));
+        }
+        let performance = &self.performance;
+        if performance.enabled {
+            if !performance.refresh_interval.is_finite() || performance.refresh_interval <= 0.0 {
+                problems.push(format!(
+                    "performance.refresh_interval: must be a positive number of seconds, got {}",
+                    performance.refresh_interval
+                ));
+            }
+            if !(1..=720).contains(&performance.samples) {
+                problems.push(format!("performance.samples: must be from 1 to 720, got {}", performance.samples));
+            }
+        }
+        let sfdp = &self.sfdp;
+        if sfdp.enabled {
+            if !(0.0..=1.0).contains(&sfdp.credits_quantile) {
+                problems.push(format!("sfdp.credits_quantile: must be from 0 to 1, got {}", sfdp.credits_quantile));
+            }
+            if sfdp.max_commission > 100 {
+                problems.push(format!("sfdp.max_commission: must be a percentage from 0 to 100, got {}", sfdp.max_commission));
+            }
+            if !sfdp.max_delinquency.is_finite() || sfdp.max_delinquency < 0.0 {
+                problems.push(format!("sfdp.max_delinquency: must be a number of seconds, 0 or more, got {}", sfdp.max_delinquency));
+            }
+        }
+        let jito = &self.jito;
+        if jito.enabled {
+            if Pubkey::from_str(&jito.program_id).is_err() {
+                problems.push(format!("jito.program_id: {:?} is not a valid base58 pubkey", jito.program_id));
+            }
+            if !jito.refresh_interval.is_finite() || jito.refresh_interval <= 0.0 {
+                problems.push(format!("jito.refresh_interval: must be a positive number of seconds, got {}", jito.refresh_interval));
+            }
+        }
+        let supply = &self.supply;
+        if supply.enabled && (!supply.refresh_interval.is_finite() || supply.refresh_interval <= 0.0) {
+            problems.push(format!("supply.refresh_interval: must be a positive number of seconds, got {}", supply.refresh_interval));
+        }
+        let vote_fees = &self.vote_fees;
+        if vote_fees.enabled && (!vote_fees.refresh_interval.is_finite() || vote_fees.refresh_interval <= 0.0) {
+            problems.push(format!("vote_fees.refresh_interval: must be a positive number of seconds, got {}", vote_fees.refresh_interval));
+        }
+        let versions = &self.versions;
+        if versions.enabled && (!versions.refresh_interval.is_finite() || versions.refresh_interval <= 0.0) {
+            problems.push(format!("versions.refresh_interval: must be a positive number of seconds, got {}", versions.refresh_interval));
+        }
+        let probe = &self.probe;
+        if probe.enabled {
+            if !probe.interval.is_finite() || probe.interval <= 0.0 {
+                problems.push(format!("probe.interval: must be a positive number of seconds, got {}", probe.interval));
+            }
+            if !probe.timeout.is_finite() || probe.timeout <= 0.0 {
+                problems.push(format!("probe.timeout: must be a positive number of seconds, got {}", probe.timeout));
+            }
+        }
+        let production = &self.block_production;
+        if production.enabled && (!production.refresh_interval.is_finite() || production.refresh_interval <= 0.0) {
+            problems.push(format!(
+                "block_production.refresh_interval: must be a positive number of seconds, got {}",
+                production.refresh_interval
+            ));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("invalid configuration:\n  - {}", problems.join("\n  - ")).into())
+        }
+    }
+
+    // The monitored clusters by name: `clusters` when configured, otherwise `rpc` as `cluster`
+    pub fn clusters(&self) -> Vec<(&str, &RpcConfig)> {
+        if self.clusters.is_empty() {
+            vec![(self.cluster.as_str(), &self.rpc)]
+        } else {
+            self.clusters.iter().map(|(name, rpc)| (name.as_str(), rpc)).collect()
+        }
+    }
+
+    pub fn cluster_rpc(&self, name: &str) -> Option<&RpcConfig> {
+        self.clusters().into_iter().find(|(cluster, _)| *cluster == name).map(|(_, rpc)| rpc)
+    }
+
+    // Seconds between two fetches of the cluster nodes, the shortest interval of
+    // the sections using them; None when none of them is enabled
+    pub fn gossip_interval(&self) -> Option<f64> {
+        let geoip = self.geoip.enabled().then_some(self.geoip.refresh_interval);
+        let versions = self.versions.enabled.then_some(self.versions.refresh_interval);
+        let probe = self.probe.enabled.then_some(self.probe.interval);
+        geoip.into_iter().chain(versions).chain(probe).reduce(f64::min)
+    }
+
+    // Resolve the host of every RPC endpoint, returning one message per failure
+    pub async fn check_endpoints(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (name, rpc) in self.clusters() {
+            let section = if self.clusters.is_empty() { "rpc".to_string() } else { format!("clusters.{}", name) };
+            problems.extend(check_cluster_endpoints(&section, rpc).await);
+        }
+        problems
+    }
+}
+
+async fn check_cluster_endpoints(section: &str, rpc: &RpcConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (i, endpoint) in rpc.endpoints.iter().enumerate() {
+        let Ok(url) = parse_rpc_url(&endpoint.url) else {
+            continue;
+        };
+        let host = url.host_str().unwrap_or_default().to_string();
+        let port = url.port_or_known_default().unwrap_or(443);
+        match lookup_host((host.as_str(), port)).await.map(|addrs| addrs.count()) {
+            Ok(0) => problems.push(format!("{}.endpoints[{}]: {} resolves to no addresses", section, i, host)),
+            Ok(_) => {}
+            Err(e) => problems.push(format!("{}.endpoints[{}]: cannot resolve {}: {}", section, i, host, e)),
+        }
+    }
+    problems
+}
+
+// Checks of one cluster's RPC settings, reported under `section`
+fn validate_rpc(section: &str, rpc: &RpcConfig, problems: &mut Vec<String>) {
+    if rpc.endpoints.is_empty() {
+        problems.push(format!("{}.endpoints: at least one endpoint is required", section));
+    }
+    for (i, endpoint) in rpc.endpoints.iter().enumerate() {
+        if let Err(e) = parse_rpc_url(&endpoint.url) {
+            problems.push(format!("{}.endpoints[{}].url: {}", section, i, e));
+        }
+        if !endpoint.min_interval.is_finite() || endpoint.min_interval < 0.0 {
+            problems.push(format!("{}.endpoints[{}].min_interval: must be a non-negative number of seconds, got {}", section, i, endpoint.min_interval));
+        }
+        if let Err(e) = header_map(&endpoint.headers) {
+            problems.push(format!("{}.endpoints[{}].headers: {}", section, i, e));
+        }
+    }
+    if let Err(e) = header_map(&rpc.headers) {
+        problems.push(format!("{}.headers: {}", section, e));
+    }
+    if let Some(proxy) = &rpc.proxy {
+        if let Err(e) = parse_proxy_url(proxy) {
+            problems.push(format!("{}.proxy: {}", section, e));
+        }
+    }
+    if !rpc.timeout.is_finite() || rpc.timeout <= 0.0 {
+        problems.push(format!("{}.timeout: must be a positive number of seconds, got {}", section, rpc.timeout));
+    }
+    if !rpc.rate_limit_pause.is_finite() || rpc.rate_limit_pause < 0.0 {
+        problems.push(format!("{}.rate_limit_pause: must be a non-negative number of seconds, got {}", section, rpc.rate_limit_pause));
+    }
+    if !rpc.health_interval.is_finite() || rpc.health_interval < 0.0 {
+        problems.push(format!("{}.health_interval: must be a non-negative number of seconds, got {}", section, rpc.health_interval));
+    }
+    if !rpc.slot_interval.is_finite() || rpc.slot_interval < 0.0 {
+        problems.push(format!("{}.slot_interval: must be a non-negative number of seconds, got {}", section, rpc.slot_interval));
+    }
+    if rpc.quorum == 0 || rpc.quorum > rpc.endpoints.len() {
+        problems.push(format!("{}.quorum: must be from 1 to the number of endpoints ({}), got {}", section, rpc.endpoints.len(), rpc.quorum));
+    }
+    if !rpc.circuit_breaker.cooldown.is_finite() || rpc.circuit_breaker.cooldown < 0.0 {
+        problems.push(format!("{}.circuit_breaker.cooldown: must be a non-negative number of seconds, got {}", section, rpc.circuit_breaker.cooldown));
+    }
+}
+
+fn check_buckets(bounds: &[f64]) -> Result<(), String> {
+    if bounds.iter().any(|bound| !bound.is_finite()) {
+        return Err("bucket bounds must be finite numbers, +Inf is added automatically".to_string());
+    }
+    if bounds.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err("bucket bounds must be strictly increasing".to_string());
+    }
+    Ok(())
+}
+
+fn check_ranks(ranks: &[usize]) -> Result<(), String> {
+    if ranks.contains(&0) {
+        return Err("ranks start at 1".to_string());
+    }
+    if let Some(twice) = ranks.iter().enumerate().find_map(|(i, rank)| ranks[..i].contains(rank).then_some(rank)) {
+        return Err(format!("rank {} is listed twice", twice));
+    }
+    Ok(())
+}
+
+fn parse_proxy_url(value: &str) -> Result<(), String> {
+    // Only report the scheme: proxy URLs often carry credentials
+    let url = Url::parse(value).map_err(|e| format!("not a valid URL: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(format!("unsupported scheme {:?}, use http, https, socks5 or socks5h", url.scheme()));
+    }
+    reqwest::Proxy::all(value).map_err(|e| format!("not a usable proxy: {}", e))?;
+    Ok(())
+}
+
+// Only report the scheme and host, as `endpoint_label` does: RPC URLs often
+// carry API keys in their path or query
+fn parse_rpc_url(value: &str) -> Result<Url, String> {
+    let url = Url::parse(value).map_err(|e| format!("not a valid URL: {}", e))?;
+    let Some(host) = host_label(&url) else {
+        return Err(format!("{} URL has no host", url.scheme()));
+    };
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("{}://{} must use http or https", url.scheme(), host));
+    }
+    Ok(url)
+}
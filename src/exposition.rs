@@ -0,0 +1,482 @@
+// Prometheus text exposition built from metric families instead of ad-hoc
+// strings: names are checked, label values escaped, and every sample of a
+// family is written under its single HELP/TYPE header, also after merging,
+// in the order of their label sets. Rendered either in the classic text
+// format or as OpenMetrics 1.0.
+
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Content types of the two formats
+const TEXT_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Text,
+    OpenMetrics,
+}
+
+impl Format {
+    // Pick the format from an Accept header: OpenMetrics when the scraper
+    // prefers it at least as much as the text format
+    pub fn negotiate(accept: Option<&str>) -> Self {
+        let Some(accept) = accept else {
+            return Format::Text;
+        };
+        let mut openmetrics = 0.0;
+        let mut text = 0.0;
+        for range in accept.split(',') {
+            let mut params = range.split(';').map(str::trim);
+            let media_type = params.next().unwrap_or_default().to_ascii_lowercase();
+            let quality = params
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f64>().ok())
+                .unwrap_or(1.0);
+            match media_type.as_str() {
+                "application/openmetrics-text" => openmetrics = f64::max(openmetrics, quality),
+                "text/plain" | "text/*" | "*/*" => text = f64::max(text, quality),
+                _ => {}
+            }
+        }
+        if openmetrics > 0.0 && openmetrics >= text {
+            Format::OpenMetrics
+        } else {
+            Format::Text
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Text => TEXT_CONTENT_TYPE,
+            Format::OpenMetrics => OPENMETRICS_CONTENT_TYPE,
+        }
+    }
+}
+
+// Whether an Accept-Encoding header allows gzip: listed with q > 0, or not
+// listed while `*` is
+pub fn accepts_gzip(accept_encoding: Option<&str>) -> bool {
+    let Some(header) = accept_encoding else {
+        return false;
+    };
+    let mut gzip = None;
+    let mut any = None;
+    for coding in header.split(',') {
+        let mut params = coding.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default();
+        let quality = params
+            .filter_map(|param| param.strip_prefix("q="))
+            .find_map(|q| q.parse::<f64>().ok())
+            .unwrap_or(1.0);
+        if name.eq_ignore_ascii_case("gzip") {
+            gzip = Some(quality);
+        } else if name == "*" {
+            any = Some(quality);
+        }
+    }
+    gzip.or(any).is_some_and(|quality| quality > 0.0)
+}
+
+// Whether an If-None-Match header lists `etag`, compared weakly as RFC 9110
+// asks for this header
+pub fn etag_matches(if_none_match: Option<&str>, etag: &str) -> bool {
+    let Some(header) = if_none_match else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    header.split(',').any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetricType {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+            MetricType::Histogram => "histogram",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Sample {
+    // Appended to the family name, e.g. "_bucket" for histograms
+    suffix: &'static str,
+    labels: Vec<(String, String)>,
+    value: f64,
+    // The time the series started, only written in OpenMetrics as `<name>_created`
+    created: bool,
+}
+
+// By the bits of the value, so identical expositions hash the same (for ETags)
+impl Hash for Sample {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.suffix.hash(state);
+        self.labels.hash(state);
+        self.value.to_bits().hash(state);
+        self.created.hash(state);
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+pub struct Family {
+    name: String,
+    help: String,
+    kind: MetricType,
+    samples: Vec<Sample>,
+}
+
+impl Family {
+    pub fn sample(&mut self, labels: &[(&str, &str)], value: f64) {
+        self.sample_with_suffix("", labels, value);
+    }
+
+    // A sample of a derived series such as `<name>_bucket`, `<name>_sum` or `<name>_count`
+    pub fn sample_with_suffix(&mut self, suffix: &'static str, labels: &[(&str, &str)], value: f64) {
+        for (name, _) in labels {
+            assert!(is_label_name(name), "invalid label name {:?} on {}", name, self.name);
+        }
+        self.samples.push(Sample {
+            suffix,
+            labels: labels.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            value,
+            created: false,
+        });
+    }
+
+    // When the counter or histogram with these labels started counting; goes
+    // right after the series' samples
+    pub fn created(&mut self, labels: &[(&str, &str)], since: SystemTime) {
+        self.sample_with_suffix("", labels, since.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64());
+        if let Some(sample) = self.samples.last_mut() {
+            sample.created = true;
+        }
+    }
+}
+
+// An ordered set of metric families, rendered as one exposition
+#[derive(Debug, Clone, Default, Hash)]
+pub struct Exposition {
+    families: Vec<Family>,
+}
+
+impl Exposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The family `name`, created with the given HELP and TYPE if it does not exist yet
+    pub fn family(&mut self, name: &str, help: &str, kind: MetricType) -> &mut Family {
+        assert!(is_metric_name(name), "invalid metric name {:?}", name);
+        let index = match self.families.iter().position(|family| family.name == name) {
+            Some(index) => index,
+            None => {
+                self.families.push(Family {
+                    name: name.to_string(),
+                    help: help.to_string(),
+                    kind,
+                    samples: Vec::new(),
+                });
+                self.families.len() - 1
+            }
+        };
+        &mut self.families[index]
+    }
+
+    // Add `name="value"` in front of the labels of every sample
+    pub fn with_label(mut self, name: &str, value: &str) -> Self {
+        assert!(is_label_name(name), "invalid label name {:?}", name);
+        for family in &mut self.families {
+            for sample in &mut family.samples {
+                sample.labels.insert(0, (name.to_string(), value.to_string()));
+            }
+        }
+        self
+    }
+
+    // Rename the families whose name starts with `from` to start with `to` instead
+    pub fn with_prefix(mut self, from: &str, to: &str) -> Self {
+        for family in &mut self.families {
+            if let Some(rest) = family.name.strip_prefix(from) {
+                family.name = format!("{}{}", to, rest);
+                assert!(is_metric_name(&family.name), "invalid metric name {:?}", family.name);
+            }
+        }
+        self
+    }
+
+    // Move the samples of `other` into the families of the same name, keeping
+    // the HELP and TYPE seen first
+    pub fn merge(&mut self, other: Exposition) {
+        for family in other.families {
+            let target = self.family(&family.name, &family.help, family.kind);
+            target.samples.extend(family.samples);
+        }
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        let mut output = String::new();
+        for family in &self.families {
+            // OpenMetrics names a counter family without its `_total` suffix
+            let base = match (format, family.kind) {
+                (Format::OpenMetrics, MetricType::Counter) => family.name.strip_suffix("_total").unwrap_or(&family.name),
+                _ => family.name.as_str(),
+            };
+            output.push_str(&format!("# HELP {} {}\n", base, escape_help(&family.help, format)));
+            output.push_str(&format!("# TYPE {} {}\n", base, family.kind.as_str()));
+            // Stable, so the buckets, sum, count and created time of a series stay in order
+            let mut samples: Vec<&Sample> = family.samples.iter().collect();
+            samples.sort_by(|a, b| series_order(a, b));
+            for sample in samples {
+                match (sample.created, format) {
+                    (true, Format::Text) => continue,
+                    (true, Format::OpenMetrics) => {
+                        output.push_str(base);
+                        output.push_str("_created");
+                    }
+                    (false, Format::OpenMetrics) if family.kind == MetricType::Counter => {
+                        output.push_str(base);
+                        output.push_str("_total");
+                    }
+                    (false, _) => {
+                        output.push_str(&family.name);
+                        output.push_str(sample.suffix);
+                    }
+                }
+                if !sample.labels.is_empty() {
+                    let labels: Vec<String> = sample
+                        .labels
+                        .iter()
+                        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+                        .collect();
+                    output.push_str(&format!("{{{}}}", labels.join(",")));
+                }
+                output.push_str(&format!(" {}\n", format_value(sample.value)));
+            }
+        }
+        if format == Format::OpenMetrics {
+            output.push_str("# EOF\n");
+        }
+        output
+    }
+}
+
+// By the labels that make the series, all but the `le` of histogram buckets
+fn series_order<'a>(a: &'a Sample, b: &'a Sample) -> Ordering {
+    let series = |sample: &'a Sample| sample.labels.iter().filter(|(name, _)| name != "le");
+    series(a).cmp(series(b))
+}
+
+// [a-zA-Z_:][a-zA-Z0-9_:]*
+pub fn is_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+// [a-zA-Z_][a-zA-Z0-9_]*, with names starting with "__" reserved for Prometheus
+pub fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !name.starts_with("__")
+}
+
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// OpenMetrics also escapes double quotes in HELP text
+fn escape_help(help: &str, format: Format) -> String {
+    let escaped = help.replace('\\', "\\\\").replace('\n', "\\n");
+    match format {
+        Format::Text => escaped,
+        Format::OpenMetrics => escaped.replace('"', "\\\""),
+    }
+}
+
+// Sample values as Prometheus parses them: integers without a fraction, and
+// +Inf, -Inf and NaN spelled its way
+pub fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_label_values() {
+        let mut exposition = Exposition::new();
+        exposition.family("m", "Help", MetricType::Gauge).sample(&[("name", "a\\b \"c\"\nd")], 1.0);
+        assert!(exposition.render(Format::Text).contains(r#"m{name="a\\b \"c\"\nd"} 1"#));
+    }
+
+    #[test]
+    fn escapes_help_per_format() {
+        let mut exposition = Exposition::new();
+        exposition.family("m", "Say \"hi\"\n", MetricType::Gauge);
+        assert!(exposition.render(Format::Text).contains("# HELP m Say \"hi\"\\n\n"));
+        assert!(exposition.render(Format::OpenMetrics).contains("# HELP m Say \\\"hi\\\"\\n\n"));
+    }
+
+    #[test]
+    fn validates_names() {
+        assert!(is_metric_name("solana_validator_credits"));
+        assert!(is_metric_name(":recorded:rate"));
+        assert!(!is_metric_name(""));
+        assert!(!is_metric_name("1st"));
+        assert!(!is_metric_name("has-dash"));
+        assert!(is_label_name("vote_pubkey"));
+        assert!(is_label_name("_private"));
+        assert!(!is_label_name("__reserved"));
+        assert!(!is_label_name("with:colon"));
+        assert!(!is_label_name("9"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid metric name")]
+    fn rejects_invalid_metric_names() {
+        Exposition::new().family("bad-name", "Help", MetricType::Gauge);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid label name")]
+    fn rejects_invalid_label_names() {
+        Exposition::new().family("m", "Help", MetricType::Gauge).sample(&[("__name__", "x")], 1.0);
+    }
+
+    #[test]
+    fn merges_into_one_family() {
+        let mut first = Exposition::new();
+        first.family("m", "First help", MetricType::Gauge).sample(&[("a", "1")], 1.0);
+        let mut second = Exposition::new();
+        second.family("m", "Second help", MetricType::Counter).sample(&[("a", "2")], 2.0);
+        second.family("n", "Other", MetricType::Gauge).sample(&[], 3.0);
+        first.merge(second);
+        let text = first.render(Format::Text);
+        assert_eq!(text.matches("# HELP m ").count(), 1);
+        assert!(text.contains("# HELP m First help\n# TYPE m gauge\n"));
+        assert!(text.find("m{a=\"2\"}").unwrap() < text.find("# HELP n").unwrap());
+    }
+
+    #[test]
+    fn orders_samples_by_labels() {
+        let mut exposition = Exposition::new();
+        let family = exposition.family("m", "Help", MetricType::Gauge);
+        family.sample(&[("vote_pubkey", "c")], 3.0);
+        family.sample(&[("vote_pubkey", "a")], 1.0);
+        family.sample(&[("vote_pubkey", "b")], 2.0);
+        let text = exposition.render(Format::Text);
+        assert!(text.ends_with("m{vote_pubkey=\"a\"} 1\nm{vote_pubkey=\"b\"} 2\nm{vote_pubkey=\"c\"} 3\n"));
+    }
+
+    #[test]
+    fn keeps_histogram_series_together() {
+        let mut exposition = Exposition::new();
+        let family = exposition.family("h", "Help", MetricType::Histogram);
+        for (endpoint, count) in [("z", 2.0), ("a", 1.0)] {
+            family.sample_with_suffix("_bucket", &[("endpoint", endpoint), ("le", "0.5")], count);
+            family.sample_with_suffix("_bucket", &[("endpoint", endpoint), ("le", "+Inf")], count);
+            family.sample_with_suffix("_sum", &[("endpoint", endpoint)], count);
+            family.sample_with_suffix("_count", &[("endpoint", endpoint)], count);
+        }
+        let text = exposition.render(Format::Text);
+        let lines: Vec<&str> = text.lines().skip(2).map(|line| line.split(' ').next().unwrap()).collect();
+        assert_eq!(
+            lines,
+            [
+                "h_bucket{endpoint=\"a\",le=\"0.5\"}",
+                "h_bucket{endpoint=\"a\",le=\"+Inf\"}",
+                "h_sum{endpoint=\"a\"}",
+                "h_count{endpoint=\"a\"}",
+                "h_bucket{endpoint=\"z\",le=\"0.5\"}",
+                "h_bucket{endpoint=\"z\",le=\"+Inf\"}",
+                "h_sum{endpoint=\"z\"}",
+                "h_count{endpoint=\"z\"}",
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_openmetrics() {
+        let mut exposition = Exposition::new();
+        let family = exposition.family("polls_total", "Polls", MetricType::Counter);
+        family.sample(&[], 5.0);
+        family.created(&[], UNIX_EPOCH + std::time::Duration::from_secs(100));
+        let text = exposition.render(Format::OpenMetrics);
+        assert_eq!(text, "# HELP polls Polls\n# TYPE polls counter\npolls_total 5\npolls_created 100\n# EOF\n");
+        let classic = exposition.render(Format::Text);
+        assert_eq!(classic, "# HELP polls_total Polls\n# TYPE polls_total counter\npolls_total 5\n");
+    }
+
+    #[test]
+    fn formats_special_values() {
+        assert_eq!(format_value(f64::NAN), "NaN");
+        assert_eq!(format_value(f64::INFINITY), "+Inf");
+        assert_eq!(format_value(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(format_value(3.0), "3");
+        assert_eq!(format_value(0.25), "0.25");
+    }
+
+    #[test]
+    fn negotiates_format() {
+        assert_eq!(Format::negotiate(None), Format::Text);
+        assert_eq!(Format::negotiate(Some("application/openmetrics-text; version=1.0.0")), Format::OpenMetrics);
+        assert_eq!(Format::negotiate(Some("application/openmetrics-text;q=0.5,text/plain")), Format::Text);
+    }
+
+    #[test]
+    fn accepts_gzip_by_quality() {
+        assert!(!accepts_gzip(None));
+        assert!(!accepts_gzip(Some("")));
+        assert!(accepts_gzip(Some("gzip")));
+        assert!(accepts_gzip(Some("deflate, GZIP;q=0.5, br")));
+        assert!(!accepts_gzip(Some("gzip;q=0")));
+        assert!(!accepts_gzip(Some("gzip; q=0.0, deflate")));
+        assert!(!accepts_gzip(Some("deflate, br")));
+        assert!(!accepts_gzip(Some("identity")));
+    }
+
+    #[test]
+    fn accepts_gzip_through_the_wildcard() {
+        assert!(accepts_gzip(Some("*")));
+        assert!(accepts_gzip(Some("br;q=1.0, *;q=0.1")));
+        assert!(!accepts_gzip(Some("*;q=0")));
+        // gzip named explicitly takes precedence over the wildcard
+        assert!(!accepts_gzip(Some("gzip;q=0, *")));
+        assert!(accepts_gzip(Some("*;q=0, gzip")));
+    }
+
+    #[test]
+    fn matches_etags_weakly() {
+        assert!(!etag_matches(None, "\"abc\""));
+        assert!(etag_matches(Some("\"abc\""), "\"abc\""));
+        assert!(etag_matches(Some("W/\"abc\""), "\"abc\""));
+        assert!(etag_matches(Some("\"abc\""), "W/\"abc\""));
+        assert!(!etag_matches(Some("\"abd\""), "\"abc\""));
+        assert!(!etag_matches(Some(""), "\"abc\""));
+    }
+
+    #[test]
+    fn matches_etag_lists_and_wildcard() {
+        assert!(etag_matches(Some("\"x\", W/\"abc\" ,\"y\""), "\"abc\""));
+        assert!(!etag_matches(Some("\"x\", \"y\""), "\"abc\""));
+        assert!(etag_matches(Some("*"), "\"abc\""));
+        assert!(etag_matches(Some(" * "), "\"abc\""));
+    }
+}
@@ -0,0 +1,104 @@
+// Country and autonomous system of every validator, from the address its node
+// advertises in gossip looked up in the MaxMind databases of `geoip`. The
+// addresses are fetched every `geoip.refresh_interval`; a database is read
+// again when its file changes, so `geoipupdate` needs no restart.
+
+use crate::config::GeoIpConfig;
+use crate::gossip::Gossip;
+use crate::mmdb::Database;
+use log::{debug, warn};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Default)]
+pub struct Location {
+    // ISO 3166 code, empty when unknown
+    pub country: String,
+    pub asn: Option<u64>,
+    // Organization of the autonomous system, empty when unknown
+    pub as_org: String,
+}
+
+// A database and the file it was read from
+struct Loaded {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    database: Database,
+}
+
+#[derive(Default)]
+pub struct GeoIp {
+    country: Option<Loaded>,
+    asn: Option<Loaded>,
+    // Gossip fetch the locations are from
+    generation: u64,
+    // Location of each node identity with a gossip address
+    pub locations: HashMap<String, Location>,
+}
+
+impl GeoIp {
+    // Locate the nodes again when the databases or the gossip addresses changed
+    pub fn refresh(&mut self, gossip: &Gossip, config: &GeoIpConfig) {
+        let mut changed = sync(&mut self.country, config.country_database.as_deref());
+        changed |= sync(&mut self.asn, config.asn_database.as_deref());
+        if changed || gossip.generation != self.generation {
+            self.generation = gossip.generation;
+            self.locate(gossip);
+        }
+    }
+
+    fn locate(&mut self, gossip: &Gossip) {
+        let mut locations = HashMap::with_capacity(gossip.nodes.len());
+        for (identity, ip) in gossip.nodes.iter().filter_map(|(identity, node)| Some((identity, node.gossip?.ip()))) {
+            let mut location = Location::default();
+            if let Some(record) = lookup(&self.country, ip) {
+                let country = record.pointer("/country/iso_code").or_else(|| record.pointer("/registered_country/iso_code"));
+                location.country = country.and_then(Value::as_str).unwrap_or_default().to_string();
+            }
+            if let Some(record) = lookup(&self.asn, ip) {
+                location.asn = record["autonomous_system_number"].as_u64();
+                location.as_org = record["autonomous_system_organization"].as_str().unwrap_or_default().to_string();
+            }
+            locations.insert(identity.clone(), location);
+        }
+        self.locations = locations;
+    }
+}
+
+// (Re)read the database at `path` when it is new or its file changed; whether
+// anything changed
+fn sync(loaded: &mut Option<Loaded>, path: Option<&Path>) -> bool {
+    let Some(path) = path else {
+        return loaded.take().is_some();
+    };
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    if loaded.as_ref().is_some_and(|loaded| loaded.path == path && loaded.modified == modified) {
+        return false;
+    }
+    match Database::open(path) {
+        Ok(database) => {
+            debug!("Loaded MaxMind database {}", path.display());
+            *loaded = Some(Loaded { path: path.to_path_buf(), modified, database });
+            true
+        }
+        Err(e) => {
+            // Tried again on the next refresh
+            warn!("{}", e);
+            false
+        }
+    }
+}
+
+fn lookup(loaded: &Option<Loaded>, ip: IpAddr) -> Option<Value> {
+    match loaded.as_ref()?.database.lookup(ip) {
+        Ok(record) => record,
+        Err(e) => {
+            warn!("Lookup of {} failed: {}", ip, e);
+            None
+        }
+    }
+}
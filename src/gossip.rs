@@ -0,0 +1,61 @@
+// What every node advertises in gossip (`getClusterNodes`): its addresses and
+// the software it runs. One fetch serves every section that needs it, as
+// often as the most frequent of them asks.
+
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Default)]
+pub struct Node {
+    pub gossip: Option<SocketAddr>,
+    // Transaction ports, UDP and QUIC
+    pub tpu: Option<SocketAddr>,
+    pub tpu_quic: Option<SocketAddr>,
+    // JSON RPC, for nodes that serve it publicly
+    pub rpc: Option<SocketAddr>,
+    // Software version, e.g. "1.18.22", None for nodes that do not say
+    pub version: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Gossip {
+    fetched: Option<Instant>,
+    // Successful fetches so far, for users to notice a new one
+    pub generation: u64,
+    // Every node by identity pubkey
+    pub nodes: HashMap<String, Node>,
+}
+
+impl Gossip {
+    // Fetch the nodes when the last fetch is `interval` seconds old; a failure
+    // is logged and keeps what was known before
+    pub async fn refresh(&mut self, client: &RpcClient, interval: f64) {
+        if self.fetched.is_some_and(|fetched| fetched.elapsed().as_secs_f64() < interval) {
+            return;
+        }
+        match client.get_cluster_nodes().await {
+            Ok(nodes) => {
+                self.nodes = nodes
+                    .into_iter()
+                    .map(|node| {
+                        let known = Node {
+                            gossip: node.gossip,
+                            tpu: node.tpu,
+                            tpu_quic: node.tpu_quic,
+                            rpc: node.rpc,
+                            version: node.version,
+                        };
+                        (node.pubkey, known)
+                    })
+                    .collect();
+                debug!("Fetched {} cluster nodes", self.nodes.len());
+                self.fetched = Some(Instant::now());
+                self.generation += 1;
+            }
+            Err(e) => warn!("Cluster nodes request failed: {}", e),
+        }
+    }
+}
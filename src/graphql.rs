@@ -0,0 +1,689 @@
+// The `/graphql` endpoint: a small GraphQL subset over the cached snapshot, so
+// consumers fetch exactly the fields and validators they need in one call.
+//
+// Supported: one query operation of fields, aliases, arguments and variables;
+// no fragments, directives, mutations or introspection. The schema:
+//
+//   type Query { clusters(cluster: String): [Cluster!]! }
+//   type Cluster {
+//     cluster: String!  updated_at: Float  data_age_seconds: Float  stale: Boolean!  epoch: Int
+//     validators(pubkeys: [String!], top: Int): [Validator!]!
+//   }
+//   type Validator { vote_pubkey: String!  node_pubkey: String!  rank: Int!  credits_earned: Int!  root_distance: Int!  vote_distance: Int!
+//     delinquent: Boolean! }
+
+use crate::limits::read_body;
+use crate::{MetricsCache, SharedCache, ValidatorMetrics};
+use hyper::header::{ALLOW, CONTENT_TYPE};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::time::UNIX_EPOCH;
+
+// Largest POST body accepted, queries are small
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+// Nesting beyond what the schema has is an error anyway; this bounds the parser's
+// recursion (selections, list values, list types) before the schema is looked at
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Punct(char),
+    Spread,
+    Name(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            // Commas are insignificant in GraphQL
+            ' ' | '\t' | '\n' | '\r' | ',' | '\u{feff}' => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '{' | '}' | '(' | ')' | '[' | ']' | ':' | '!' | '$' | '=' | '@' | '|' | '&' => {
+                tokens.push(Token::Punct(c));
+                i += 1;
+            }
+            '.' if chars[i..].starts_with(&['.', '.', '.']) => {
+                tokens.push(Token::Spread);
+                i += 3;
+            }
+            '"' => {
+                i += 1;
+                let mut value = String::new();
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => return Err("unterminated string".to_string()),
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escaped = match chars.get(i + 1) {
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some('r') => '\r',
+                                Some(other @ ('"' | '\\' | '/')) => *other,
+                                Some('u') => {
+                                    let hex: String = chars.iter().skip(i + 2).take(4).collect();
+                                    i += 4;
+                                    u32::from_str_radix(&hex, 16)
+                                        .ok()
+                                        .and_then(char::from_u32)
+                                        .ok_or_else(|| format!("invalid escape \\u{}", hex))?
+                                }
+                                _ => return Err("invalid escape in string".to_string()),
+                            };
+                            value.push(escaped);
+                            i += 2;
+                        }
+                        Some(other) => {
+                            value.push(*other);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Str(value));
+                i += 1;
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '.' | '+' | '-')) {
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                let token = match number.parse::<i64>() {
+                    Ok(int) => Token::Int(int),
+                    Err(_) => Token::Float(number.parse().map_err(|_| format!("invalid number {}", number))?),
+                };
+                tokens.push(token);
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Name(chars[start..i].iter().collect()));
+            }
+            other => return Err(format!("unexpected character {:?}", other)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Field {
+    // Key of the field in the response, the alias if one was given
+    key: String,
+    name: String,
+    arguments: Vec<(String, Value)>,
+    selection: Vec<Field>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    variables: Map<String, Value>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self.tokens.get(self.position).cloned().ok_or("unexpected end of query")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, punct: char) -> Result<(), String> {
+        match self.next()? {
+            Token::Punct(c) if c == punct => Ok(()),
+            other => Err(format!("expected '{}', found {:?}", punct, other)),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Name(name) => Ok(name),
+            other => Err(format!("expected a name, found {:?}", other)),
+        }
+    }
+
+    fn eat(&mut self, punct: char) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // `{ ... }` or `query Name($var: Type = default) { ... }`
+    fn document(&mut self) -> Result<Vec<Field>, String> {
+        if let Some(Token::Name(keyword)) = self.peek() {
+            match keyword.as_str() {
+                "query" => self.position += 1,
+                "mutation" | "subscription" => return Err(format!("{} operations are not supported", keyword)),
+                "fragment" => return Err("fragments are not supported".to_string()),
+                _ => return Err(format!("unexpected {:?}", keyword)),
+            }
+            if let Some(Token::Name(_)) = self.peek() {
+                self.position += 1;
+            }
+            if self.eat('(') {
+                self.variable_definitions()?;
+            }
+        }
+        let selection = self.selection_set(0)?;
+        if self.peek().is_some() {
+            return Err("only a single operation is supported".to_string());
+        }
+        Ok(selection)
+    }
+
+    // Types are not checked; declared defaults fill in missing variables
+    fn variable_definitions(&mut self) -> Result<(), String> {
+        while !self.eat(')') {
+            self.expect('$')?;
+            let name = self.name()?;
+            self.expect(':')?;
+            self.skip_type(0)?;
+            if self.eat('=') {
+                let default = self.value(true, 0)?;
+                self.variables.entry(name).or_insert(default);
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_type(&mut self, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err("type is nested too deeply".to_string());
+        }
+        if self.eat('[') {
+            self.skip_type(depth + 1)?;
+            self.expect(']')?;
+        } else {
+            self.name()?;
+        }
+        self.eat('!');
+        Ok(())
+    }
+
+    fn selection_set(&mut self, depth: usize) -> Result<Vec<Field>, String> {
+        if depth > MAX_DEPTH {
+            return Err("query is nested too deeply".to_string());
+        }
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        while !self.eat('}') {
+            match self.peek() {
+                Some(Token::Spread) => return Err("fragments are not supported".to_string()),
+                Some(Token::Punct('@')) => return Err("directives are not supported".to_string()),
+                _ => {}
+            }
+            let key = self.name()?;
+            let mut name = key.clone();
+            if self.eat(':') {
+                name = self.name()?;
+            }
+            let mut arguments = Vec::new();
+            if self.eat('(') {
+                while !self.eat(')') {
+                    let argument = self.name()?;
+                    self.expect(':')?;
+                    arguments.push((argument, self.value(false, 0)?));
+                }
+            }
+            if self.peek() == Some(&Token::Punct('@')) {
+                return Err("directives are not supported".to_string());
+            }
+            let selection = if self.peek() == Some(&Token::Punct('{')) {
+                self.selection_set(depth + 1)?
+            } else {
+                Vec::new()
+            };
+            if fields.iter().any(|field: &Field| field.key == key) {
+                return Err(format!("{} is selected twice, use an alias", key));
+            }
+            fields.push(Field { key, name, arguments, selection });
+        }
+        Ok(fields)
+    }
+
+    // A literal or, outside of defaults, a `$variable`
+    fn value(&mut self, constant: bool, depth: usize) -> Result<Value, String> {
+        if depth > MAX_DEPTH {
+            return Err("value is nested too deeply".to_string());
+        }
+        Ok(match self.next()? {
+            Token::Punct('$') if !constant => {
+                let name = self.name()?;
+                self.variables.get(&name).cloned().unwrap_or(Value::Null)
+            }
+            Token::Str(value) => Value::String(value),
+            Token::Int(value) => json!(value),
+            Token::Float(value) => json!(value),
+            Token::Name(name) => match name.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => Value::String(name),
+            },
+            Token::Punct('[') => {
+                let mut items = Vec::new();
+                while !self.eat(']') {
+                    items.push(self.value(constant, depth + 1)?);
+                }
+                Value::Array(items)
+            }
+            Token::Punct('{') => return Err("input objects are not supported".to_string()),
+            other => return Err(format!("expected a value, found {:?}", other)),
+        })
+    }
+}
+
+// `GET /graphql?query=...&variables=...` or `POST /graphql` with the usual
+// `{"query": ..., "variables": ...}` JSON body (or a bare `application/graphql` query)
+pub async fn handle(req: Request<Body>, cache: &SharedCache, stale_after: f64) -> Response<Body> {
+    let request = match *req.method() {
+        Method::GET => {
+            let params: BTreeMap<String, String> = url::form_urlencoded::parse(req.uri().query().unwrap_or_default().as_bytes())
+                .into_owned()
+                .collect();
+            match params.get("variables").map(|variables| serde_json::from_str::<Value>(variables)) {
+                Some(Err(e)) => Err(format!("variables are not valid JSON: {}", e)),
+                variables => Ok((params.get("query").cloned(), variables.and_then(Result::ok))),
+            }
+        }
+        Method::POST => {
+            let is_graphql = req
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("application/graphql"));
+            match read_body(req.into_body(), MAX_BODY_SIZE).await {
+                Err(e) => Err(e),
+                Ok(body) if is_graphql => Ok((Some(String::from_utf8_lossy(&body).into_owned()), None)),
+                Ok(body) => match serde_json::from_slice::<Value>(&body) {
+                    Ok(request) => Ok((request.get("query").and_then(Value::as_str).map(str::to_string), request.get("variables").cloned())),
+                    Err(e) => Err(format!("body is not valid JSON: {}", e)),
+                },
+            }
+        }
+        _ => {
+            return Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(ALLOW, "GET, POST")
+                .body(Body::from("405 Method Not Allowed\n"))
+                .unwrap();
+        }
+    };
+
+    let (body, failed) = match request {
+        Ok((Some(query), variables)) => {
+            let variables = match variables {
+                Some(Value::Object(variables)) => variables,
+                _ => Map::new(),
+            };
+            execute(&*cache.lock().await, &query, variables, stale_after)
+        }
+        Ok((None, _)) => (error("no query given"), true),
+        Err(message) => (error(&message), true),
+    };
+    Response::builder()
+        .status(if failed { StatusCode::BAD_REQUEST } else { StatusCode::OK })
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+// Run `query` against the cache: the response body and whether it is an error
+// the client has to fix (answered with 400)
+fn execute(
+    cache: &BTreeMap<String, MetricsCache>,
+    query: &str,
+    variables: Map<String, Value>,
+    stale_after: f64,
+) -> (String, bool) {
+    let result = tokenize(query).and_then(|tokens| {
+        let mut parser = Parser { tokens, position: 0, variables };
+        let selection = parser.document()?;
+        resolve_query(cache, &selection, stale_after)
+    });
+    match result {
+        Ok(data) => (serde_json::to_string(&Data { data }).expect("graphql response serializes"), false),
+        Err(message) => (error(&message), true),
+    }
+}
+
+fn error(message: &str) -> String {
+    json!({ "errors": [{ "message": message }] }).to_string()
+}
+
+#[derive(Serialize)]
+struct Data {
+    data: Output,
+}
+
+// Response values; objects keep the order of the selection, as GraphQL requires
+// (a JSON map would sort the keys)
+enum Output {
+    Scalar(Value),
+    List(Vec<Output>),
+    Object(Vec<(String, Output)>),
+}
+
+impl Serialize for Output {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Output::Scalar(value) => value.serialize(serializer),
+            Output::List(items) => serializer.collect_seq(items),
+            Output::Object(fields) => serializer.collect_map(fields.iter().map(|(key, value)| (key, value))),
+        }
+    }
+}
+
+fn resolve_query(cache: &BTreeMap<String, MetricsCache>, selection: &[Field], stale_after: f64) -> Result<Output, String> {
+    let mut data = Vec::new();
+    for field in selection {
+        let value = match field.name.as_str() {
+            "clusters" => {
+                check_arguments(field, &["cluster"])?;
+                if field.selection.is_empty() {
+                    return Err(format!("{} needs a selection of Cluster fields", field.key));
+                }
+                let cluster = string_argument(field, "cluster")?;
+                let clusters = cache
+                    .iter()
+                    .filter(|(name, _)| cluster.as_ref().is_none_or(|cluster| cluster == *name))
+                    .map(|(name, cluster_cache)| resolve_cluster(name, cluster_cache, field, stale_after))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Output::List(clusters)
+            }
+            "__typename" => {
+                check_leaf(field)?;
+                Output::Scalar(json!("Query"))
+            }
+            other => return Err(format!("Query has no field {}", other)),
+        };
+        data.push((field.key.clone(), value));
+    }
+    Ok(Output::Object(data))
+}
+
+fn resolve_cluster(name: &str, cache: &MetricsCache, parent: &Field, stale_after: f64) -> Result<Output, String> {
+    let mut object = Vec::new();
+    for field in &parent.selection {
+        if field.name != "validators" {
+            check_leaf(field)?;
+        }
+        let value = match field.name.as_str() {
+            "cluster" => Output::Scalar(json!(name)),
+            "updated_at" => Output::Scalar(json!(cache.updated_at.map(|at| at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()))),
+            "data_age_seconds" => Output::Scalar(json!(cache.age())),
+            "stale" => Output::Scalar(json!(cache.is_stale(stale_after))),
+            "epoch" => Output::Scalar(json!(cache.epoch_info.as_ref().map(|info| info.epoch))),
+            "validators" => {
+                check_arguments(field, &["pubkeys", "top"])?;
+                if field.selection.is_empty() {
+                    return Err(format!("{} needs a selection of Validator fields", field.key));
+                }
+                let pubkeys = list_argument(field, "pubkeys")?;
+                let top = int_argument(field, "top")?;
+                let validators = cache
+                    .validators
+                    .iter()
+                    .filter(|validator| pubkeys.as_ref().is_none_or(|pubkeys| pubkeys.contains(&validator.vote_pubkey)))
+                    .filter(|validator| top.is_none_or(|top| validator.rank as i64 <= top))
+                    .map(|validator| resolve_validator(validator, field))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Output::List(validators)
+            }
+            "__typename" => Output::Scalar(json!("Cluster")),
+            other => return Err(format!("Cluster has no field {}", other)),
+        };
+        object.push((field.key.clone(), value));
+    }
+    Ok(Output::Object(object))
+}
+
+fn resolve_validator(validator: &ValidatorMetrics, parent: &Field) -> Result<Output, String> {
+    let mut object = Vec::new();
+    for field in &parent.selection {
+        check_leaf(field)?;
+        let value = match field.name.as_str() {
+            "vote_pubkey" => Output::Scalar(json!(validator.vote_pubkey)),
+            "node_pubkey" => Output::Scalar(json!(validator.node_pubkey)),
+            "rank" => Output::Scalar(json!(validator.rank)),
+            "credits_earned" => Output::Scalar(json!(validator.credits_earned)),
+            "root_distance" => Output::Scalar(json!(validator.root_distance)),
+            "vote_distance" => Output::Scalar(json!(validator.vote_distance)),
+            "delinquent" => Output::Scalar(json!(validator.delinquent)),
+            "__typename" => Output::Scalar(json!("Validator")),
+            other => return Err(format!("Validator has no field {}", other)),
+        };
+        object.push((field.key.clone(), value));
+    }
+    Ok(Output::Object(object))
+}
+
+// Fields without arguments are checked too, so a typo is not silently ignored
+fn check_arguments(field: &Field, allowed: &[&str]) -> Result<(), String> {
+    match field.arguments.iter().find(|(name, _)| !allowed.contains(&name.as_str())) {
+        Some((name, _)) => Err(format!("{} has no argument {}", field.name, name)),
+        None => Ok(()),
+    }
+}
+
+// Scalars take neither arguments nor a selection
+fn check_leaf(field: &Field) -> Result<(), String> {
+    check_arguments(field, &[])?;
+    if !field.selection.is_empty() {
+        return Err(format!("{} is a scalar and has no fields to select", field.key));
+    }
+    Ok(())
+}
+
+fn argument<'a>(field: &'a Field, name: &str) -> Option<&'a Value> {
+    field
+        .arguments
+        .iter()
+        .find(|(argument, _)| argument == name)
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_null())
+}
+
+fn string_argument(field: &Field, name: &str) -> Result<Option<String>, String> {
+    match argument(field, name) {
+        None => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(_) => Err(format!("{}({}:) must be a string", field.key, name)),
+    }
+}
+
+fn int_argument(field: &Field, name: &str) -> Result<Option<i64>, String> {
+    match argument(field, name) {
+        None => Ok(None),
+        Some(value) => value.as_i64().map(Some).ok_or_else(|| format!("{}({}:) must be an integer", field.key, name)),
+    }
+}
+
+// A single string is accepted as a list of one, as GraphQL input coercion does
+fn list_argument(field: &Field, name: &str) -> Result<Option<Vec<String>>, String> {
+    let invalid = || format!("{}({}:) must be a list of strings", field.key, name);
+    match argument(field, name) {
+        None => Ok(None),
+        Some(Value::String(value)) => Ok(Some(vec![value.clone()])),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| item.as_str().map(str::to_string).ok_or_else(invalid))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(_) => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    fn validator(vote_pubkey: &str, rank: usize, credits_earned: u64) -> ValidatorMetrics {
+        ValidatorMetrics {
+            vote_pubkey: vote_pubkey.to_string(),
+            node_pubkey: format!("node-{}", vote_pubkey),
+            activated_stake: 0,
+            stake_share: 0.0,
+            credits_per_slot: None,
+            projected_credits: None,
+            vote_efficiency: None,
+            vote_latency: None,
+            commission: 0,
+            delinquent: false,
+            epoch: 700,
+            history: Vec::new(),
+            root_distance: 1,
+            vote_distance: 2,
+            credits_earned,
+            rank,
+        }
+    }
+
+    fn cache() -> BTreeMap<String, MetricsCache> {
+        let mut cluster = MetricsCache::new();
+        cluster.validators = vec![validator("a", 1, 300), validator("b", 2, 200), validator("c", 3, 100)];
+        BTreeMap::from([("mainnet-beta".to_string(), cluster), ("testnet".to_string(), MetricsCache::new())])
+    }
+
+    fn run(query: &str, variables: Value) -> Result<Value, String> {
+        let Value::Object(variables) = variables else {
+            panic!("variables must be an object");
+        };
+        let (body, failed) = execute(&cache(), query, variables, 0.0);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        if failed {
+            Err(body["errors"][0]["message"].as_str().unwrap().to_string())
+        } else {
+            Ok(body["data"].clone())
+        }
+    }
+
+    #[test]
+    fn resolves_nested_selections() {
+        let data = run("{ clusters { cluster validators { vote_pubkey rank } } }", json!({})).unwrap();
+        assert_eq!(data["clusters"][0]["cluster"], "mainnet-beta");
+        assert_eq!(data["clusters"][0]["validators"][2], json!({ "vote_pubkey": "c", "rank": 3 }));
+        assert_eq!(data["clusters"][1]["validators"], json!([]));
+    }
+
+    #[test]
+    fn keeps_selection_order_and_aliases() {
+        let (body, failed) = execute(&cache(), "{ clusters(cluster: \"mainnet-beta\") { validators(top: 1) { r: rank id: vote_pubkey } } }", Map::new(), 0.0);
+        assert!(!failed);
+        assert_eq!(body, r#"{"data":{"clusters":[{"validators":[{"r":1,"id":"a"}]}]}}"#);
+    }
+
+    #[test]
+    fn applies_arguments() {
+        let data = run("{ clusters(cluster: \"mainnet-beta\") { validators(pubkeys: [\"c\", \"a\"]) { vote_pubkey } } }", json!({})).unwrap();
+        assert_eq!(data["clusters"].as_array().unwrap().len(), 1);
+        assert_eq!(data["clusters"][0]["validators"], json!([{ "vote_pubkey": "a" }, { "vote_pubkey": "c" }]));
+        // A single string stands for a list of one
+        let data = run("{ clusters { validators(pubkeys: \"b\") { rank } } }", json!({})).unwrap();
+        assert_eq!(data["clusters"][0]["validators"], json!([{ "rank": 2 }]));
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        let query = "query Top($cluster: String, $top: Int = 2) { clusters(cluster: $cluster) { validators(top: $top) { rank } } }";
+        let data = run(query, json!({ "cluster": "mainnet-beta" })).unwrap();
+        assert_eq!(data["clusters"][0]["validators"], json!([{ "rank": 1 }, { "rank": 2 }]));
+        let data = run(query, json!({ "cluster": "mainnet-beta", "top": 1 })).unwrap();
+        assert_eq!(data["clusters"][0]["validators"], json!([{ "rank": 1 }]));
+        // Missing without a default, so the argument is left out
+        let data = run("query($cluster: String) { clusters(cluster: $cluster) { cluster } }", json!({})).unwrap();
+        assert_eq!(data["clusters"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn limits_depth() {
+        let query = format!("{}{}", "{ a ".repeat(MAX_DEPTH + 2), "}".repeat(MAX_DEPTH + 2));
+        assert_eq!(run(&query, json!({})).unwrap_err(), "query is nested too deeply");
+        let query = format!("{{ clusters(cluster: {}\"x\"{}) {{ cluster }} }}", "[".repeat(MAX_DEPTH + 2), "]".repeat(MAX_DEPTH + 2));
+        assert_eq!(run(&query, json!({})).unwrap_err(), "value is nested too deeply");
+        let query = format!("query($v: {}String{}) {{ clusters {{ cluster }} }}", "[".repeat(MAX_DEPTH + 2), "]".repeat(MAX_DEPTH + 2));
+        assert_eq!(run(&query, json!({})).unwrap_err(), "type is nested too deeply");
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        for (query, error) in [
+            ("", "unexpected end of query"),
+            ("{ clusters { cluster }", "unexpected end of query"),
+            ("{ clusters { cluster } } }", "only a single operation is supported"),
+            ("{ clusters(cluster: \"x) { cluster } }", "unterminated string"),
+            ("{ clusters(cluster: \"\\q\") { cluster } }", "invalid escape in string"),
+            ("{ clusters(cluster: 1.2.3) { cluster } }", "invalid number 1.2.3"),
+            ("{ clusters { cluster ~ } }", "unexpected character '~'"),
+            ("mutation { clusters { cluster } }", "mutation operations are not supported"),
+            ("{ clusters { ...Fields } }", "fragments are not supported"),
+            ("{ clusters @skip(if: true) { cluster } }", "directives are not supported"),
+            ("{ clusters(cluster: {name: \"x\"}) { cluster } }", "input objects are not supported"),
+            ("{ clusters { cluster cluster } }", "cluster is selected twice, use an alias"),
+        ] {
+            assert_eq!(run(query, json!({})).unwrap_err(), error, "{}", query);
+        }
+    }
+
+    #[test]
+    fn rejects_queries_the_schema_does_not_have() {
+        for (query, error) in [
+            ("{ clusters }", "clusters needs a selection of Cluster fields"),
+            ("{ validators { rank } }", "Query has no field validators"),
+            ("{ clusters(name: \"x\") { cluster } }", "clusters has no argument name"),
+            ("{ clusters { stale { x } } }", "stale is a scalar and has no fields to select"),
+            ("{ clusters { validators(top: \"1\") { rank } } }", "validators(top:) must be an integer"),
+            ("{ clusters { validators(pubkeys: [1]) { rank } } }", "validators(pubkeys:) must be a list of strings"),
+            ("{ clusters { validators { stake } } }", "Validator has no field stake"),
+        ] {
+            assert_eq!(run(query, json!({})).unwrap_err(), error, "{}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_bodies() {
+        let cache: SharedCache = Arc::new(Mutex::new(cache()));
+        let query = format!("{{ clusters {{ cluster }} }}{}", " ".repeat(MAX_BODY_SIZE));
+        let req = Request::post("/graphql").header(CONTENT_TYPE, "application/graphql").body(Body::from(query)).unwrap();
+        let response = handle(req, &cache, 0.0).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("request body is larger than"));
+    }
+
+    #[tokio::test]
+    async fn answers_get_and_post() {
+        let cache: SharedCache = Arc::new(Mutex::new(cache()));
+        let req = Request::get("/graphql?query=%7Bclusters%7Bcluster%7D%7D").body(Body::empty()).unwrap();
+        assert_eq!(handle(req, &cache, 0.0).await.status(), StatusCode::OK);
+        let body = json!({ "query": "query($c: String) { clusters(cluster: $c) { cluster } }", "variables": { "c": "testnet" } });
+        let req = Request::post("/graphql").body(Body::from(body.to_string())).unwrap();
+        let body = hyper::body::to_bytes(handle(req, &cache, 0.0).await.into_body()).await.unwrap();
+        assert_eq!(&body[..], br#"{"data":{"clusters":[{"cluster":"testnet"}]}}"#);
+        let req = Request::put("/graphql").body(Body::empty()).unwrap();
+        assert_eq!(handle(req, &cache, 0.0).await.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
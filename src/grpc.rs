@@ -0,0 +1,377 @@
+// gRPC service `solana_credits_exporter.v1.Snapshots` (proto/snapshots.proto)
+// on `server.grpc_listen`, for services that want typed messages instead of
+// scraping text. Served by tonic from code that build.rs generates from the
+// proto file, over TLS with the settings of the HTTPS listener when
+// `server.tls` is set.
+
+use crate::limits::Slot;
+use crate::tls::ReloadingTls;
+use crate::{auth, HttpState, MetricsCache};
+use log::error;
+use std::error::Error;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::task;
+use tokio::time::{sleep, timeout};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::server::Connected;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+mod proto {
+    tonic::include_proto!("solana_credits_exporter.v1");
+}
+
+use proto::snapshots_server::{Snapshots, SnapshotsServer};
+use proto::{ClusterSnapshot, Snapshot, SnapshotRequest, Validator};
+
+// Requests only carry a cluster name
+const MAX_REQUEST_SIZE: usize = 4096;
+
+// Messages a `WatchSnapshots` stream buffers for a slow client
+const STREAM_BUFFER: usize = 16;
+
+// Accept connections forever; like the HTTP listeners they count towards
+// `server.limits.max_connections`, and each one gets its TLS handshake in its
+// own task before tonic serves it
+pub async fn serve(listener: TcpListener, tls: Option<Arc<ReloadingTls>>, state: HttpState) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (connections, incoming) = mpsc::channel(STREAM_BUFFER);
+    task::spawn(accept(listener, tls, state.clone(), connections));
+    let service = SnapshotsServer::new(Service { state }).max_decoding_message_size(MAX_REQUEST_SIZE);
+    Server::builder().add_service(service).serve_with_incoming(ReceiverStream::new(incoming)).await?;
+    Ok(())
+}
+
+async fn accept(listener: TcpListener, tls: Option<Arc<ReloadingTls>>, state: HttpState, connections: mpsc::Sender<io::Result<Connection>>) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                error!("Failed to accept gRPC connection: {}", e);
+                sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let max_connections = state.config.borrow().server.limits.max_connections;
+        let Some(slot) = state.connections.try_acquire(max_connections) else {
+            continue;
+        };
+        let acceptor = tls.as_ref().map(|tls| tls.grpc_acceptor());
+        let connections = connections.clone();
+        task::spawn(async move {
+            let stream: Box<dyn Io> = match acceptor {
+                // Failed handshakes are not logged, as on the HTTPS listener
+                Some(acceptor) => match timeout(crate::TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => Box::new(stream),
+                    _ => return,
+                },
+                None => Box::new(stream),
+            };
+            let _ = connections.send(Ok(Connection { stream, peer, _slot: slot })).await;
+        });
+    }
+}
+
+trait Io: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Io for T {}
+
+// A plain or TLS connection, holding its slot of `max_connections` until tonic drops it
+struct Connection {
+    stream: Box<dyn Io>,
+    peer: SocketAddr,
+    _slot: Slot,
+}
+
+impl Connected for Connection {
+    type ConnectInfo = SocketAddr;
+
+    fn connect_info(&self) -> SocketAddr {
+        self.peer
+    }
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.stream).poll_shutdown(cx)
+    }
+}
+
+struct Service {
+    state: HttpState,
+}
+
+impl Service {
+    // Why a request is refused: missing credentials or a cluster that is not
+    // monitored; None to answer it
+    fn rejection(&self, request: &Request<SnapshotRequest>) -> Option<Status> {
+        let config = self.state.config.borrow();
+        let authorization = request.metadata().get("authorization").and_then(|value| value.to_str().ok());
+        if !auth::authorized(&config.server.auth, &self.state.htpasswd, authorization) {
+            return Some(Status::unauthenticated("credentials required"));
+        }
+        let cluster = &request.get_ref().cluster;
+        if !cluster.is_empty() && config.cluster_rpc(cluster).is_none() {
+            return Some(Status::not_found("unknown cluster"));
+        }
+        None
+    }
+}
+
+#[tonic::async_trait]
+impl Snapshots for Service {
+    async fn get_snapshot(&self, request: Request<SnapshotRequest>) -> Result<Response<Snapshot>, Status> {
+        if let Some(status) = self.rejection(&request) {
+            return Err(status);
+        }
+        let cluster = request.into_inner().cluster;
+        let stale_after = self.state.config.borrow().metrics.stale_after;
+        let cache = self.state.cache.lock().await;
+        let clusters = cache.iter().filter(|(name, _)| wanted(&cluster, name));
+        Ok(Response::new(snapshot(clusters, stale_after)))
+    }
+
+    type WatchSnapshotsStream = ReceiverStream<Result<Snapshot, Status>>;
+
+    async fn watch_snapshots(&self, request: Request<SnapshotRequest>) -> Result<Response<Self::WatchSnapshotsStream>, Status> {
+        if let Some(status) = self.rejection(&request) {
+            return Err(status);
+        }
+        let cluster = request.into_inner().cluster;
+        let (messages, stream) = mpsc::channel(STREAM_BUFFER);
+        // Subscribed before the current data is read, so no update falls in between
+        let mut updates = self.state.updates.subscribe();
+        let state = self.state.clone();
+        task::spawn(async move {
+            let initial: Vec<Snapshot> = {
+                let stale_after = state.config.borrow().metrics.stale_after;
+                let cache = state.cache.lock().await;
+                cache
+                    .iter()
+                    .filter(|(name, _)| wanted(&cluster, name))
+                    .map(|cluster| snapshot(std::iter::once(cluster), stale_after))
+                    .collect()
+            };
+            for message in initial {
+                if messages.send(Ok(message)).await.is_err() {
+                    return;
+                }
+            }
+            loop {
+                match updates.recv().await {
+                    Ok(name) if wanted(&cluster, &name) => {
+                        let stale_after = state.config.borrow().metrics.stale_after;
+                        let message = {
+                            let cache = state.cache.lock().await;
+                            cache.get_key_value(&name).map(|cluster| snapshot(std::iter::once(cluster), stale_after))
+                        };
+                        if let Some(message) = message {
+                            if messages.send(Ok(message)).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    // Every message is a complete snapshot, so missed ones lose nothing
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    // Dropping the sender ends the stream with OK
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(stream)))
+    }
+}
+
+fn wanted(cluster: &str, name: &str) -> bool {
+    cluster.is_empty() || cluster == name
+}
+
+fn snapshot<'a>(clusters: impl Iterator<Item = (&'a String, &'a MetricsCache)>, stale_after: f64) -> Snapshot {
+    let clusters = clusters
+        .map(|(name, cache)| ClusterSnapshot {
+            cluster: name.clone(),
+            updated_at: cache.updated_at.map_or(0.0, |at| at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()),
+            data_age_seconds: cache.age().unwrap_or_default(),
+            stale: cache.is_stale(stale_after),
+            epoch: cache.epoch_info.as_ref().map_or(0, |info| info.epoch),
+            validators: cache
+                .validators
+                .iter()
+                .map(|validator| Validator {
+                    vote_pubkey: validator.vote_pubkey.clone(),
+                    rank: validator.rank as u64,
+                    credits_earned: validator.credits_earned,
+                    root_distance: validator.root_distance,
+                    vote_distance: validator.vote_distance,
+                    node_pubkey: validator.node_pubkey.clone(),
+                    delinquent: validator.delinquent,
+                })
+                .collect(),
+        })
+        .collect();
+    Snapshot { clusters }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::snapshots_client::SnapshotsClient;
+    use super::*;
+    use crate::auth::Htpasswd;
+    use crate::config::Config;
+    use crate::limits::InFlight;
+    use crate::watchlist::Watchlist;
+    use crate::ValidatorMetrics;
+    use std::collections::BTreeMap;
+    use std::time::{Instant, SystemTime};
+    use tokio::sync::{broadcast, watch, Mutex};
+    use tonic::transport::Channel;
+
+    fn validator(vote_pubkey: &str, rank: usize, credits_earned: u64) -> ValidatorMetrics {
+        ValidatorMetrics {
+            vote_pubkey: vote_pubkey.to_string(),
+            node_pubkey: format!("node-{}", vote_pubkey),
+            activated_stake: 0,
+            stake_share: 0.0,
+            credits_per_slot: None,
+            projected_credits: None,
+            vote_efficiency: None,
+            vote_latency: None,
+            commission: 0,
+            delinquent: rank == 2,
+            epoch: 700,
+            history: Vec::new(),
+            root_distance: 1,
+            vote_distance: 2,
+            credits_earned,
+            rank,
+        }
+    }
+
+    // A server on a free port with a polled mainnet-beta and a testnet that
+    // has not been polled yet
+    async fn start(config: Config) -> (HttpState, SnapshotsClient<Channel>) {
+        let mut mainnet = MetricsCache::new();
+        mainnet.validators = vec![validator("a", 1, 300), validator("b", 2, 200)];
+        mainnet.updated = Some(Instant::now());
+        mainnet.updated_at = Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let cache = BTreeMap::from([("mainnet-beta".to_string(), mainnet), ("testnet".to_string(), MetricsCache::new())]);
+        let (_, config) = watch::channel(Arc::new(config));
+        let state = HttpState {
+            cache: Arc::new(Mutex::new(cache)),
+            config,
+            connections: Arc::new(InFlight::default()),
+            scrapes: Arc::new(InFlight::default()),
+            updates: broadcast::channel(16).0,
+            refresh: broadcast::channel(16).0,
+            watchlist: Arc::new(Watchlist::new(Vec::new())),
+            htpasswd: Arc::new(Htpasswd::default()),
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        task::spawn(serve(listener, None, state.clone()));
+        let client = SnapshotsClient::connect(format!("http://{}", addr)).await.unwrap();
+        (state, client)
+    }
+
+    fn clusters() -> Config {
+        let mut config = Config::default();
+        for name in ["mainnet-beta", "testnet"] {
+            config.clusters.insert(name.to_string(), config.rpc.clone());
+        }
+        config
+    }
+
+    fn request(cluster: &str) -> SnapshotRequest {
+        SnapshotRequest { cluster: cluster.to_string() }
+    }
+
+    #[tokio::test]
+    async fn get_snapshot_round_trips_the_cache() {
+        let (_state, mut client) = start(clusters()).await;
+        let snapshot = client.get_snapshot(request("")).await.unwrap().into_inner();
+        assert_eq!(snapshot.clusters.len(), 2);
+        let mainnet = &snapshot.clusters[0];
+        assert_eq!(mainnet.cluster, "mainnet-beta");
+        assert_eq!(mainnet.updated_at, 1_700_000_000.0);
+        assert!(!mainnet.stale);
+        assert_eq!(
+            mainnet.validators[1],
+            Validator {
+                vote_pubkey: "b".to_string(),
+                rank: 2,
+                credits_earned: 200,
+                root_distance: 1,
+                vote_distance: 2,
+                node_pubkey: "node-b".to_string(),
+                delinquent: true,
+            }
+        );
+        let testnet = &snapshot.clusters[1];
+        assert_eq!((testnet.cluster.as_str(), testnet.updated_at, testnet.validators.len()), ("testnet", 0.0, 0));
+
+        let snapshot = client.get_snapshot(request("testnet")).await.unwrap().into_inner();
+        assert_eq!(snapshot.clusters.len(), 1);
+        assert_eq!(snapshot.clusters[0].cluster, "testnet");
+        let status = client.get_snapshot(request("devnet")).await.unwrap_err();
+        assert_eq!((status.code(), status.message()), (tonic::Code::NotFound, "unknown cluster"));
+    }
+
+    #[tokio::test]
+    async fn watch_snapshots_streams_updates() {
+        let (state, mut client) = start(clusters()).await;
+        let mut stream = client.watch_snapshots(request("mainnet-beta")).await.unwrap().into_inner();
+        let initial = stream.message().await.unwrap().unwrap();
+        assert_eq!(initial.clusters.len(), 1);
+        assert_eq!(initial.clusters[0].validators.len(), 2);
+
+        // Updates of other clusters are left out
+        state.updates.send("testnet".to_string()).unwrap();
+        {
+            let mut cache = state.cache.lock().await;
+            let mainnet = cache.get_mut("mainnet-beta").unwrap();
+            mainnet.validators.push(validator("c", 3, 100));
+            mainnet.updated_at = Some(SystemTime::now());
+        }
+        state.updates.send("mainnet-beta".to_string()).unwrap();
+        let update = stream.message().await.unwrap().unwrap();
+        assert_eq!(update.clusters[0].cluster, "mainnet-beta");
+        assert_eq!(update.clusters[0].validators[2].vote_pubkey, "c");
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_credentials() {
+        let mut config = clusters();
+        config.server.auth.bearer_token = Some("secret".to_string());
+        let (_state, mut client) = start(config).await;
+        let status = client.get_snapshot(request("")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        let status = client.watch_snapshots(request("")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let mut request = Request::new(request(""));
+        request.metadata_mut().insert("authorization", "Bearer secret".parse().unwrap());
+        assert_eq!(client.get_snapshot(request).await.unwrap().into_inner().clusters.len(), 2);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::exposition::{format_value, Family};
+use std::time::SystemTime;
+
+// Cumulative histogram in the Prometheus sense: one counter per upper bound,
+// plus the sum and count of all observations
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+    // When the histogram started counting, for `_created` in OpenMetrics
+    created: SystemTime,
+}
+
+impl Histogram {
+    // `bounds` must be sorted ascending; the +Inf bucket is implicit
+    pub fn new(bounds: &[f64]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+            created: SystemTime::now(),
+        }
+    }
+
+    pub fn bounds(&self) -> &[f64] {
+        &self.bounds
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    // Bucket, sum and count samples of the histogram family
+    pub fn write(&self, family: &mut Family) {
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            family.sample_with_suffix("_bucket", &[("le", &format_value(*bound))], *count as f64);
+        }
+        family.sample_with_suffix("_bucket", &[("le", "+Inf")], self.count as f64);
+        family.sample_with_suffix("_sum", &[], self.sum);
+        family.sample_with_suffix("_count", &[], self.count as f64);
+        family.created(&[], self.created);
+    }
+}
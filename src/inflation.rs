@@ -0,0 +1,51 @@
+// The cluster's inflation rate and supply, from `getInflationRate` and
+// `getSupply`. The rate is set per epoch, so both are fetched once after each
+// epoch change (again on the next poll after a failure), and every
+// `supply.refresh_interval` with `[supply]` as the supply keeps changing.
+
+use log::{debug, warn};
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_response::{Response, RpcInflationRate, RpcSupply};
+use std::time::Instant;
+
+#[derive(Default)]
+pub struct Inflation {
+    // Epoch the rate and supply were fetched in
+    epoch: Option<u64>,
+    fetched: Option<Instant>,
+    // Yearly rates, the validators' share included; None until fetched
+    pub rate: Option<RpcInflationRate>,
+    // Lamports in total and circulating; None until fetched
+    pub supply: Option<RpcSupply>,
+}
+
+impl Inflation {
+    // Fetch the rate and supply unless they are known for `epoch` already and
+    // younger than `interval` seconds, if given
+    pub async fn refresh(&mut self, client: &RpcClient, epoch: u64, interval: Option<f64>) {
+        let expired = interval.is_some_and(|interval| self.fetched.is_some_and(|fetched| fetched.elapsed().as_secs_f64() >= interval));
+        if self.epoch == Some(epoch) && !expired {
+            return;
+        }
+        match fetch(client).await {
+            Ok((rate, supply)) => {
+                debug!("Fetched inflation of epoch {}: {} a year, {} lamports in total", rate.epoch, rate.total, supply.total);
+                self.rate = Some(rate);
+                self.supply = Some(supply);
+                self.epoch = Some(epoch);
+                self.fetched = Some(Instant::now());
+            }
+            Err(e) => warn!("Inflation request failed: {}", e),
+        }
+    }
+}
+
+async fn fetch(client: &RpcClient) -> Result<(RpcInflationRate, RpcSupply), String> {
+    let rate = client.get_inflation_rate().await.map_err(|e| e.to_string())?;
+    // Without the accounts that are not circulating, a list of hundreds
+    let params = json!([{ "excludeNonCirculatingAccountsList": true }]);
+    let supply: Response<RpcSupply> = client.send(RpcRequest::GetSupply, params).await.map_err(|e| e.to_string())?;
+    Ok((rate, supply.value))
+}
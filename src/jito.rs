@@ -0,0 +1,108 @@
+// MEV of the watched validators that run the Jito client, read on chain from
+// their tip distribution accounts. Every validator gets one such account per
+// epoch, at an address derived from its vote account and the epoch; the tips
+// of the epoch collect in it until they are paid out after the epoch ends, so
+// its balance above rent is the tips so far. The account also holds the MEV
+// commission the validator set for the epoch.
+
+use crate::config::JitoConfig;
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::MAX_MULTIPLE_ACCOUNTS;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Instant;
+
+// Anchor discriminator, vote account and merkle root upload authority, then
+// the optional merkle root
+const MERKLE_ROOT_OFFSET: usize = 8 + 32 + 32;
+// Root hash, max total claim, max nodes, total claimed and nodes claimed
+const MERKLE_ROOT_LEN: usize = 32 + 4 * 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TipAccount {
+    // Share of the tips the validator keeps, in basis points
+    pub commission_bps: u16,
+    // Lamports of tips collected so far
+    pub tips: u64,
+}
+
+#[derive(Default)]
+pub struct Tips {
+    fetched: Option<Instant>,
+    // Epoch the accounts collect the tips of
+    pub epoch: Option<u64>,
+    // By vote pubkey; validators without an account this epoch are missing
+    pub by_vote: HashMap<String, TipAccount>,
+}
+
+impl Tips {
+    // Fetch the tip distribution accounts of `vote_pubkeys` for `epoch` when
+    // due, or right away in a new epoch; a failure is logged and keeps the
+    // accounts from before
+    pub async fn refresh(&mut self, client: &RpcClient, vote_pubkeys: &[&str], epoch: u64, config: &JitoConfig) {
+        if self.epoch == Some(epoch) && self.fetched.is_some_and(|fetched| fetched.elapsed().as_secs_f64() < config.refresh_interval) {
+            return;
+        }
+        // Checked when the config is loaded
+        let Ok(program) = Pubkey::from_str(&config.program_id) else {
+            return;
+        };
+        let watched: Vec<(&str, Pubkey)> = vote_pubkeys
+            .iter()
+            .filter_map(|&vote_pubkey| {
+                let vote_account = Pubkey::from_str(vote_pubkey).ok()?;
+                let seeds: &[&[u8]] = &[b"TIP_DISTRIBUTION_ACCOUNT", vote_account.as_ref(), &epoch.to_le_bytes()];
+                Some((vote_pubkey, Pubkey::find_program_address(seeds, &program).0))
+            })
+            .collect();
+        let addresses: Vec<Pubkey> = watched.iter().map(|&(_, address)| address).collect();
+        match fetch(client, &addresses).await {
+            Ok(accounts) => {
+                let rent = Rent::default();
+                self.by_vote = watched
+                    .iter()
+                    .zip(accounts)
+                    .filter_map(|(&(vote_pubkey, _), account)| {
+                        let account = account.filter(|account| account.owner == program)?;
+                        let Some(commission_bps) = commission_bps(&account.data) else {
+                            warn!("Tip distribution account of {} has an unknown layout", vote_pubkey);
+                            return None;
+                        };
+                        let tips = account.lamports.saturating_sub(rent.minimum_balance(account.data.len()));
+                        Some((vote_pubkey.to_string(), TipAccount { commission_bps, tips }))
+                    })
+                    .collect();
+                debug!("Fetched tip distribution accounts of epoch {}: {} of {}", epoch, self.by_vote.len(), watched.len());
+                self.epoch = Some(epoch);
+                self.fetched = Some(Instant::now());
+            }
+            Err(e) => warn!("Tip distribution accounts request failed: {}", e),
+        }
+    }
+}
+
+// The accounts at `addresses` in order, in as many requests as
+// `getMultipleAccounts` needs for its limit of accounts per call
+async fn fetch(client: &RpcClient, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>, String> {
+    let mut accounts = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        accounts.extend(client.get_multiple_accounts(chunk).await.map_err(|e| e.to_string())?);
+    }
+    Ok(accounts)
+}
+
+// The commission follows the optional merkle root and the epoch the account
+// was created in
+fn commission_bps(data: &[u8]) -> Option<u16> {
+    let root = match data.get(MERKLE_ROOT_OFFSET)? {
+        0 => 0,
+        1 => MERKLE_ROOT_LEN,
+        _ => return None,
+    };
+    let at = MERKLE_ROOT_OFFSET + 1 + root + 8;
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
@@ -0,0 +1,111 @@
+// Keybase usernames from the validators' on-chain info, checked the way
+// `solana validator-info` does: the user must exist on Keybase and have
+// published `solana/validator-<identity>` in its public folder, which only the
+// owner of the account can do. Results are kept for `keybase.refresh_interval`;
+// a few usernames are checked per poll, so a restart does not burst hundreds
+// of requests.
+
+use crate::config::KeybaseConfig;
+use crate::validator_info::Directory;
+use futures_util::future::join_all;
+use log::{debug, warn};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Usernames checked per poll
+const BATCH_SIZE: usize = 20;
+// Until a failed check is tried again
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+struct Entry {
+    username: String,
+    // None until a check completed
+    verified: Option<bool>,
+    due: Instant,
+}
+
+// Check results by node identity pubkey
+#[derive(Default)]
+pub struct Verifications {
+    entries: HashMap<String, Entry>,
+}
+
+impl Verifications {
+    // The checked username of `identity`, and whether it is verified
+    pub fn get(&self, identity: &str) -> Option<(&str, bool)> {
+        let entry = self.entries.get(identity)?;
+        Some((&entry.username, entry.verified?))
+    }
+
+    // Check the usernames in `directory` that are new, changed or due
+    pub async fn refresh(&mut self, config: &KeybaseConfig, directory: &Directory) {
+        let usernames: HashMap<&String, &str> = directory
+            .iter()
+            .map(|(identity, info)| (identity, info.keybase_username.trim()))
+            .filter(|(_, username)| !username.is_empty())
+            .collect();
+        self.entries.retain(|identity, entry| usernames.get(identity).is_some_and(|username| *username == entry.username));
+
+        let now = Instant::now();
+        let due: Vec<(&String, &str)> = usernames
+            .iter()
+            .filter(|(identity, _)| self.entries.get(**identity).is_none_or(|entry| now >= entry.due))
+            .map(|(identity, username)| (*identity, *username))
+            .take(BATCH_SIZE)
+            .collect();
+        if due.is_empty() {
+            return;
+        }
+        let client = reqwest::Client::new();
+        let results = join_all(due.iter().map(|(identity, username)| verify(&client, config, username, identity))).await;
+        for ((identity, username), result) in due.into_iter().zip(results) {
+            let previous = self.entries.remove(identity).and_then(|entry| entry.verified);
+            let entry = match result {
+                Ok(verified) => {
+                    debug!("Keybase user {} of {}: verified {}", username, identity, verified);
+                    Entry {
+                        username: username.to_string(),
+                        verified: Some(verified),
+                        due: now + Duration::from_secs_f64(config.refresh_interval),
+                    }
+                }
+                Err(e) => {
+                    warn!("Keybase check of {} for {} failed: {}", username, identity, e);
+                    Entry {
+                        username: username.to_string(),
+                        verified: previous,
+                        due: now + RETRY_INTERVAL,
+                    }
+                }
+            };
+            self.entries.insert(identity.clone(), entry);
+        }
+    }
+}
+
+// Whether `username` exists and published the proof for `identity`
+async fn verify(client: &reqwest::Client, config: &KeybaseConfig, username: &str, identity: &str) -> Result<bool, String> {
+    let timeout = Duration::from_secs_f64(config.timeout);
+    let lookup = format!("{}/_/api/1.0/user/lookup.json", config.api_url.trim_end_matches('/'));
+    let response = client
+        .get(&lookup)
+        .query(&[("username", username), ("fields", "basics")])
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let body: Value = response.json().await.map_err(|e| format!("invalid response: {}", e))?;
+    // Status 0 is found, anything else (205 for unknown users) is not
+    if body.pointer("/status/code").and_then(Value::as_i64) != Some(0) {
+        return Ok(false);
+    }
+
+    let proof = format!("{}/{}/solana/validator-{}", config.proof_url.trim_end_matches('/'), username, identity);
+    let response = client.get(&proof).timeout(timeout).send().await.map_err(|e| e.to_string())?;
+    match response.status() {
+        status if status.is_success() => Ok(true),
+        status if status.is_client_error() => Ok(false),
+        status => Err(format!("HTTP {} from {}", status, proof)),
+    }
+}
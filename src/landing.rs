@@ -0,0 +1,80 @@
+// The index page on `/`: links to every endpoint and a summary of the running
+// configuration, like other Prometheus exporters have. RPC URLs are left out,
+// they often carry API keys.
+
+use crate::config::{Config, RpcMode};
+
+pub fn page(config: &Config) -> String {
+    let metrics_path = escape_html(&config.metrics.path);
+    let mut endpoints = format!("<li><a href=\"{0}\">{0}</a> metrics of all clusters</li>\n", metrics_path);
+    for (name, _) in config.clusters() {
+        let path = format!("{}/{}", config.metrics.path.trim_end_matches('/'), name);
+        endpoints.push_str(&format!("<li><a href=\"{0}\">{0}</a> metrics of {1} only</li>\n", escape_html(&path), escape_html(name)));
+    }
+    endpoints.push_str("<li><a href=\"/api/v1/validators\">/api/v1/validators</a> validators of all clusters as JSON</li>\n");
+    endpoints.push_str("<li><a href=\"/api/v1/watchlist\">/api/v1/watchlist</a> watched validators, POST to change them</li>\n");
+    endpoints.push_str("<li><a href=\"/export.csv\">/export.csv</a> validators of all clusters as CSV</li>\n");
+    endpoints.push_str("<li><a href=\"/status\">/status</a> validator table</li>\n");
+    endpoints.push_str("<li><a href=\"/stream\">/stream</a> server-sent events with every update</li>\n");
+    endpoints.push_str("<li>/ws WebSocket with rank and credit changes after every poll</li>\n");
+    endpoints.push_str("<li>/graphql GraphQL queries over the same data</li>\n");
+    endpoints.push_str("<li><a href=\"/healthz\">/healthz</a> liveness</li>\n");
+    endpoints.push_str("<li><a href=\"/readyz\">/readyz</a> readiness, 200 once every cluster has fresh data</li>\n");
+    endpoints.push_str("<li>POST /-/reload-data poll every cluster now</li>\n");
+    endpoints.push_str("<li>POST /-/pause, /-/resume stop and restart the scheduled polls</li>\n");
+    endpoints.push_str("<li>GET, PUT /-/loglevel current log level, change it</li>\n");
+
+    let mut clusters = String::new();
+    for (name, rpc) in config.clusters() {
+        let mode = match rpc.mode {
+            RpcMode::Failover => "failover",
+            RpcMode::RoundRobin => "round-robin",
+        };
+        clusters.push_str(&format!(
+            "<tr><td>{}</td><td>{} endpoint(s), {}, {}s timeout</td></tr>\n",
+            escape_html(name),
+            rpc.endpoints.len(),
+            mode,
+            rpc.timeout
+        ));
+    }
+    let enabled = |on: bool| if on { "on" } else { "off" };
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head><meta charset=\"utf-8\"><title>Solana Credits Exporter</title></head>
+<body>
+<h1>Solana Credits Exporter</h1>
+<p>Version {version}</p>
+<h2>Endpoints</h2>
+<ul>
+{endpoints}</ul>
+<h2>Configuration</h2>
+<table>
+{clusters}<tr><td>poll interval</td><td>{interval}s{adaptive}</td></tr>
+<tr><td>stale after</td><td>{stale_after}s</td></tr>
+<tr><td>TLS</td><td>{tls}</td></tr>
+<tr><td>auth</td><td>{auth}</td></tr>
+</table>
+</body>
+</html>
+",
+        version = env!("CARGO_PKG_VERSION"),
+        endpoints = endpoints,
+        clusters = clusters,
+        interval = config.poll.interval,
+        adaptive = if config.poll.adaptive.enabled { ", adaptive" } else { "" },
+        stale_after = config.metrics.stale_after,
+        tls = enabled(config.server.tls.is_some()),
+        auth = enabled(config.server.auth.is_enabled()),
+    )
+}
+
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
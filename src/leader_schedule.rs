@@ -0,0 +1,52 @@
+// Leader slots of every node identity in the current epoch, from
+// `getLeaderSchedule`. The schedule is fixed for the whole epoch, so it is
+// fetched once per epoch (again on the next poll after a failure).
+
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct LeaderSchedule {
+    // Epoch the schedule is for
+    epoch: Option<u64>,
+    // Slot indexes in the epoch at which each node identity leads, ascending
+    pub by_identity: HashMap<String, Vec<u64>>,
+}
+
+impl LeaderSchedule {
+    // Fetch the schedule of `epoch` unless it is known already
+    pub async fn refresh(&mut self, client: &RpcClient, epoch: u64) {
+        if self.epoch == Some(epoch) {
+            return;
+        }
+        self.by_identity.clear();
+        match client.get_leader_schedule(None).await {
+            Ok(Some(schedule)) => {
+                self.by_identity = schedule
+                    .into_iter()
+                    .map(|(identity, slots)| {
+                        let mut slots: Vec<u64> = slots.into_iter().map(|slot| slot as u64).collect();
+                        slots.sort_unstable();
+                        (identity, slots)
+                    })
+                    .collect();
+                debug!("Fetched leader schedule of epoch {}: {} leaders", epoch, self.by_identity.len());
+                self.epoch = Some(epoch);
+            }
+            Ok(None) => warn!("No leader schedule for epoch {}", epoch),
+            Err(e) => warn!("Leader schedule request failed: {}", e),
+        }
+    }
+
+    // Leader slots of `identity` in `epoch` and the first of them at or after
+    // `slot_index`, if any is left; None while that schedule is not known
+    pub fn position(&self, identity: &str, epoch: u64, slot_index: u64) -> Option<(usize, Option<u64>)> {
+        if self.epoch != Some(epoch) {
+            return None;
+        }
+        let slots = self.by_identity.get(identity).map_or(&[][..], Vec::as_slice);
+        let next = slots[slots.partition_point(|&slot| slot < slot_index)..].first().copied();
+        Some((slots.len(), next))
+    }
+}
@@ -0,0 +1,43 @@
+use hyper::body::HttpBody;
+use hyper::Body;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Number of things in progress, such as open connections or running scrapes,
+// held below a limit that is passed on every use so reloads apply right away
+#[derive(Default)]
+pub struct InFlight {
+    count: AtomicUsize,
+}
+
+impl InFlight {
+    // A slot if fewer than `max` are in progress (0 means no limit); it is
+    // given back when dropped
+    pub fn try_acquire(self: &Arc<Self>, max: usize) -> Option<Slot> {
+        self.count
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| (max == 0 || count < max).then_some(count + 1))
+            .ok()?;
+        Some(Slot(Arc::clone(self)))
+    }
+}
+
+pub struct Slot(Arc<InFlight>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.count.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+// A request body, refused once it grows beyond `max` bytes
+pub async fn read_body(mut body: Body, max: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| format!("cannot read request body: {}", e))?;
+        if bytes.len() + chunk.len() > max {
+            return Err(format!("request body is larger than {} bytes", max));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,53 @@
+// Log lines as the exporter always wrote them: undecorated, since journald
+// and container runtimes add timestamps, informational ones on stdout and
+// warnings and errors on stderr. Only the exporter's own messages are logged,
+// at a level that can change while running.
+
+use crate::config::LogLevel;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error | Level::Warn => eprintln!("{}", record.args()),
+            Level::Info | Level::Debug | Level::Trace => println!("{}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init(level: LogLevel) {
+    // Only fails when called twice
+    let _ = log::set_logger(&LOGGER);
+    set_level(level);
+}
+
+pub fn set_level(level: LogLevel) {
+    log::set_max_level(match level {
+        LogLevel::Error => LevelFilter::Error,
+        LogLevel::Warn => LevelFilter::Warn,
+        LogLevel::Info => LevelFilter::Info,
+        LogLevel::Debug => LevelFilter::Debug,
+    });
+}
+
+pub fn level() -> LogLevel {
+    match log::max_level() {
+        LevelFilter::Off | LevelFilter::Error => LogLevel::Error,
+        LevelFilter::Warn => LogLevel::Warn,
+        LevelFilter::Info => LogLevel::Info,
+        LevelFilter::Debug | LevelFilter::Trace => LogLevel::Debug,
+    }
+}
@@ -1,236 +1,2806 @@
-use hyper::{Body, Request, Response, Server, StatusCode};
-use hyper::service::{make_service_fn, service_fn};
-use solana_client::rpc_client::RpcClient;
+mod admin;
+mod api;
+mod auth;
+mod block_production;
+mod cli;
+mod commission;
+mod config;
+mod exposition;
+mod geoip;
+mod gossip;
+mod graphql;
+mod grpc;
+mod histogram;
+mod inflation;
+mod jito;
+mod keybase;
+mod landing;
+mod leader_schedule;
+mod limits;
+mod logging;
+mod mmdb;
+mod performance;
+mod probe;
+mod quorum;
+mod rewards;
+mod rpc;
+mod sender;
+mod stakewiz;
+mod stats;
+mod status;
+mod stream;
+mod systemd;
+mod tls;
+mod validator_info;
+mod validators_app;
+mod vote_fees;
+mod watchdog;
+mod watchlist;
+mod websocket;
+
+use serde::Serialize;
+use auth::Htpasswd;
+use cli::Args;
+use config::{AccessLog, Config, MetricsConfig, SfdpConfig, StaleAction};
+use exposition::{accepts_gzip, etag_matches, Exposition, Format, MetricType};
+use histogram::Histogram;
+use limits::InFlight;
+use rpc::{slot_lags, Backoff, Endpoint, EndpointPool};
+use sender::RateLimited;
+use hyper::header::{ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_NONE_MATCH, RETRY_AFTER, VARY, WWW_AUTHENTICATE};
+use hyper::{Body, Request, Response, StatusCode};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use log::{debug, error, info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_response::RpcVoteAccountStatus;
+use solana_sdk::clock::DEFAULT_MS_PER_SLOT;
+use solana_sdk::epoch_info::EpochInfo;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::task;
-use tokio::time::{sleep, timeout};
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::task::{self, JoinSet};
+use tokio::time::{interval, sleep, timeout, MissedTickBehavior};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch, Mutex};
+use watchdog::{Heartbeat, RestartCounter};
+use watchlist::{WatchedValidator, Watchlist};
+use tls::ReloadingTls;
+use validator_info::Directory;
+use validators_app::{AppValidator, Listing};
+use block_production::BlockProduction;
+use commission::{Changes, Tracker};
+use geoip::{GeoIp, Location};
+use gossip::{Gossip, Node};
+use inflation::Inflation;
+use jito::{TipAccount, Tips};
+use keybase::Verifications;
+use leader_schedule::LeaderSchedule;
+use performance::{Performance, Rates};
+use probe::{Probe, Prober};
+use quorum::{Answer, Disagreement};
+use rewards::{Reward, Rewards};
+use stakewiz::Scores;
+use vote_fees::VoteFees;
 use std::error::Error;
+use std::io::Write;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use std::convert::Infallible;
+use std::process;
 
-#[derive(Debug, Clone)]
+// Log target of the access log lines, at info level like the exporter's others
+const ACCESS_LOG_TARGET: &str = concat!(env!("CARGO_CRATE_NAME"), "::access");
+
+// Time a client gets to complete the TLS handshake
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Credits a vote earns with Timely Vote Credits when it lands within the grace
+// slots, the most a validator can earn per slot
+const MAX_CREDITS_PER_SLOT: f64 = 16.0;
+// Slots a vote may take to land and still earn them; every slot later earns
+// one credit less, down to one
+const VOTE_CREDITS_GRACE_SLOTS: f64 = 2.0;
+
+#[derive(Debug, Clone, Serialize)]
 struct ValidatorMetrics {
     pub vote_pubkey: String,
+    // Identity key of the node voting with the account
+    pub node_pubkey: String,
+    // Lamports delegated to the vote account, active this epoch
+    pub activated_stake: u64,
+    // Share of the cluster's activated stake, 0 to 1
+    pub stake_share: f64,
+    // Credits earned per slot of the epoch so far, None while unknown
+    pub credits_per_slot: Option<f64>,
+    // Credits at the end of the epoch if that rate holds, None while unknown
+    pub projected_credits: Option<f64>,
+    // Credits earned as a percentage of the most possible in the slots elapsed,
+    // None while unknown
+    pub vote_efficiency: Option<f64>,
+    // Average slots its votes took to land, estimated from the credits earned
+    // per slot; None while unknown
+    pub vote_latency: Option<f64>,
+    // Percentage of the rewards the validator keeps
+    pub commission: u8,
+    // In the delinquent vote accounts rather than the current ones
+    pub delinquent: bool,
+    // Epoch the credits were earned in
+    pub epoch: u64,
+    // (epoch, credits earned) of the earlier epochs `getVoteAccounts` returns,
+    // oldest first
+    pub history: Vec<(u64, u64)>,
     pub root_distance: u64,
     pub vote_distance: u64,
     pub credits_earned: u64,
     pub rank: usize,
 }
 
+// Everything collected by one successful poll
+#[derive(Debug, Clone)]
+struct PollData {
+    // Every validator that earned credits this epoch, delinquent or not, in rank
+    // order, then those that earned none with `metrics.include_zero_credit`
+    pub validators: Vec<ValidatorMetrics>,
+    // Those that earned credits and are not delinquent
+    pub active_count: usize,
+    // Vote accounts that earned no credits this epoch, included or not
+    pub zero_credit_current: usize,
+    pub zero_credit_delinquent: usize,
+    // Vote pubkeys of every validator that is not delinquent, earning credits or not
+    pub current: HashSet<String>,
+    // Node identity of every vote account, delinquent ones included
+    pub nodes: HashMap<String, String>,
+    // Commission of every vote account, delinquent ones included
+    pub commissions: HashMap<String, u8>,
+    // Activated stake of every vote account, delinquent ones included
+    pub stakes: HashMap<String, u64>,
+    // Credits every vote account earned in the last completed epoch, delinquent
+    // ones included; missing for those that earned none
+    pub last_epoch_credits: HashMap<String, u64>,
+    // Missing when `getEpochInfo` failed while the vote accounts were fetched
+    pub epoch_info: Option<EpochInfo>,
+}
+
 #[derive(Debug, Clone)]
 struct MetricsCache {
-    pub data: String,
+    pub data: Exposition,
+    // When the last successful poll finished, None until the first one
+    pub updated: Option<Instant>,
+    // Wall clock time of the same poll, for `time() - metric` alerts
+    pub updated_at: Option<SystemTime>,
+    // When the last poll started, and the last successful one; a poll asked for
+    // through `/-/reload-data` is done once the first is past the request
+    pub attempted: Option<Instant>,
+    pub polled: Option<Instant>,
+    // Validators and epoch of the last successful poll, for the JSON API
+    pub validators: Vec<ValidatorMetrics>,
+    pub epoch_info: Option<EpochInfo>,
+    // Scheduled polls are skipped, set through `/-/pause`
+    pub paused: bool,
 }
 
 impl MetricsCache {
     fn new() -> Self {
         Self {
-            data: String::new(),
+            data: Exposition::new(),
+            updated: None,
+            updated_at: None,
+            attempted: None,
+            polled: None,
+            validators: Vec::new(),
+            epoch_info: None,
+            paused: false,
         }
     }
+
+    // Seconds since the last successful poll, measured now
+    fn age(&self) -> Option<f64> {
+        self.updated.map(|updated| updated.elapsed().as_secs_f64())
+    }
+
+    // Older than `stale_after` seconds (0 = never stale), or never updated at all
+    fn is_stale(&self, stale_after: f64) -> bool {
+        stale_after > 0.0 && self.age().is_none_or(|age| age > stale_after)
+    }
+}
+
+// Latest data of every monitored cluster, by cluster name
+type SharedCache = Arc<Mutex<BTreeMap<String, MetricsCache>>>;
+
+// What every HTTP connection and request handler works with
+#[derive(Clone)]
+struct HttpState {
+    cache: SharedCache,
+    config: watch::Receiver<Arc<Config>>,
+    // Open connections across all listeners
+    connections: Arc<InFlight>,
+    // Scrapes being rendered
+    scrapes: Arc<InFlight>,
+    // Name of each cluster whose cache was just updated, for `/stream` and `/ws`
+    updates: broadcast::Sender<String>,
+    // Name of a cluster to poll right away, for `/-/reload-data`
+    refresh: broadcast::Sender<String>,
+    watchlist: Arc<Watchlist>,
+    // Basic auth users, kept between requests
+    htpasswd: Arc<Htpasswd>,
 }
 
-fn fetch_and_calculate_metrics(client: &RpcClient) -> Result<(Vec<ValidatorMetrics>, usize), Box<dyn Error + Send + Sync>> {
-    let vote_accounts = client.get_vote_accounts()?;
+// The vote accounts, and the epoch unless `getEpochInfo` failed
+async fn fetch_vote_accounts(client: &RpcClient) -> Result<(RpcVoteAccountStatus, Option<EpochInfo>), Box<dyn Error + Send + Sync>> {
+    let (vote_accounts, epoch_info) = tokio::join!(client.get_vote_accounts(), client.get_epoch_info());
+    Ok((vote_accounts?, epoch_info.ok()))
+}
+
+fn calculate_metrics(vote_accounts: &RpcVoteAccountStatus, epoch_info: Option<EpochInfo>, include_zero_credit: bool) -> PollData {
+    let epoch = epoch_info.as_ref().map(|info| info.epoch);
     let top_root_slot = vote_accounts.current.iter().map(|v| v.root_slot).max().unwrap_or(0);
     let top_vote_slot = vote_accounts.current.iter().map(|v| v.last_vote).max().unwrap_or(0);
     let mut validator_metrics: Vec<ValidatorMetrics> = Vec::new();
     let mut active_count = 0;
+    let (mut zero_credit_current, mut zero_credit_delinquent) = (0, 0);
+    let current = vote_accounts.current.iter().map(|account| account.vote_pubkey.clone()).collect();
+    let nodes = vote_accounts
+        .current
+        .iter()
+        .chain(&vote_accounts.delinquent)
+        .map(|account| (account.vote_pubkey.clone(), account.node_pubkey.clone()))
+        .collect();
+    let commissions = vote_accounts
+        .current
+        .iter()
+        .chain(&vote_accounts.delinquent)
+        .map(|account| (account.vote_pubkey.clone(), account.commission))
+        .collect();
+    let stakes: HashMap<String, u64> = vote_accounts
+        .current
+        .iter()
+        .chain(&vote_accounts.delinquent)
+        .map(|account| (account.vote_pubkey.clone(), account.activated_stake))
+        .collect();
+    let last_epoch = epoch.and_then(|epoch| epoch.checked_sub(1));
+    let last_epoch_credits = vote_accounts
+        .current
+        .iter()
+        .chain(&vote_accounts.delinquent)
+        .filter_map(|account| {
+            let &(_, credits, previous_credits) = account.epoch_credits.iter().find(|(entry_epoch, _, _)| Some(*entry_epoch) == last_epoch)?;
+            Some((account.vote_pubkey.clone(), credits.saturating_sub(previous_credits)))
+        })
+        .collect();
 
-    for account in vote_accounts.current {
-        // Ensure the validator has epoch credits
-        if let Some((_, current_credits, _)) = account.epoch_credits.last() {
-            // Check if there are at least two entries in epoch_credits to compare current and previous credits
-            if let Some((_, previous_credits, _)) = account.epoch_credits.iter().rev().nth(1) {
-                if *current_credits > *previous_credits {
-                    active_count += 1;
-                    let root_distance = top_root_slot.saturating_sub(account.root_slot);
-                    let vote_distance = top_vote_slot.saturating_sub(account.last_vote);
-
-                    let credits_earned = *current_credits - *previous_credits;
-
-                    validator_metrics.push(ValidatorMetrics {
-                        vote_pubkey: account.vote_pubkey.clone(),
-                        root_distance,
-                        vote_distance,
-                        credits_earned,
-                        rank: 0,
-                    });
-                }
+    // Delinquent validators that earned credits this epoch are ranked too,
+    // their distances measured against the current ones
+    let accounts = vote_accounts
+        .current
+        .iter()
+        .map(|account| (account, false))
+        .chain(vote_accounts.delinquent.iter().map(|account| (account, true)));
+    for (account, delinquent) in accounts {
+        // The entry of the current epoch only appears with the validator's
+        // first vote in it; until then the latest one is from an earlier
+        // epoch, and none were earned yet instead of that epoch's credits
+        let (credits_epoch, credits_earned) = match account.epoch_credits.last() {
+            Some(&(entry_epoch, credits, previous_credits)) if epoch.is_none_or(|epoch| entry_epoch == epoch) => {
+                (entry_epoch, credits.saturating_sub(previous_credits))
+            }
+            _ => (epoch.unwrap_or_default(), 0),
+        };
+        if credits_earned > 0 {
+            active_count += !delinquent as usize;
+        } else {
+            if delinquent {
+                zero_credit_delinquent += 1;
+            } else {
+                zero_credit_current += 1;
+            }
+            if !include_zero_credit {
+                continue;
             }
         }
+        let root_distance = top_root_slot.saturating_sub(account.root_slot);
+        let vote_distance = top_vote_slot.saturating_sub(account.last_vote);
+        let history = account
+            .epoch_credits
+            .iter()
+            .filter(|(entry_epoch, _, _)| *entry_epoch < credits_epoch)
+            .map(|&(entry_epoch, credits, previous_credits)| (entry_epoch, credits.saturating_sub(previous_credits)))
+            .collect();
+
+        validator_metrics.push(ValidatorMetrics {
+            vote_pubkey: account.vote_pubkey.clone(),
+            node_pubkey: account.node_pubkey.clone(),
+            activated_stake: account.activated_stake,
+            stake_share: 0.0,
+            credits_per_slot: None,
+            projected_credits: None,
+            vote_efficiency: None,
+            vote_latency: None,
+            commission: account.commission,
+            delinquent,
+            epoch: credits_epoch,
+            history,
+            root_distance,
+            vote_distance,
+            credits_earned,
+            rank: 0,
+        });
     }
 
-    // Sort validators by credits earned and assign rank
-    validator_metrics.sort_by(|a, b| b.credits_earned.cmp(&a.credits_earned));
+    // Sort validators by credits earned and assign rank, those without any last
+    validator_metrics.sort_by_key(|v| std::cmp::Reverse(v.credits_earned));
     for (rank, validator) in validator_metrics.iter_mut().enumerate() {
         validator.rank = rank + 1;
     }
 
-    Ok((validator_metrics, active_count))
+    // Comparable at any point of the epoch, unlike the credits themselves
+    if let Some(info) = &epoch_info {
+        if info.slot_index > 0 {
+            for validator in validator_metrics.iter_mut().filter(|validator| validator.epoch == info.epoch) {
+                let rate = validator.credits_earned as f64 / info.slot_index as f64;
+                validator.credits_per_slot = Some(rate);
+                validator.projected_credits = Some(rate * info.slots_in_epoch as f64);
+                validator.vote_efficiency = Some(rate / MAX_CREDITS_PER_SLOT * 100.0);
+                // As if it voted on every slot, so missed votes count as late ones
+                let lost = MAX_CREDITS_PER_SLOT - rate.clamp(1.0, MAX_CREDITS_PER_SLOT);
+                validator.vote_latency = Some(VOTE_CREDITS_GRACE_SLOTS + lost);
+            }
+        }
+    }
+
+    // Shares of the stake of every vote account, delinquent ones too
+    let total_stake: u64 = stakes.values().sum();
+    if total_stake > 0 {
+        for validator in &mut validator_metrics {
+            validator.stake_share = validator.activated_stake as f64 / total_stake as f64;
+        }
+    }
+
+    PollData {
+        validators: validator_metrics,
+        active_count,
+        zero_credit_current,
+        zero_credit_delinquent,
+        current,
+        nodes,
+        commissions,
+        stakes,
+        last_epoch_credits,
+        epoch_info,
+    }
 }
 
-fn export_prometheus_metrics(
-    validators: Vec<ValidatorMetrics>,
-    active_count: usize,
-    rpc_status: u8,
-    rpc_duration: f64,
-    rpc_timeout: u8,
-) -> String {
-    let mut output = String::new();
-
-    // Rating position for each validator (Rank)
-    output.push_str("# HELP solana_validator_rating_position Rating position of each validator\n");
-    output.push_str("# TYPE solana_validator_rating_position gauge\n");
-    for validator in &validators {
-        output.push_str(&format!(
-            "solana_validator_rating_position{{identity=\"{}\"}} {}\n",
-            validator.vote_pubkey, validator.rank
-        ));
+// State of the collection loop and data source, exported next to the validator metrics
+struct RpcReport<'a> {
+    pub poll_interval: Duration,
+    pub status: u8,
+    pub durations: &'a Histogram,
+    pub timeout: u8,
+    pub last_endpoint: Option<&'a str>,
+    pub endpoints: &'a [Endpoint],
+    pub backoff: &'a Backoff,
+    pub restarts: &'a RestartCounter,
+}
+
+// What is known about validators beyond their vote accounts, each empty
+// unless its source is enabled
+struct Enrichment<'a> {
+    // On-chain validator info, by node identity
+    pub directory: &'a Directory,
+    // validators.app, by vote pubkey
+    pub app: &'a HashMap<String, AppValidator>,
+    pub keybase: &'a Verifications,
+    // Gossip address locations, by node identity
+    pub locations: &'a HashMap<String, Location>,
+    // What the nodes advertise in gossip, by node identity, with `versions`
+    pub nodes: &'a HashMap<String, Node>,
+}
+
+impl Enrichment<'_> {
+    // The name published on chain, else the one on validators.app
+    fn name(&self, vote_pubkey: &str, node_pubkey: &str) -> Option<&str> {
+        self.directory
+            .get(node_pubkey)
+            .map(|info| info.name.as_str())
+            .filter(|name| !name.is_empty())
+            .or_else(|| self.app.get(vote_pubkey)?.name.as_deref().filter(|name| !name.is_empty()))
+    }
+
+    // The validator client a node runs: as validators.app detected it, else
+    // from the version it advertises in gossip, which cannot tell Jito from
+    // Agave without validators.app's Jito flag; empty when unknown
+    fn client(&self, vote_pubkey: &str, node_pubkey: &str) -> &'static str {
+        let listed = self.app.get(vote_pubkey);
+        match listed.and_then(|listed| listed.software_client.as_deref()).map(str::to_ascii_lowercase).as_deref() {
+            Some("agave") => return "agave",
+            Some("jitolabs" | "jito") => return "jito",
+            Some("firedancer" | "frankendancer") => return "firedancer",
+            Some("solanalabs") => return "solana-labs",
+            _ => {}
+        }
+        let Some(version) = self.nodes.get(node_pubkey).and_then(|node| node.version.as_deref()) else {
+            return "";
+        };
+        let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+        match (parts.next().flatten(), parts.next().flatten()) {
+            // Firedancer and Frankendancer count their releases from 0
+            (Some(0), _) => "firedancer",
+            // Jito forks the Agave (before 1.18 Solana Labs) releases and keeps their versions
+            _ if listed.is_some_and(|listed| listed.jito == Some(true)) => "jito",
+            (Some(1), Some(minor)) if minor < 18 => "solana-labs",
+            (Some(_), _) => "agave",
+            _ => "",
+        }
+    }
+}
+
+// Keyed by vote account, like the validator's credits on chain, with whether
+// it is current or delinquent, plus its name when `validator_info` or
+// `validators_app` know it
+fn validator_labels<'a>(validator: &'a ValidatorMetrics, known: &'a Enrichment) -> Vec<(&'a str, &'a str)> {
+    let status = if validator.delinquent { "delinquent" } else { "current" };
+    let mut labels = vec![
+        ("vote_pubkey", validator.vote_pubkey.as_str()),
+        ("identity", validator.node_pubkey.as_str()),
+        ("status", status),
+    ];
+    if let Some(name) = known.name(&validator.vote_pubkey, &validator.node_pubkey) {
+        labels.push(("name", name));
+    }
+    labels
+}
+
+fn export_validator_metrics(output: &mut Exposition, validators: &[&ValidatorMetrics], known: &Enrichment, settings: &MetricsConfig) {
+    // Rank of each validator
+    let rank = output.family("solana_validator_rank", "Rank of each validator by credits earned this epoch (1=most)", MetricType::Gauge);
+    for validator in validators {
+        rank.sample(&validator_labels(validator, known), validator.rank as f64);
     }
 
     // Root distance for each validator
-    output.push_str("# HELP solana_validator_root_distance Root distance for each validator\n");
-    output.push_str("# TYPE solana_validator_root_distance gauge\n");
-    for validator in &validators {
-        output.push_str(&format!(
-            "solana_validator_root_distance{{identity=\"{}\"}} {}\n",
-            validator.vote_pubkey, validator.root_distance
-        ));
+    let root_distance = output.family("solana_validator_root_distance", "Root distance for each validator", MetricType::Gauge);
+    for validator in validators {
+        root_distance.sample(&validator_labels(validator, known), validator.root_distance as f64);
     }
 
     // Vote distance for each validator
-    output.push_str("# HELP solana_validator_vote_distance Vote distance for each validator\n");
-    output.push_str("# TYPE solana_validator_vote_distance gauge\n");
-    for validator in &validators {
-        output.push_str(&format!(
-            "solana_validator_vote_distance{{identity=\"{}\"}} {}\n",
-            validator.vote_pubkey, validator.vote_distance
-        ));
+    let vote_distance = output.family("solana_validator_vote_distance", "Vote distance for each validator", MetricType::Gauge);
+    for validator in validators {
+        vote_distance.sample(&validator_labels(validator, known), validator.vote_distance as f64);
     }
 
     // Credits earned for each validator
-    output.push_str("# HELP solana_validator_credits_so_far Credits earned by each validator\n");
-    output.push_str("# TYPE solana_validator_credits_so_far gauge\n");
-    for validator in &validators {
-        output.push_str(&format!(
-            "solana_validator_credits_so_far{{identity=\"{}\"}} {}\n",
-            validator.vote_pubkey, validator.credits_earned
-        ));
+    let credits = output.family("solana_validator_credits", "Credits earned by each validator this epoch", MetricType::Gauge);
+    for validator in validators {
+        let epoch = validator.epoch.to_string();
+        let mut labels = validator_labels(validator, known);
+        labels.push(("epoch", &epoch));
+        credits.sample(&labels, validator.credits_earned as f64);
+    }
+
+    // How the last full epoch went against the one before, in percent; the
+    // current epoch is not over, so it is left out
+    let change = output.family(
+        "solana_validator_credits_change_percent",
+        "Change in credits earned by each validator in its last completed epoch against the one before, in percent",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        if let [.., (before_epoch, before), (epoch, last)] = validator.history.as_slice() {
+            // Not when the validator sat an epoch out in between
+            if *before > 0 && before_epoch + 1 == *epoch {
+                let epoch = epoch.to_string();
+                let mut labels = validator_labels(validator, known);
+                labels.push(("epoch", &epoch));
+                change.sample(&labels, (*last as f64 - *before as f64) / *before as f64 * 100.0);
+            }
+        }
     }
 
-    // Top validator metrics
-    output.push_str("# HELP solana_validator_top_1 Credits earned by the top 1 validator\n");
-    output.push_str("# TYPE solana_validator_top_1 gauge\n");
-    if let Some(top_1) = validators.get(0) {
-        output.push_str(&format!("solana_validator_top_1 {}\n", top_1.credits_earned));
+    // Credits of the earlier epochs, to compare with; several series per
+    // validator, so only with `metrics.epoch_history`
+    if settings.epoch_history {
+        let history = output.family(
+            "solana_validator_epoch_credits",
+            "Credits earned by each validator in the earlier epochs getVoteAccounts returns",
+            MetricType::Gauge,
+        );
+        for validator in validators {
+            for (epoch, credits) in &validator.history {
+                let epoch = epoch.to_string();
+                let mut labels = validator_labels(validator, known);
+                labels.push(("epoch", &epoch));
+                history.sample(&labels, *credits as f64);
+            }
+        }
     }
 
-    output.push_str("# HELP solana_validator_top_100 Credits earned by the top 100 validator\n");
-    output.push_str("# TYPE solana_validator_top_100 gauge\n");
-    if let Some(top_100) = validators.get(99) {
-        output.push_str(&format!("solana_validator_top_100 {}\n", top_100.credits_earned));
+    // Credits per slot of each validator
+    let per_slot = output.family(
+        "solana_validator_credits_per_slot",
+        "Credits earned by each validator this epoch per slot elapsed in it",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        if let Some(rate) = validator.credits_per_slot {
+            per_slot.sample(&validator_labels(validator, known), rate);
+        }
     }
 
-    output.push_str("# HELP solana_validator_top_200 Credits earned by the top 200 validator\n");
-    output.push_str("# TYPE solana_validator_top_200 gauge\n");
-    if let Some(top_200) = validators.get(199) {
-        output.push_str(&format!("solana_validator_top_200 {}\n", top_200.credits_earned));
+    // How close each validator comes to voting on every slot in time
+    let efficiency = output.family(
+        "solana_validator_vote_efficiency_percent",
+        "Credits earned by each validator this epoch as a percentage of the most possible in the slots elapsed",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        if let Some(percent) = validator.vote_efficiency {
+            efficiency.sample(&validator_labels(validator, known), percent);
+        }
+    }
+
+    // What the operator tunes to earn more of them
+    let latency = output.family(
+        "solana_validator_vote_latency_slots",
+        "Average slots the votes of each validator took to land this epoch, estimated from its credits per slot",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        if let Some(slots) = validator.vote_latency {
+            latency.sample(&validator_labels(validator, known), slots);
+        }
     }
 
+    // Commission of each validator, for delegators
+    let commission = output.family("solana_validator_commission", "Commission of each validator in percent", MetricType::Gauge);
+    for validator in validators {
+        commission.sample(&validator_labels(validator, known), validator.commission as f64);
+    }
+
+    // Stake of each validator, to weigh its rank by
+    let stake = output.family(
+        "solana_validator_activated_stake_lamports",
+        "Stake delegated to each validator and active this epoch, in lamports",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        stake.sample(&validator_labels(validator, known), validator.activated_stake as f64);
+    }
+    let stake_share = output.family(
+        "solana_validator_stake_share",
+        "Share of the cluster's activated stake delegated to each validator (0 to 1)",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        stake_share.sample(&validator_labels(validator, known), validator.stake_share);
+    }
+
+    // What validators published about themselves, for tables and links
+    let info = output.family("solana_validator_info", "Validator info published on chain, always 1", MetricType::Gauge);
+    for validator in validators {
+        if let Some(published) = known.directory.get(&validator.node_pubkey) {
+            info.sample(
+                &[
+                    ("vote_pubkey", &validator.vote_pubkey),
+                    ("identity", &validator.node_pubkey),
+                    ("name", &published.name),
+                    ("website", &published.website),
+                    ("details", &published.details),
+                ],
+                1.0,
+            );
+        }
+    }
+
+    // What validators.app knows about them; the score is a value, not a
+    // label, so its changes do not start new series
+    let app_info = output.family(
+        "solana_validator_validators_app_info",
+        "Validator name and data center on validators.app, always 1",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        if let Some(listed) = known.app.get(&validator.vote_pubkey) {
+            app_info.sample(
+                &[
+                    ("vote_pubkey", &validator.vote_pubkey),
+                    ("identity", &validator.node_pubkey),
+                    ("name", listed.name.as_deref().unwrap_or_default()),
+                    ("data_center", listed.data_center_key.as_deref().unwrap_or_default()),
+                ],
+                1.0,
+            );
+        }
+    }
+    let app_score = output.family("solana_validator_validators_app_score", "Total score of the validator on validators.app", MetricType::Gauge);
+    for validator in validators {
+        if let Some(score) = known.app.get(&validator.vote_pubkey).and_then(|listed| listed.total_score) {
+            app_score.sample(&validator_labels(validator, known), score as f64);
+        }
+    }
+
+    // Whether the Keybase username in the validator info checked out
+    let keybase = output.family(
+        "solana_validator_keybase_verified",
+        "Whether the validator's Keybase user published a proof for its identity (1) or not (0)",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        if let Some((username, verified)) = known.keybase.get(&validator.node_pubkey) {
+            let mut labels = validator_labels(validator, known);
+            labels.push(("keybase_username", username));
+            keybase.sample(&labels, verified as u8 as f64);
+        }
+    }
+
+    // Where the validator's gossip address is, as labels to join on
+    let location = output.family(
+        "solana_validator_location_info",
+        "Country and autonomous system of the validator's gossip address, always 1",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        if let Some(located) = known.locations.get(&validator.node_pubkey) {
+            let asn = located.asn.map(|asn| asn.to_string()).unwrap_or_default();
+            let mut labels = validator_labels(validator, known);
+            labels.extend([("country", located.country.as_str()), ("asn", asn.as_str()), ("as_org", located.as_org.as_str())]);
+            location.sample(&labels, 1.0);
+        }
+    }
+
+    // What each validator runs, as labels to join on
+    let software = output.family(
+        "solana_validator_software_info",
+        "Software version and client of the validator's node, always 1",
+        MetricType::Gauge,
+    );
+    for validator in validators {
+        if let Some(node) = known.nodes.get(&validator.node_pubkey) {
+            let mut labels = validator_labels(validator, known);
+            labels.extend([
+                ("version", node.version.as_deref().unwrap_or_default()),
+                ("client", known.client(&validator.vote_pubkey, &validator.node_pubkey)),
+            ]);
+            software.sample(&labels, 1.0);
+        }
+    }
+}
+
+fn export_prometheus_metrics(
+    validators: &[ValidatorMetrics],
+    active_count: usize,
+    watched: &[WatchedValidator],
+    known: &Enrichment,
+    settings: &MetricsConfig,
+    rpc: &RpcReport,
+) -> Exposition {
+    let mut output = Exposition::new();
+
+    // Per-validator families, unless only aggregates are wanted. Past
+    // `metrics.max_validators` only the best ranked and the watched ones are
+    // kept (the list is in rank order).
+    let max = settings.max_validators;
+    let truncated = settings.per_validator && max > 0 && validators.len() > max;
+    if settings.per_validator {
+        let exported: Vec<&ValidatorMetrics> = validators
+            .iter()
+            .enumerate()
+            .filter(|(i, validator)| {
+                !truncated || *i < max || watched.iter().any(|listed| listed.vote_pubkey == validator.vote_pubkey)
+            })
+            .map(|(_, validator)| validator)
+            .collect();
+        export_validator_metrics(&mut output, &exported, known, settings);
+    }
+    let series_truncated = output.family(
+        "solana_exporter_series_truncated",
+        "Whether validators were left out of the per-validator metrics by metrics.max_validators (1) or not (0)",
+        MetricType::Gauge,
+    );
+    series_truncated.sample(&[], truncated as u8 as f64);
+
+    // The cluster aggregates are over the validators that earned credits,
+    // ranked ahead of any with `metrics.include_zero_credit`
+    let validators = &validators[..validators.partition_point(|validator| validator.credits_earned > 0)];
+
+    // Credits at each rank of `metrics.top_ranks`
+    for rank in &settings.top_ranks {
+        let top = output.family(
+            &format!("solana_validator_top_{}", rank),
+            &format!("Credits earned by the top {} validator", rank),
+            MetricType::Gauge,
+        );
+        if let Some(validator) = validators.get(rank - 1) {
+            top.sample(&[], validator.credits_earned as f64);
+        }
+    }
+
+    // The pace to keep up with
+    let leader_per_slot = output.family(
+        "solana_validator_leader_credits_per_slot",
+        "Credits earned this epoch per slot elapsed in it by the top ranked validator",
+        MetricType::Gauge,
+    );
+    if let Some(rate) = validators.first().and_then(|validator| validator.credits_per_slot) {
+        leader_per_slot.sample(&[], rate);
+    }
+
+    // Where the top and the middle of the cluster end the epoch at their pace
+    let leader_projected = output.family(
+        "solana_validator_leader_projected_credits",
+        "Credits the top ranked validator ends the epoch with if it keeps its rate",
+        MetricType::Gauge,
+    );
+    if let Some(projected) = validators.first().and_then(|validator| validator.projected_credits) {
+        leader_projected.sample(&[], projected);
+    }
+    let median_projected = output.family(
+        "solana_validator_median_projected_credits",
+        "Median of the credits the active validators end the epoch with if they keep their rates",
+        MetricType::Gauge,
+    );
+    let mut projected: Vec<u64> = validators.iter().filter_map(|validator| validator.projected_credits).map(|credits| credits as u64).collect();
+    projected.sort_unstable();
+    if let Some(median) = stats::quantile(&projected, 0.5) {
+        median_projected.sample(&[], median);
+    }
+
+    // The floor of the active set: every listed validator earned credits, so
+    // this is the last one
+    let last_active = output.family(
+        "solana_validator_last_active_credits",
+        "Credits earned this epoch by the lowest ranked validator that earned any",
+        MetricType::Gauge,
+    );
+    if let Some(validator) = validators.last() {
+        last_active.sample(&[], validator.credits_earned as f64);
+    }
+
+    // Where the credits of the active validators lie, to judge a validator
+    // against the cluster instead of fixed ranks
+    let quantiles = output.family(
+        "solana_validator_credits_quantile",
+        "Credits earned this epoch at each quantile of the active validators",
+        MetricType::Gauge,
+    );
+    let mut credits: Vec<u64> = validators.iter().map(|validator| validator.credits_earned).collect();
+    credits.sort_unstable();
+    for (label, q) in [("0.5", 0.5), ("0.75", 0.75), ("0.9", 0.9), ("0.95", 0.95), ("0.99", 0.99)] {
+        if let Some(value) = stats::quantile(&credits, q) {
+            quantiles.sample(&[("quantile", label)], value);
+        }
+    }
+
+    // How far from average a validator is, without heavy PromQL
+    let mean = output.family("solana_validator_credits_mean", "Mean credits earned this epoch by the active validators", MetricType::Gauge);
+    if let Some(value) = stats::mean(&credits) {
+        mean.sample(&[], value);
+    }
+    let stddev = output.family(
+        "solana_validator_credits_stddev",
+        "Standard deviation of the credits earned this epoch by the active validators",
+        MetricType::Gauge,
+    );
+    if let Some(value) = stats::stddev(&credits) {
+        stddev.sample(&[], value);
+    }
+    let trimmed_mean = output.family(
+        "solana_validator_credits_trimmed_mean",
+        "Mean credits earned this epoch by the active validators, without the highest and lowest metrics.trim_fraction",
+        MetricType::Gauge,
+    );
+    if let Some(value) = stats::trimmed_mean(&credits, settings.trim_fraction) {
+        trimmed_mean.sample(&[], value);
+    }
+
+    // Votes count by stake in consensus, so this is the cluster's performance
+    // as it matters there
+    let weighted_mean = output.family(
+        "solana_validator_credits_stake_weighted_mean",
+        "Mean credits earned this epoch by the active validators, weighted by their activated stake",
+        MetricType::Gauge,
+    );
+    let weighted: Vec<(u64, u64)> = validators.iter().map(|validator| (validator.credits_earned, validator.activated_stake)).collect();
+    if let Some(value) = stats::weighted_mean(&weighted) {
+        weighted_mean.sample(&[], value);
+    }
+
+    // Recomputed on every poll, for heatmaps of the whole cluster over time
+    let mut distribution = Histogram::new(&settings.credits_buckets);
+    for validator in validators {
+        distribution.observe(validator.credits_earned as f64);
+    }
+    distribution.write(output.family(
+        "solana_validator_credits_distribution",
+        "Active validators by credits earned this epoch",
+        MetricType::Histogram,
+    ));
+
     // Active validator count
-    output.push_str("# HELP solana_validator_active Total number of active validators\n");
-    output.push_str("# TYPE solana_validator_active gauge\n");
-    output.push_str(&format!("solana_validator_active {}\n", active_count));
+    output
+        .family("solana_validator_active", "Total number of active validators", MetricType::Gauge)
+        .sample(&[], active_count as f64);
+
+    // How the active validators and their stake spread over countries,
+    // networks and data centers, to follow how concentrated the cluster's
+    // infrastructure is; unknown ones go under empty labels
+    let total_stake: u64 = validators.iter().map(|validator| validator.activated_stake).sum();
+    if !known.locations.is_empty() {
+        let mut countries: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut networks: BTreeMap<(String, &str), (u64, u64)> = BTreeMap::new();
+        for validator in validators {
+            let located = known.locations.get(&validator.node_pubkey);
+            *countries.entry(located.map_or("", |located| located.country.as_str())).or_default() += 1;
+            let asn = located.and_then(|located| located.asn).map(|asn| asn.to_string()).unwrap_or_default();
+            let network = networks.entry((asn, located.map_or("", |located| located.as_org.as_str()))).or_default();
+            network.0 += 1;
+            network.1 += validator.activated_stake;
+        }
+        let by_country = output.family(
+            "solana_validator_active_by_country",
+            "Active validators by country of their gossip address",
+            MetricType::Gauge,
+        );
+        for (country, count) in &countries {
+            by_country.sample(&[("country", country)], *count as f64);
+        }
+        let by_asn = output.family(
+            "solana_validator_active_by_asn",
+            "Active validators by autonomous system of their gossip address",
+            MetricType::Gauge,
+        );
+        for ((asn, as_org), (count, _)) in &networks {
+            by_asn.sample(&[("asn", asn), ("as_org", as_org)], *count as f64);
+        }
+        let stake_by_asn = output.family(
+            "solana_validator_stake_share_by_asn",
+            "Share of the active validators' stake by autonomous system of their gossip address (0 to 1)",
+            MetricType::Gauge,
+        );
+        for ((asn, as_org), (_, stake)) in &networks {
+            if total_stake > 0 {
+                stake_by_asn.sample(&[("asn", asn), ("as_org", as_org)], *stake as f64 / total_stake as f64);
+            }
+        }
+    }
+    // Software versions and clients, to follow upgrades and how diverse the
+    // cluster is; nodes missing from gossip or not saying go under an empty label
+    if !known.nodes.is_empty() {
+        let mut versions: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+        for validator in validators {
+            let advertised = known.nodes.get(&validator.node_pubkey).and_then(|node| node.version.as_deref());
+            let version = versions.entry(advertised.unwrap_or_default()).or_default();
+            version.0 += 1;
+            version.1 += validator.activated_stake;
+        }
+        let by_version = output.family(
+            "solana_validator_active_by_version",
+            "Active validators by software version their node advertises in gossip",
+            MetricType::Gauge,
+        );
+        for (version, (count, _)) in &versions {
+            by_version.sample(&[("version", version)], *count as f64);
+        }
+        let stake_by_version = output.family(
+            "solana_validator_stake_share_by_version",
+            "Share of the active validators' stake by software version their node advertises in gossip (0 to 1)",
+            MetricType::Gauge,
+        );
+        for (version, (_, stake)) in &versions {
+            if total_stake > 0 {
+                stake_by_version.sample(&[("version", version)], *stake as f64 / total_stake as f64);
+            }
+        }
+
+        let mut clients: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+        for validator in validators {
+            let client = clients.entry(known.client(&validator.vote_pubkey, &validator.node_pubkey)).or_default();
+            client.0 += 1;
+            client.1 += validator.activated_stake;
+        }
+        let by_client = output.family(
+            "solana_validator_active_by_client",
+            "Active validators by validator client (agave, jito, firedancer, solana-labs)",
+            MetricType::Gauge,
+        );
+        for (client, (count, _)) in &clients {
+            by_client.sample(&[("client", client)], *count as f64);
+        }
+        let stake_by_client = output.family(
+            "solana_validator_stake_share_by_client",
+            "Share of the active validators' stake by validator client (0 to 1)",
+            MetricType::Gauge,
+        );
+        for (client, (_, stake)) in &clients {
+            if total_stake > 0 {
+                stake_by_client.sample(&[("client", client)], *stake as f64 / total_stake as f64);
+            }
+        }
+    }
+    if !known.app.is_empty() {
+        let mut data_centers: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+        for validator in validators {
+            let listed = known.app.get(&validator.vote_pubkey).and_then(|listed| listed.data_center_key.as_deref());
+            let data_center = data_centers.entry(listed.unwrap_or_default()).or_default();
+            data_center.0 += 1;
+            data_center.1 += validator.activated_stake;
+        }
+        let by_data_center = output.family(
+            "solana_validator_active_by_data_center",
+            "Active validators by data center on validators.app",
+            MetricType::Gauge,
+        );
+        for (data_center, (count, _)) in &data_centers {
+            by_data_center.sample(&[("data_center", data_center)], *count as f64);
+        }
+        let stake_by_data_center = output.family(
+            "solana_validator_stake_share_by_data_center",
+            "Share of the active validators' stake by data center on validators.app (0 to 1)",
+            MetricType::Gauge,
+        );
+        for (data_center, (_, stake)) in &data_centers {
+            if total_stake > 0 {
+                stake_by_data_center.sample(&[("data_center", data_center)], *stake as f64 / total_stake as f64);
+            }
+        }
+    }
 
     // RPC response status
-    output.push_str("# HELP solana_validator_exporter_last_rpc_status RPC response status (1=success, 0=failure)\n");
-    output.push_str("# TYPE solana_validator_exporter_last_rpc_status gauge\n");
-    output.push_str(&format!("solana_validator_exporter_last_rpc_status {}\n", rpc_status));
+    output
+        .family("solana_validator_exporter_last_rpc_status", "RPC response status (1=success, 0=failure)", MetricType::Gauge)
+        .sample(&[], rpc.status as f64);
 
     // RPC response timeout
-    output.push_str("# HELP solana_validator_exporter_rpc_response_timeout RPC response timeout (1=timeout, 0=no timeout)\n");
-    output.push_str("# TYPE solana_validator_exporter_rpc_response_timeout gauge\n");
-    output.push_str(&format!("solana_validator_exporter_rpc_response_timeout {}\n", rpc_timeout));
+    output
+        .family("solana_validator_exporter_rpc_response_timeout", "RPC response timeout (1=timeout, 0=no timeout)", MetricType::Gauge)
+        .sample(&[], rpc.timeout as f64);
 
     // RPC duration
-    output.push_str("# HELP solana_validator_exporter_rpc_duration_seconds RPC response time in seconds\n");
-    output.push_str("# TYPE solana_validator_exporter_rpc_duration_seconds gauge\n");
-    output.push_str(&format!("solana_validator_exporter_rpc_duration_seconds {}\n", rpc_duration));
+    rpc.durations.write(output.family(
+        "solana_validator_exporter_rpc_duration_seconds",
+        "RPC response time in seconds, over all fetch attempts",
+        MetricType::Histogram,
+    ));
+
+    // RPC endpoint that served the last successful update
+    let last_endpoint = output.family(
+        "solana_validator_exporter_last_rpc_endpoint",
+        "RPC endpoint that served the last successful update",
+        MetricType::Gauge,
+    );
+    if let Some(endpoint) = rpc.last_endpoint {
+        last_endpoint.sample(&[("endpoint", endpoint)], 1.0);
+    }
+
+    // Per-endpoint request accounting
+    let requests = output.family("solana_exporter_rpc_requests_total", "RPC fetch attempts per endpoint and outcome", MetricType::Counter);
+    for endpoint in rpc.endpoints {
+        for (result, count) in [("ok", endpoint.successes), ("error", endpoint.errors), ("timeout", endpoint.timeouts)] {
+            requests.sample(&[("endpoint", &endpoint.label), ("result", result)], count as f64);
+            requests.created(&[("endpoint", &endpoint.label), ("result", result)], endpoint.created);
+        }
+    }
+
+    let endpoint_duration = output.family(
+        "solana_validator_exporter_rpc_endpoint_duration_seconds",
+        "Response time of the last successful fetch per endpoint",
+        MetricType::Gauge,
+    );
+    for endpoint in rpc.endpoints {
+        if let Some(duration) = endpoint.last_duration {
+            endpoint_duration.sample(&[("endpoint", &endpoint.label)], duration);
+        }
+    }
+
+    let rate_limited = output.family("solana_exporter_rpc_rate_limited_total", "Rate-limited RPC responses per endpoint", MetricType::Counter);
+    for endpoint in rpc.endpoints {
+        rate_limited.sample(&[("endpoint", &endpoint.label)], endpoint.throttle.hits() as f64);
+        rate_limited.created(&[("endpoint", &endpoint.label)], endpoint.created);
+    }
+
+    let healthy = output.family(
+        "solana_exporter_rpc_healthy",
+        "Whether the last getHealth request to each endpoint succeeded (1=healthy, 0=unhealthy or no answer)",
+        MetricType::Gauge,
+    );
+    for endpoint in rpc.endpoints {
+        if let Some(state) = endpoint.healthy {
+            healthy.sample(&[("endpoint", &endpoint.label)], state as u8 as f64);
+        }
+    }
+
+    let slot_lag = output.family(
+        "solana_exporter_rpc_slot_lag",
+        "Slots each endpoint is behind the furthest one in the last slot check",
+        MetricType::Gauge,
+    );
+    for (endpoint, lag) in slot_lags(rpc.endpoints) {
+        slot_lag.sample(&[("endpoint", endpoint)], lag as f64);
+    }
+
+    let circuit_state = output.family(
+        "solana_validator_exporter_rpc_circuit_state",
+        "Circuit breaker state per endpoint (0=closed, 1=open, 2=half-open)",
+        MetricType::Gauge,
+    );
+    for endpoint in rpc.endpoints {
+        circuit_state.sample(&[("endpoint", &endpoint.label)], endpoint.breaker.state.value() as f64);
+    }
+
+    // Current polling cadence
+    output
+        .family("solana_validator_exporter_poll_interval_seconds", "Seconds between the start of two polls", MetricType::Gauge)
+        .sample(&[], rpc.poll_interval.as_secs_f64());
+
+    // Backoff after failed polls
+    output
+        .family(
+            "solana_validator_exporter_rpc_backoff_seconds",
+            "Delay before the next poll because of failures (0=not backing off)",
+            MetricType::Gauge,
+        )
+        .sample(&[], rpc.backoff.delay().as_secs_f64());
+
+    output
+        .family(
+            "solana_validator_exporter_rpc_consecutive_failures",
+            "Polls in a row in which every RPC endpoint failed",
+            MetricType::Gauge,
+        )
+        .sample(&[], rpc.backoff.failures() as f64);
+
+    // Restarts of the fetch loop by the watchdog
+    let restarts = output.family(
+        "solana_exporter_fetch_task_restarts_total",
+        "Times the fetch task was restarted after panicking or stalling",
+        MetricType::Counter,
+    );
+    restarts.sample(&[], rpc.restarts.get() as f64);
+    restarts.created(&[], rpc.restarts.created);
+
+    output
+}
+
+// The operator's own validators, labeled with their names, so dashboards and
+// alerts do not have to pick them out of the bulk export
+fn export_watched_metrics(
+    output: &mut Exposition,
+    data: &PollData,
+    watched: &[WatchedValidator],
+    settings: &MetricsConfig,
+    start_ranks: &mut HashMap<String, usize>,
+) {
+    // Only validators that earned credits since the previous epoch have numbers
+    let earning: Vec<(&WatchedValidator, &ValidatorMetrics)> = watched
+        .iter()
+        .filter_map(|watched| {
+            let metrics = data.validators.iter().find(|validator| validator.vote_pubkey == watched.vote_pubkey)?;
+            Some((watched, metrics))
+        })
+        .collect();
+
+    let credits = output.family("solana_watched_validator_credits", "Credits earned this epoch by a watched validator", MetricType::Gauge);
+    for (watched, validator) in &earning {
+        let epoch = validator.epoch.to_string();
+        let mut labels = watched_labels(watched, &data.nodes).to_vec();
+        labels.push(("epoch", &epoch));
+        credits.sample(&labels, validator.credits_earned as f64);
+    }
+
+    let projected = output.family(
+        "solana_watched_validator_projected_credits",
+        "Credits a watched validator ends the epoch with if it keeps its rate",
+        MetricType::Gauge,
+    );
+    for (watched, validator) in &earning {
+        if let Some(credits) = validator.projected_credits {
+            projected.sample(&watched_labels(watched, &data.nodes), credits);
+        }
+    }
+
+    let rank = output.family("solana_watched_validator_rank", "Rank of a watched validator by credits earned this epoch (1=most)", MetricType::Gauge);
+    for (watched, validator) in &earning {
+        rank.sample(&watched_labels(watched, &data.nodes), validator.rank as f64);
+    }
+
+    // Against the rank in the first poll of the epoch that saw the validator
+    // earn credits: positive while climbing, negative while falling
+    let rank_change = output.family(
+        "solana_watched_validator_rank_change",
+        "Ranks a watched validator gained since the start of the epoch (negative = lost)",
+        MetricType::Gauge,
+    );
+    for (watched, validator) in &earning {
+        let start = *start_ranks.entry(watched.vote_pubkey.clone()).or_insert(validator.rank);
+        rank_change.sample(&watched_labels(watched, &data.nodes), start as f64 - validator.rank as f64);
+    }
+
+    // The validators `solana_validator_active` counts, by rank; zero-credit and
+    // delinquent ones exported alongside them are no competition
+    let active: Vec<&ValidatorMetrics> = data
+        .validators
+        .iter()
+        .filter(|validator| validator.credits_earned > 0 && (!validator.delinquent || settings.active_includes_delinquent))
+        .collect();
+
+    // Stays comparable while the number of active validators changes
+    let percentile = output.family(
+        "solana_watched_validator_rank_percentile",
+        "Share of the active validators a watched validator ranks above (0.93 = better than 93%)",
+        MetricType::Gauge,
+    );
+    if !active.is_empty() {
+        for (watched, validator) in &earning {
+            let below = active.iter().filter(|other| other.rank > validator.rank).count();
+            percentile.sample(&watched_labels(watched, &data.nodes), below as f64 / active.len() as f64);
+        }
+    }
+
+    let root_distance = output.family("solana_watched_validator_root_distance", "Root distance of a watched validator", MetricType::Gauge);
+    for (watched, validator) in &earning {
+        root_distance.sample(&watched_labels(watched, &data.nodes), validator.root_distance as f64);
+    }
+
+    let vote_distance = output.family("solana_watched_validator_vote_distance", "Vote distance of a watched validator", MetricType::Gauge);
+    for (watched, validator) in &earning {
+        vote_distance.sample(&watched_labels(watched, &data.nodes), validator.vote_distance as f64);
+    }
+
+    // What separates a watched validator from a rank band: positive while behind
+    // the validator at that rank, negative once ahead of it
+    let gap = output.family(
+        "solana_watched_validator_credits_gap",
+        "Credits earned this epoch by the active validator at rank <top> minus those of a watched validator",
+        MetricType::Gauge,
+    );
+    for (watched, validator) in &earning {
+        for top in &settings.gap_ranks {
+            if let Some(cutoff) = active.get(top - 1) {
+                let top = top.to_string();
+                let [vote_pubkey, identity, name] = watched_labels(watched, &data.nodes);
+                gap.sample(&[vote_pubkey, identity, name, ("top", &top)], cutoff.credits_earned as f64 - validator.credits_earned as f64);
+            }
+        }
+    }
+
+    // How contested the validator's rank is
+    let window = settings.competitor_window;
+    let competitors = output.family(
+        "solana_watched_validator_competitors",
+        "Other active validators within metrics.competitor_window credits of a watched validator",
+        MetricType::Gauge,
+    );
+    for (watched, validator) in &earning {
+        let nearby = active
+            .iter()
+            .filter(|other| other.vote_pubkey != validator.vote_pubkey && other.credits_earned.abs_diff(validator.credits_earned) <= window)
+            .count();
+        competitors.sample(&watched_labels(watched, &data.nodes), nearby as f64);
+    }
+
+    // Unknown vote pubkeys count as delinquent, so a typo or a closed account alerts too
+    let delinquent = output.family(
+        "solana_watched_validator_delinquent",
+        "Whether a watched validator is missing from the current vote accounts (1=delinquent, 0=voting)",
+        MetricType::Gauge,
+    );
+    for watched in watched {
+        delinquent.sample(&watched_labels(watched, &data.nodes), !data.current.contains(&watched.vote_pubkey) as u8 as f64);
+    }
+}
+
+// Every delinquent validator however it ranked, to tell a blip from an outage
+fn export_delinquency_metrics(output: &mut Exposition, data: &PollData, since: &HashMap<String, Instant>, known: &Enrichment) {
+    let mut delinquent: Vec<(&String, &Instant)> = since.iter().collect();
+    delinquent.sort_by_key(|(vote_pubkey, _)| *vote_pubkey);
+    let seconds = output.family(
+        "solana_validator_delinquent_seconds",
+        "Seconds a validator has been delinquent without interruption, as seen by the exporter",
+        MetricType::Gauge,
+    );
+    for (vote_pubkey, since) in delinquent {
+        let node = data.nodes.get(vote_pubkey).map_or("", String::as_str);
+        let mut labels = vec![("vote_pubkey", vote_pubkey.as_str()), ("identity", node)];
+        labels.extend(known.name(vote_pubkey, node).map(|name| ("name", name)));
+        seconds.sample(&labels, since.elapsed().as_secs_f64());
+    }
+}
+
+// Delegation program style criteria of the watched validators, each checked
+// continuously rather than at the end of the epoch
+fn export_sfdp_metrics(output: &mut Exposition, data: &PollData, watched: &[WatchedValidator], since: &HashMap<String, Instant>, settings: &SfdpConfig) {
+    let mut credits: Vec<u64> = data.validators.iter().filter(|validator| !validator.delinquent).map(|validator| validator.credits_earned).collect();
+    credits.sort_unstable();
+    let Some(threshold) = stats::quantile(&credits, settings.credits_quantile) else {
+        return;
+    };
+
+    let checks: Vec<(&WatchedValidator, [(&str, bool); 3])> = watched
+        .iter()
+        .filter_map(|watched| {
+            let commission = *data.commissions.get(&watched.vote_pubkey)?;
+            // Vote accounts that earned nothing are not among the validators
+            let earned = data
+                .validators
+                .iter()
+                .find(|validator| validator.vote_pubkey == watched.vote_pubkey)
+                .map_or(0, |validator| validator.credits_earned);
+            let delinquent_for = since.get(&watched.vote_pubkey).map_or(0.0, |since| since.elapsed().as_secs_f64());
+            let delinquency = data.current.contains(&watched.vote_pubkey) || delinquent_for <= settings.max_delinquency;
+            let criteria = [
+                ("credits", earned as f64 >= threshold),
+                ("commission", commission <= settings.max_commission),
+                ("delinquency", delinquency),
+            ];
+            Some((watched, criteria))
+        })
+        .collect();
+
+    let criterion = output.family(
+        "solana_watched_validator_sfdp_criterion",
+        "Whether a watched validator meets a delegation program style criterion (1=pass, 0=fail)",
+        MetricType::Gauge,
+    );
+    for (watched, criteria) in &checks {
+        for (name, pass) in criteria {
+            let mut labels = watched_labels(watched, &data.nodes).to_vec();
+            labels.push(("criterion", name));
+            criterion.sample(&labels, *pass as u8 as f64);
+        }
+    }
+    let eligible = output.family(
+        "solana_watched_validator_sfdp_eligible",
+        "Whether a watched validator meets every delegation program style criterion (1=pass, 0=fail)",
+        MetricType::Gauge,
+    );
+    for (watched, criteria) in &checks {
+        let pass = criteria.iter().all(|(_, pass)| *pass);
+        eligible.sample(&watched_labels(watched, &data.nodes), pass as u8 as f64);
+    }
+}
+
+// Leader slots of each vote account's node this epoch and how many it filled
+fn export_block_production_metrics(output: &mut Exposition, data: &PollData, production: &BlockProduction, known: &Enrichment) {
+    let mut leaders: Vec<(&String, &String)> = data
+        .nodes
+        .iter()
+        .filter(|(_, node)| production.by_identity.contains_key(*node))
+        .collect();
+    leaders.sort();
+    let labelled: Vec<(Vec<(&str, &str)>, _)> = leaders
+        .into_iter()
+        .map(|(vote_pubkey, node)| {
+            let mut labels = vec![("vote_pubkey", vote_pubkey.as_str()), ("identity", node.as_str())];
+            labels.extend(known.name(vote_pubkey, node).map(|name| ("name", name)));
+            (labels, production.by_identity[node])
+        })
+        .collect();
+
+    let slots = output.family("solana_validator_leader_slots", "Leader slots of each validator this epoch so far", MetricType::Gauge);
+    for (labels, produced) in &labelled {
+        slots.sample(labels, produced.leader_slots as f64);
+    }
+    let blocks = output.family("solana_validator_blocks_produced", "Blocks each validator produced this epoch so far", MetricType::Gauge);
+    for (labels, produced) in &labelled {
+        blocks.sample(labels, produced.blocks_produced as f64);
+    }
+    let skip_rate = output.family(
+        "solana_validator_skip_rate",
+        "Share of the leader slots of each validator this epoch without a block (0 to 1)",
+        MetricType::Gauge,
+    );
+    for (labels, produced) in &labelled {
+        if let Some(rate) = produced.skip_rate() {
+            skip_rate.sample(labels, rate);
+        }
+    }
+}
+
+// How the stake of the whole cluster is spread, delinquent validators included
+fn export_stake_metrics(output: &mut Exposition, data: &PollData) {
+    let stakes: Vec<u64> = data.stakes.values().copied().collect();
+    output
+        .family(
+            "solana_validator_activated_stake_total_lamports",
+            "Activated stake of all vote accounts, delinquent ones included, in lamports",
+            MetricType::Gauge,
+        )
+        .sample(&[], stakes.iter().sum::<u64>() as f64);
+    output
+        .family(
+            "solana_validator_stake_hhi",
+            "Herfindahl-Hirschman index of the activated stake of all vote accounts (1/N when spread evenly, 1 when one holds all)",
+            MetricType::Gauge,
+        )
+        .sample(&[], stats::hhi(&stakes));
+}
+
+// Only the validators that changed their commission while the exporter ran,
+// so these stay a handful of series
+fn export_commission_metrics(output: &mut Exposition, data: &PollData, tracker: &Tracker, known: &Enrichment) {
+    let mut changed: Vec<(Vec<(&str, &str)>, &Changes)> = tracker
+        .changes
+        .iter()
+        .map(|(vote_pubkey, changes)| {
+            let node = data.nodes.get(vote_pubkey).map_or("", String::as_str);
+            let mut labels = vec![("vote_pubkey", vote_pubkey.as_str()), ("identity", node)];
+            labels.extend(known.name(vote_pubkey, node).map(|name| ("name", name)));
+            (labels, changes)
+        })
+        .collect();
+    changed.sort_by_key(|(labels, _)| labels[0].1);
+
+    let total = output.family(
+        "solana_validator_commission_changed_total",
+        "Commission changes of each validator seen since the exporter started",
+        MetricType::Counter,
+    );
+    for (labels, changes) in &changed {
+        total.sample(labels, changes.total as f64);
+    }
+    let late = output.family(
+        "solana_validator_commission_changed_late_epoch_total",
+        "Commission changes of each validator seen after metrics.commission_late_epoch of an epoch",
+        MetricType::Counter,
+    );
+    for (labels, changes) in &changed {
+        late.sample(labels, changes.late as f64);
+    }
+    let previous = output.family(
+        "solana_validator_commission_previous",
+        "Commission of each validator in percent before its last change",
+        MetricType::Gauge,
+    );
+    for (labels, changes) in &changed {
+        previous.sample(labels, changes.previous as f64);
+    }
+}
+
+// How far the endpoints of a quorum agreed on the vote accounts
+fn export_quorum_metrics(output: &mut Exposition, disagreement: &Disagreement) {
+    output
+        .family(
+            "solana_exporter_rpc_quorum_answers",
+            "Endpoints whose vote accounts were reconciled in the last poll",
+            MetricType::Gauge,
+        )
+        .sample(&[], disagreement.answers as f64);
+    output
+        .family(
+            "solana_exporter_rpc_disagreeing_validators",
+            "Validators whose credits differed between the endpoints by more than rpc.quorum_tolerance",
+            MetricType::Gauge,
+        )
+        .sample(&[], disagreement.validators as f64);
+    let behind = output.family(
+        "solana_exporter_rpc_validators_behind",
+        "Validators each endpoint reported more than rpc.quorum_tolerance credits too few for, or not at all",
+        MetricType::Gauge,
+    );
+    for (endpoint, count) in &disagreement.behind {
+        behind.sample(&[("endpoint", endpoint)], *count as f64);
+    }
+}
+
+// How busy the cluster is, to tell a cluster-wide stall from a validator's own
+fn export_performance_metrics(output: &mut Exposition, rates: &Rates) {
+    output
+        .family(
+            "solana_cluster_transactions_per_second",
+            "Transactions the cluster processed per second over the recent performance samples",
+            MetricType::Gauge,
+        )
+        .sample(&[], rates.transactions);
+    if let Some(non_vote) = rates.non_vote_transactions {
+        output
+            .family(
+                "solana_cluster_non_vote_transactions_per_second",
+                "Transactions other than votes the cluster processed per second over the recent performance samples",
+                MetricType::Gauge,
+            )
+            .sample(&[], non_vote);
+    }
+    output
+        .family(
+            "solana_cluster_slots_per_second",
+            "Slots the cluster advanced per second over the recent performance samples",
+            MetricType::Gauge,
+        )
+        .sample(&[], rates.slots);
+}
+
+// What the credits of the last epoch paid the watched validators
+fn export_reward_metrics(output: &mut Exposition, data: &PollData, watched: &[WatchedValidator], rewards: &Rewards) {
+    let rewarded: Vec<(&WatchedValidator, &Reward, String)> = watched
+        .iter()
+        .filter_map(|watched| Some((watched, rewards.by_vote.get(&watched.vote_pubkey)?)))
+        .map(|(watched, reward)| (watched, reward, reward.epoch.to_string()))
+        .collect();
 
+    let lamports = output.family(
+        "solana_watched_validator_inflation_reward_lamports",
+        "Lamports of inflation rewards a watched vote account was paid for the epoch, its commission",
+        MetricType::Gauge,
+    );
+    for (watched, reward, epoch) in &rewarded {
+        let mut labels = watched_labels(watched, &data.nodes).to_vec();
+        labels.push(("epoch", epoch));
+        lamports.sample(&labels, reward.lamports as f64);
+    }
+    let commission = output.family(
+        "solana_watched_validator_inflation_reward_commission_percent",
+        "Commission a watched validator took from the inflation rewards of the epoch",
+        MetricType::Gauge,
+    );
+    for (watched, reward, epoch) in &rewarded {
+        if let Some(percent) = reward.commission {
+            let mut labels = watched_labels(watched, &data.nodes).to_vec();
+            labels.push(("epoch", epoch));
+            commission.sample(&labels, f64::from(percent));
+        }
+    }
+    // Against the credits of the same epoch, from the history of the vote account
+    let per_credit = output.family(
+        "solana_watched_validator_inflation_reward_lamports_per_credit",
+        "Lamports of inflation rewards a watched vote account was paid per credit it earned in the epoch",
+        MetricType::Gauge,
+    );
+    for (watched, reward, epoch) in &rewarded {
+        let credits = data
+            .validators
+            .iter()
+            .find(|validator| validator.vote_pubkey == watched.vote_pubkey)
+            .and_then(|validator| validator.history.iter().find(|(earned_in, _)| *earned_in == reward.epoch))
+            .map(|&(_, credits)| credits);
+        if let Some(credits) = credits.filter(|&credits| credits > 0) {
+            let mut labels = watched_labels(watched, &data.nodes).to_vec();
+            labels.push(("epoch", epoch));
+            per_credit.sample(&labels, reward.lamports as f64 / credits as f64);
+        }
+    }
+}
+
+// MEV next to the credits, for the watched validators that run Jito
+fn export_jito_metrics(output: &mut Exposition, data: &PollData, watched: &[WatchedValidator], tips: &Tips) {
+    let Some(epoch) = tips.epoch.map(|epoch| epoch.to_string()) else {
+        return;
+    };
+    let accounts: Vec<(&WatchedValidator, &TipAccount)> = watched
+        .iter()
+        .filter_map(|watched| Some((watched, tips.by_vote.get(&watched.vote_pubkey)?)))
+        .collect();
+
+    let commission = output.family(
+        "solana_watched_validator_mev_commission_percent",
+        "Share of the MEV tips a watched validator keeps this epoch, from its Jito tip distribution account",
+        MetricType::Gauge,
+    );
+    for (watched, account) in &accounts {
+        commission.sample(&watched_labels(watched, &data.nodes), f64::from(account.commission_bps) / 100.0);
+    }
+    let collected = output.family(
+        "solana_watched_validator_mev_tips_lamports",
+        "Lamports of MEV tips collected so far this epoch in a watched validator's Jito tip distribution account",
+        MetricType::Gauge,
+    );
+    for (watched, account) in &accounts {
+        let mut labels = watched_labels(watched, &data.nodes).to_vec();
+        labels.push(("epoch", &epoch));
+        collected.sample(&labels, account.tips as f64);
+    }
+}
+
+fn export_supply_metrics(output: &mut Exposition, inflation: &Inflation) {
+    if let Some(supply) = &inflation.supply {
+        output
+            .family("solana_cluster_total_supply_lamports", "Lamports in existence", MetricType::Gauge)
+            .sample(&[], supply.total as f64);
+        output
+            .family(
+                "solana_cluster_circulating_supply_lamports",
+                "Lamports in circulation, those outside the accounts the cluster lists as not circulating",
+                MetricType::Gauge,
+            )
+            .sample(&[], supply.circulating as f64);
+    }
+    if let Some(rate) = &inflation.rate {
+        output
+            .family("solana_cluster_inflation_rate", "Yearly inflation rate of this epoch, 0 to 1", MetricType::Gauge)
+            .sample(&[], rate.total);
+        output
+            .family(
+                "solana_cluster_validator_inflation_rate",
+                "Share of the yearly inflation rate of this epoch paid to validators and their stakers, 0 to 1",
+                MetricType::Gauge,
+            )
+            .sample(&[], rate.validator);
+    }
+}
+
+// What staking with the watched validators yields in a year. Each epoch's
+// inflation is shared by points, stake times credits, so a staker earns the
+// yearly inflation over the points of the cluster for each credit of their
+// validator, less its commission. The credits of the last completed epoch
+// stand for the year, compounded every epoch; the points are those of every
+// vote account, however few credits it earned this epoch or whether it is
+// delinquent now.
+fn export_apy_metrics(output: &mut Exposition, data: &PollData, watched: &[WatchedValidator], inflation: &Inflation, info: &EpochInfo) {
+    let (Some(rate), Some(supply)) = (&inflation.rate, &inflation.supply) else {
+        return;
+    };
+    let points: f64 = data
+        .last_epoch_credits
+        .iter()
+        .map(|(vote_pubkey, &credits)| data.stakes.get(vote_pubkey).copied().unwrap_or_default() as f64 * credits as f64)
+        .sum();
+    if points == 0.0 || info.slots_in_epoch == 0 {
+        return;
+    }
+    let yearly_inflation = rate.validator * supply.total as f64;
+    let epochs_per_year = 365.25 * 86_400.0 * 1000.0 / (info.slots_in_epoch as f64 * DEFAULT_MS_PER_SLOT as f64);
+
+    let apy = output.family(
+        "solana_watched_validator_estimated_apy_percent",
+        "Estimated yearly yield of stake delegated to a watched validator, compounded every epoch, from the credits of the last epoch",
+        MetricType::Gauge,
+    );
+    for watched in watched {
+        let (Some(&credits), Some(&commission)) = (data.last_epoch_credits.get(&watched.vote_pubkey), data.commissions.get(&watched.vote_pubkey)) else {
+            continue;
+        };
+        let apr = yearly_inflation * credits as f64 / points * (1.0 - f64::from(commission) / 100.0);
+        let compounded = (1.0 + apr / epochs_per_year).powf(epochs_per_year) - 1.0;
+        apy.sample(&watched_labels(watched, &data.nodes), compounded * 100.0);
+    }
+}
+
+// What voting costs: a validator that keeps up votes once a slot, at the slot
+// rate the cluster keeps with `[performance]` and the one it aims for without
+fn export_vote_fee_metrics(output: &mut Exposition, data: &PollData, watched: &[WatchedValidator], lamports: u64, rates: Option<&Rates>) {
     output
+        .family("solana_vote_fee_lamports", "Lamports a vote transaction signed by the node identity alone pays", MetricType::Gauge)
+        .sample(&[], lamports as f64);
+
+    let slots_per_second = rates.map_or(1000.0 / DEFAULT_MS_PER_SLOT as f64, |rates| rates.slots);
+    let per_day = slots_per_second * 86_400.0 * lamports as f64 / LAMPORTS_PER_SOL as f64;
+    let spend = output.family(
+        "solana_watched_validator_vote_fees_sol_per_day",
+        "Estimated SOL a watched validator's identity spends on vote fees per day, 0 while it is delinquent",
+        MetricType::Gauge,
+    );
+    for watched in watched.iter().filter(|watched| data.nodes.contains_key(&watched.vote_pubkey)) {
+        let voting = data.current.contains(&watched.vote_pubkey);
+        spend.sample(&watched_labels(watched, &data.nodes), if voting { per_day } else { 0.0 });
+    }
+}
+
+// When the watched validators lead next, to plan restarts around
+fn export_leader_schedule_metrics(output: &mut Exposition, data: &PollData, watched: &[WatchedValidator], schedule: &LeaderSchedule, info: &EpochInfo) {
+    let positions: Vec<(&WatchedValidator, (usize, Option<u64>))> = watched
+        .iter()
+        .filter_map(|watched| {
+            let node = data.nodes.get(&watched.vote_pubkey)?;
+            Some((watched, schedule.position(node, info.epoch, info.slot_index)?))
+        })
+        .collect();
+
+    let slots = output.family(
+        "solana_watched_validator_leader_slots_scheduled",
+        "Leader slots of a watched validator in the leader schedule of this epoch",
+        MetricType::Gauge,
+    );
+    for (watched, (count, _)) in &positions {
+        slots.sample(&watched_labels(watched, &data.nodes), *count as f64);
+    }
+    let next = output.family(
+        "solana_watched_validator_next_leader_slot_index",
+        "Slot index in the epoch of the next leader slot of a watched validator",
+        MetricType::Gauge,
+    );
+    for (watched, (_, slot)) in &positions {
+        if let Some(slot) = slot {
+            next.sample(&watched_labels(watched, &data.nodes), *slot as f64);
+        }
+    }
+    let until = output.family(
+        "solana_watched_validator_slots_until_leader",
+        "Slots until the next leader slot of a watched validator this epoch",
+        MetricType::Gauge,
+    );
+    for (watched, (_, slot)) in &positions {
+        if let Some(slot) = slot {
+            until.sample(&watched_labels(watched, &data.nodes), (slot - info.slot_index) as f64);
+        }
+    }
+    // At the slot time the cluster aims for; slots run a little longer in practice
+    let seconds = output.family(
+        "solana_watched_validator_seconds_until_leader",
+        "Estimated seconds until the next leader slot of a watched validator this epoch",
+        MetricType::Gauge,
+    );
+    for (watched, (_, slot)) in &positions {
+        if let Some(slot) = slot {
+            let slots = (slot - info.slot_index) as f64;
+            seconds.sample(&watched_labels(watched, &data.nodes), slots * DEFAULT_MS_PER_SLOT as f64 / 1000.0);
+        }
+    }
+}
+
+// Whether the world can reach the watched validators, port by port
+fn export_probe_metrics(output: &mut Exposition, data: &PollData, watched: &[WatchedValidator], prober: &Prober) {
+    let probed: Vec<(&WatchedValidator, &str, &Probe)> = watched
+        .iter()
+        .filter_map(|watched| Some((watched, data.nodes.get(&watched.vote_pubkey)?)))
+        .flat_map(|(watched, identity)| {
+            prober
+                .results
+                .iter()
+                .filter(move |((node, _), _)| node == identity)
+                .map(move |((_, port), probe)| (watched, port.label(), probe))
+        })
+        .collect();
+
+    let reachable = output.family(
+        "solana_watched_validator_port_reachable",
+        "Whether a port a watched validator advertises in gossip answered the last probe (1=reachable, 0=not)",
+        MetricType::Gauge,
+    );
+    for (watched, port, probe) in &probed {
+        let mut labels = watched_labels(watched, &data.nodes).to_vec();
+        labels.push(("port", port));
+        reachable.sample(&labels, probe.reachable as u8 as f64);
+    }
+    let latency = output.family(
+        "solana_watched_validator_probe_latency_seconds",
+        "Seconds the last TCP connection to a port a watched validator advertises in gossip took",
+        MetricType::Gauge,
+    );
+    for (watched, port, probe) in &probed {
+        if let Some(seconds) = probe.latency {
+            let mut labels = watched_labels(watched, &data.nodes).to_vec();
+            labels.push(("port", port));
+            latency.sample(&labels, seconds);
+        }
+    }
+}
+
+// Stakewiz's view of the watched validators, to correlate with their credits
+fn export_stakewiz_metrics(output: &mut Exposition, data: &PollData, watched: &[WatchedValidator], scores: &Scores) {
+    let score = output.family(
+        "solana_watched_validator_stakewiz_score",
+        "Wiz score of a watched validator on Stakewiz, 0 to 100",
+        MetricType::Gauge,
+    );
+    for watched in watched {
+        if let Some(value) = scores.get(&watched.vote_pubkey).and_then(|listed| listed.wiz_score) {
+            score.sample(&watched_labels(watched, &data.nodes), value);
+        }
+    }
+    let rank = output.family("solana_watched_validator_stakewiz_rank", "Rank of a watched validator on Stakewiz (1=best)", MetricType::Gauge);
+    for watched in watched {
+        if let Some(value) = scores.get(&watched.vote_pubkey).and_then(|listed| listed.rank) {
+            rank.sample(&watched_labels(watched, &data.nodes), value as f64);
+        }
+    }
+}
+
+// A watched validator without a name goes by its vote pubkey; the identity
+// is empty for a vote account that is not on chain
+fn watched_labels<'a>(watched: &'a WatchedValidator, nodes: &'a HashMap<String, String>) -> [(&'a str, &'a str); 3] {
+    [
+        ("vote_pubkey", &watched.vote_pubkey),
+        ("identity", nodes.get(&watched.vote_pubkey).map_or("", String::as_str)),
+        ("name", watched.name.as_deref().unwrap_or(&watched.vote_pubkey)),
+    ]
+}
+
+// Fraction of the epoch's slots that have passed, 0.0 to 1.0
+fn epoch_progress(epoch_info: &EpochInfo) -> f64 {
+    if epoch_info.slots_in_epoch == 0 {
+        return 0.0;
+    }
+    epoch_info.slot_index as f64 / epoch_info.slots_in_epoch as f64
+}
+
+// Freshness gauges of one cluster; the age is taken at scrape time, so it keeps
+// growing while polls fail or hang
+fn freshness_metrics(output: &mut Exposition, cache: &MetricsCache, age: Option<f64>, stale: bool) {
+    let last_success = output.family(
+        "solana_exporter_last_success_timestamp_seconds",
+        "Unix time of the last successful poll",
+        MetricType::Gauge,
+    );
+    if let Some(updated_at) = cache.updated_at {
+        last_success.sample(&[], updated_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64());
+    }
+    let data_age = output.family("solana_exporter_data_age_seconds", "Seconds since the last successful poll", MetricType::Gauge);
+    if let Some(age) = age {
+        data_age.sample(&[], age);
+    }
+    output
+        .family(
+            "solana_exporter_data_stale",
+            "Whether the data is older than metrics.stale_after (1=stale, 0=fresh)",
+            MetricType::Gauge,
+        )
+        .sample(&[], stale as u8 as f64);
+    output
+        .family(
+            "solana_exporter_paused",
+            "Whether polling is paused through /-/pause (1=paused, 0=polling)",
+            MetricType::Gauge,
+        )
+        .sample(&[], cache.paused as u8 as f64);
+}
+
+fn gzip(data: &[u8], level: u32) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level));
+    // Writing into a Vec cannot fail
+    encoder.write_all(data).expect("gzip into memory");
+    encoder.finish().expect("gzip into memory")
 }
 
 // HTTP handler for serving Prometheus metrics
-async fn serve_metrics(
-    req: Request<Body>,
-    cache: Arc<Mutex<MetricsCache>>,
-) -> Result<Response<Body>, Infallible> {
-    if req.uri().path() == "/metrics" {
-        let cache = cache.lock().await;
-        Ok(Response::new(Body::from(cache.data.clone())))
+async fn serve_metrics(req: Request<Body>, peer: SocketAddr, state: HttpState) -> Result<Response<Body>, Infallible> {
+    let (metrics_path, stale_after, stale_action, gzip_level, labels, auth, max_scrapes) = {
+        let config = state.config.borrow();
+        let metrics = &config.metrics;
+        (
+            metrics.path.clone(),
+            metrics.stale_after,
+            metrics.stale_action,
+            metrics.gzip_level,
+            metrics.labels.clone(),
+            config.server.auth.clone(),
+            config.server.limits.max_in_flight_scrapes,
+        )
+    };
+    // Liveness: answering at all is the point. Readiness: every cluster has
+    // been polled successfully and its data is not stale.
+    let path = req.uri().path();
+    if path == "/healthz" {
+        return Ok(Response::new(Body::from("ok\n")));
+    }
+
+    // Everything but the health checks needs credentials when auth is configured
+    let authorization = req.headers().get(AUTHORIZATION).and_then(|value| value.to_str().ok());
+    if path != "/readyz" && !auth::authorized(&auth, &state.htpasswd, authorization) {
+        let unauthorized = Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .header(WWW_AUTHENTICATE, auth::challenge(&auth))
+            .body(Body::from("401 Unauthorized\n"))
+            .unwrap();
+        return Ok(unauthorized);
+    }
+
+    // An index of the endpoints, unless the metrics themselves are served on `/`
+    if path == "/" && metrics_path != "/" {
+        let page = landing::page(&state.config.borrow());
+        let index = Response::builder()
+            .header(CONTENT_TYPE, "text/html; charset=utf-8")
+            .body(Body::from(page))
+            .unwrap();
+        return Ok(index);
+    }
+
+    // Live updates, long-lived, so they do not count as scrapes
+    let cluster = req
+        .uri()
+        .query()
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("cluster=")));
+    if path == "/stream" {
+        if cluster.is_some_and(|cluster| state.config.borrow().cluster_rpc(cluster).is_none()) {
+            return Ok(Response::builder().status(StatusCode::NOT_FOUND).body(Body::from("404 Not Found: unknown cluster\n")).unwrap());
+        }
+        let subscription = state.updates.subscribe();
+        return Ok(stream::response(state.cache, state.config, subscription, cluster.map(str::to_string)));
+    }
+    if path == "/ws" {
+        if cluster.is_some_and(|cluster| state.config.borrow().cluster_rpc(cluster).is_none()) {
+            return Ok(Response::builder().status(StatusCode::NOT_FOUND).body(Body::from("404 Not Found: unknown cluster\n")).unwrap());
+        }
+        let (subscription, cluster) = (state.updates.subscribe(), cluster.map(str::to_string));
+        return Ok(websocket::upgrade(req, state.cache, state.config, subscription, state.connections, cluster));
+    }
+    if path == "/api/v1/watchlist" {
+        return Ok(watchlist::handle(req, peer, &state).await);
+    }
+    if path.starts_with("/-/") {
+        let cluster = cluster.map(str::to_string);
+        return Ok(admin::handle(req, peer, &state, cluster.as_deref()).await);
+    }
+
+    // Shed load instead of queueing up behind the cache lock
+    let Some(_scrape) = state.scrapes.try_acquire(max_scrapes) else {
+        let busy = Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .header(RETRY_AFTER, "1")
+            .body(Body::from("503 Service Unavailable: too many scrapes in progress\n"))
+            .unwrap();
+        return Ok(busy);
+    };
+
+    if path == "/graphql" {
+        return Ok(graphql::handle(req, &state.cache, stale_after).await);
+    }
+
+    let cache = state.cache.lock().await;
+    if path == "/readyz" {
+        let ready = !cache.is_empty()
+            && cache.values().all(|cluster| cluster.updated.is_some() && !cluster.is_stale(stale_after));
+        let (status, body) = if ready {
+            (StatusCode::OK, "ready\n")
+        } else {
+            (StatusCode::SERVICE_UNAVAILABLE, "not ready: no fresh data yet\n")
+        };
+        return Ok(Response::builder().status(status).body(Body::from(body)).unwrap());
+    }
+
+    // The same data as JSON or CSV, optionally for one cluster with `?cluster=<name>`
+    if path == "/api/v1/validators" {
+        let response = match api::validators(&cache, cluster, stale_after) {
+            Some(json) => Response::builder().header(CONTENT_TYPE, "application/json").body(Body::from(json)),
+            None => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from("{\"error\":\"unknown cluster\"}")),
+        };
+        return Ok(response.unwrap());
+    }
+    if path == "/status" {
+        let page = status::page(&cache, req.uri().query(), stale_after);
+        return Ok(Response::builder().header(CONTENT_TYPE, "text/html; charset=utf-8").body(Body::from(page)).unwrap());
+    }
+    if path == "/export.csv" {
+        let response = match api::validators_csv(&cache, cluster) {
+            Some(csv) => Response::builder()
+                .header(CONTENT_TYPE, "text/csv; charset=utf-8")
+                .header(CONTENT_DISPOSITION, "attachment; filename=\"validators.csv\"")
+                .body(Body::from(csv)),
+            None => Response::builder().status(StatusCode::NOT_FOUND).body(Body::from("404 Not Found: unknown cluster\n")),
+        };
+        return Ok(response.unwrap());
+    }
+
+    // `metrics.path` serves every cluster, `metrics.path/<cluster>` a single one
+    let cluster_path = path
+        .strip_prefix(metrics_path.trim_end_matches('/'))
+        .and_then(|rest| rest.strip_prefix('/'));
+    let clusters: Vec<(&String, &MetricsCache)> = if path == metrics_path {
+        cache.iter().collect()
+    } else if let Some(entry) = cluster_path.and_then(|name| cache.get_key_value(name)) {
+        vec![entry]
     } else {
         let not_found = Response::builder()
             .status(StatusCode::NOT_FOUND)
             .body(Body::from("404 Not Found"))
             .unwrap();
-        Ok(not_found)
+        return Ok(not_found);
+    };
+
+    let mut output = Exposition::new();
+    let mut all_stale = true;
+    // Hash of the cached payload behind this response, for the ETag
+    let mut payload = DefaultHasher::new();
+    for (cluster, cluster_cache) in clusters {
+        let age = cluster_cache.age();
+        let stale = cluster_cache.is_stale(stale_after);
+        all_stale &= stale;
+        (cluster, &cluster_cache.data, cluster_cache.updated_at, stale, cluster_cache.paused).hash(&mut payload);
+        // In "unavailable" mode a stale cluster only reports its freshness
+        let mut data = if stale && stale_action == StaleAction::Unavailable {
+            Exposition::new()
+        } else {
+            cluster_cache.data.clone()
+        };
+        freshness_metrics(&mut data, cluster_cache, age, stale);
+        output.merge(data.with_label("cluster", cluster));
+    }
+    // `metrics.labels` go in front of every series, in name order
+    labels.hash(&mut payload);
+    for (name, value) in labels.iter().rev() {
+        output = output.with_label(name, value);
+    }
+    if all_stale && stale_action == StaleAction::Unavailable {
+        let unavailable = Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body(Body::from("503 Service Unavailable: metrics data is stale\n"))
+            .unwrap();
+        return Ok(unavailable);
     }
+    let format = Format::negotiate(req.headers().get(ACCEPT).and_then(|accept| accept.to_str().ok()));
+    let accept_encoding = req.headers().get(ACCEPT_ENCODING).and_then(|encoding| encoding.to_str().ok());
+    let compress = gzip_level > 0 && accepts_gzip(accept_encoding);
+
+    // Weak, because the data age gauge is taken at scrape time and differs even
+    // when the cached payload does not
+    (format, compress).hash(&mut payload);
+    let etag = format!("W/\"{:016x}\"", payload.finish());
+    let if_none_match = req.headers().get(IF_NONE_MATCH).and_then(|value| value.to_str().ok());
+    if etag_matches(if_none_match, &etag) {
+        let not_modified = Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(ETAG, etag)
+            .header(VARY, "Accept-Encoding")
+            .body(Body::empty())
+            .unwrap();
+        return Ok(not_modified);
+    }
+
+    let body = output.render(format);
+    let mut response = Response::builder()
+        .header(CONTENT_TYPE, format.content_type())
+        .header(ETAG, &etag)
+        .header(VARY, "Accept-Encoding");
+    let body = if compress {
+        response = response.header(CONTENT_ENCODING, "gzip");
+        gzip(body.as_bytes(), gzip_level)
+    } else {
+        body.into_bytes()
+    };
+    Ok(response.body(Body::from(body)).unwrap())
 }
 
-// Main function to run the exporter
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let cache = Arc::new(Mutex::new(MetricsCache::new()));
-    let cache_clone = Arc::clone(&cache);
+// What the fetch loops share with the HTTP handlers
+#[derive(Clone)]
+struct LoopShared {
+    cache: SharedCache,
+    updates: broadcast::Sender<String>,
+    refresh: broadcast::Sender<String>,
+    watchlist: Arc<Watchlist>,
+}
 
-    // Background task to fetch and update metrics
-    task::spawn(async move {
-        let client = RpcClient::new("https://api.mainnet-beta.solana.com".to_string());
+// What a fetch loop follows about the validators from poll to poll, kept by
+// its supervisor so a restart by the watchdog does not start it over
+#[derive(Default)]
+struct Trackers {
+    // Ranks of the watched validators when first seen in `start_epoch`
+    start_epoch: Option<u64>,
+    start_ranks: HashMap<String, usize>,
+    commissions: Tracker,
+    // When each delinquent vote account was first seen delinquent
+    delinquent_since: HashMap<String, Instant>,
+}
 
-        loop {
-            let start = Instant::now();
-            let result = timeout(Duration::from_secs_f32(4.5), async {
-                fetch_and_calculate_metrics(&client)
-            })
-            .await;
+// Poll the RPC endpoints of one cluster and keep its metrics cache up to date
+// until the cluster is removed from the configuration. Runs under the watchdog,
+// which restarts it when it panics or stops making progress.
+async fn fetch_loop(
+    cluster: String,
+    mut fetch_config_rx: watch::Receiver<Arc<Config>>,
+    heartbeat: Arc<Heartbeat>,
+    restarts: Arc<RestartCounter>,
+    trackers: Arc<Mutex<Trackers>>,
+    shared: LoopShared,
+) {
+    let LoopShared { cache, updates, refresh, watchlist } = shared;
+    let mut refresh = refresh.subscribe();
+    // An entry before the first poll, so the cluster is reported as stale until then
+    cache.lock().await.entry(cluster.clone()).or_insert_with(MetricsCache::new);
 
-            // Only lock the cache when updating it
-            let new_data = match result {
-                Ok(Ok((validator_metrics, active_count))) => {
-                    let duration = start.elapsed().as_secs_f64();
-                    export_prometheus_metrics(validator_metrics, active_count, 1, duration, 0)
-                }
-                Ok(Err(_)) => export_prometheus_metrics(vec![], 0, 0, 0.0, 0),  // RPC failure
-                Err(_) => export_prometheus_metrics(vec![], 0, 0, 0.0, 1),     // Timeout case
+    let mut pool = EndpointPool::default();
+    let mut backoff = Backoff::default();
+    let mut durations = Histogram::new(&fetch_config_rx.borrow().metrics.rpc_duration_buckets);
+    let mut last_endpoint: Option<String> = None;
+    let mut epoch_info: Option<EpochInfo> = None;
+    // Epoch changes seen by this loop
+    let mut rollovers: u64 = 0;
+    let rollovers_created = SystemTime::now();
+    // Published validator info, and when it was last fetched
+    let mut directory = Directory::new();
+    let mut directory_fetched: Option<Instant> = None;
+    let mut listing = Listing::default();
+    let mut scores = Scores::default();
+    let mut verifications = Verifications::default();
+    let mut gossip = Gossip::default();
+    let mut geoip = GeoIp::default();
+    let mut prober = Prober::default();
+    let mut production = BlockProduction::default();
+    let mut schedule = LeaderSchedule::default();
+    let mut rewards = Rewards::default();
+    let mut inflation = Inflation::default();
+    let mut tips = Tips::default();
+    let mut performance = Performance::default();
+    let mut vote_fees = VoteFees::default();
+
+    // Polls start on a fixed schedule; a poll that overruns skips the ticks it missed
+    let mut period = fetch_config_rx.borrow().poll.period(None);
+    let mut ticker = interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    // Asked for while backing off, so the poll starts right after the delay
+    let mut refresh_pending = false;
+
+    loop {
+        heartbeat.expect_within(period);
+        let requested = std::mem::take(&mut refresh_pending)
+            || tokio::select! {
+                _ = ticker.tick() => false,
+                _ = refresh_requested(&mut refresh, &cluster) => true,
             };
+        // Polled out of turn; the next regular poll is a full period later
+        if requested {
+            debug!("Polling cluster {} on request", cluster);
+            ticker.reset();
+        }
+        // While paused only polls asked for through `/-/reload-data` run
+        if !requested && cache.lock().await.get(&cluster).is_some_and(|cache| cache.paused) {
+            continue;
+        }
+
+        let started = Instant::now();
+
+        // Pick up settings swapped in by a reload before every fetch
+        let fetch_config = fetch_config_rx.borrow_and_update().clone();
+        let Some(rpc) = fetch_config.cluster_rpc(&cluster) else {
+            cache.lock().await.remove(&cluster);
+            info!("Cluster {} is no longer configured, stopping its polls", cluster);
+            return;
+        };
+        let new_period = fetch_config.poll.period(epoch_info.as_ref().map(epoch_progress));
+        if new_period != period {
+            period = new_period;
+            ticker = interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            heartbeat.expect_within(period);
+            ticker.tick().await;
+        }
+        pool.sync(rpc);
+        if durations.bounds() != fetch_config.metrics.rpc_duration_buckets.as_slice() {
+            durations = Histogram::new(&fetch_config.metrics.rpc_duration_buckets);
+        }
+
+        // Respect per-endpoint rate limits: wait until at least one endpoint may be used
+        let mut order = pool.attempt_order(rpc.mode);
+        if order.is_empty() {
+            let wait = pool.next_available_in();
+            heartbeat.expect_within(wait);
+            sleep(wait).await;
+            order = pool.attempt_order(rpc.mode);
+        }
 
-            // Update the cache outside the main loop to minimize the lock time
-            {
-                let mut cache = cache_clone.lock().await;
-                cache.data = new_data;
+        // Try the endpoints in order until one of them answers, or until
+        // `rpc.quorum` of them did
+        let wanted = rpc.quorum.min(pool.endpoints.len()).max(1);
+        let mut answers: Vec<Answer> = Vec::new();
+        let mut timed_out = false;
+        for i in order {
+            if answers.len() >= wanted {
+                break;
             }
+            let endpoint = &mut pool.endpoints[i];
+            endpoint.mark_used();
+            heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+            let attempt_start = Instant::now();
+            let result = timeout(Duration::from_secs_f64(rpc.timeout), fetch_vote_accounts(&endpoint.client)).await;
+            let duration = attempt_start.elapsed().as_secs_f64();
+            durations.observe(duration);
 
-            // Calculate next delay based on RPC call time + 2 seconds
-            let duration = start.elapsed().as_secs_f64();
-            sleep(Duration::from_secs_f64(duration + 2.0)).await;
+            match result {
+                Ok(Ok((vote_accounts, epoch_info))) => {
+                    debug!(
+                        "Polled cluster {} from {} in {:.3}s: {} vote accounts",
+                        cluster,
+                        endpoint.label,
+                        duration,
+                        vote_accounts.current.len() + vote_accounts.delinquent.len()
+                    );
+                    endpoint.record_success(duration);
+                    // The other sources are asked once per poll, of the first endpoint that answers
+                    if answers.is_empty() {
+                        last_endpoint = Some(endpoint.label.clone());
+                        let info = &fetch_config.validator_info;
+                        if !info.enabled {
+                            directory.clear();
+                        } else if directory_fetched.is_none_or(|fetched| fetched.elapsed().as_secs_f64() >= info.refresh_interval) {
+                            // From the endpoint that just answered; a failure keeps the
+                            // names from before and is tried again with the next poll
+                            heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+                            match timeout(Duration::from_secs_f64(rpc.timeout), validator_info::fetch(&endpoint.client)).await {
+                                Ok(Ok(fetched)) => {
+                                    debug!("Fetched validator info of cluster {}: {} validators", cluster, fetched.len());
+                                    directory = fetched;
+                                    directory_fetched = Some(Instant::now());
+                                }
+                                Ok(Err(e)) => warn!("Validator info request to {} failed: {}", endpoint.label, e),
+                                Err(_) => warn!("Validator info request to {} timed out", endpoint.label),
+                            }
+                        }
+                        match fetch_config.gossip_interval() {
+                            None => gossip = Gossip::default(),
+                            Some(interval) => {
+                                heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+                                if timeout(Duration::from_secs_f64(rpc.timeout), gossip.refresh(&endpoint.client, interval)).await.is_err() {
+                                    warn!("Cluster nodes request to {} timed out", endpoint.label);
+                                }
+                            }
+                        }
+                        if !fetch_config.geoip.enabled() {
+                            geoip = GeoIp::default();
+                        } else {
+                            geoip.refresh(&gossip, &fetch_config.geoip);
+                        }
+                        if !fetch_config.block_production.enabled {
+                            production = BlockProduction::default();
+                        } else {
+                            heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+                            let epoch = epoch_info.as_ref().map(|info| info.epoch);
+                            let refresh = production.refresh(&endpoint.client, &fetch_config.block_production, epoch);
+                            if timeout(Duration::from_secs_f64(rpc.timeout), refresh).await.is_err() {
+                                warn!("Block production request to {} timed out", endpoint.label);
+                            }
+                        }
+                        if !fetch_config.leader_schedule.enabled {
+                            schedule = LeaderSchedule::default();
+                        } else if let Some(info) = &epoch_info {
+                            heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+                            if timeout(Duration::from_secs_f64(rpc.timeout), schedule.refresh(&endpoint.client, info.epoch)).await.is_err() {
+                                warn!("Leader schedule request to {} timed out", endpoint.label);
+                            }
+                        }
+                        if !fetch_config.inflation_rewards.enabled {
+                            rewards = Rewards::default();
+                        } else if let Some(info) = &epoch_info {
+                            let watched = watchlist.all(&fetch_config);
+                            let vote_pubkeys: Vec<&str> = watched.iter().map(|watched| watched.vote_pubkey.as_str()).collect();
+                            heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+                            if timeout(Duration::from_secs_f64(rpc.timeout), rewards.refresh(&endpoint.client, &vote_pubkeys, info.epoch)).await.is_err() {
+                                warn!("Inflation reward request to {} timed out", endpoint.label);
+                            }
+                        }
+                        let supply = &fetch_config.supply;
+                        if !fetch_config.apy.enabled && !supply.enabled {
+                            inflation = Inflation::default();
+                        } else if let Some(info) = &epoch_info {
+                            heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+                            let refresh = inflation.refresh(&endpoint.client, info.epoch, supply.enabled.then_some(supply.refresh_interval));
+                            if timeout(Duration::from_secs_f64(rpc.timeout), refresh).await.is_err() {
+                                warn!("Inflation request to {} timed out", endpoint.label);
+                            }
+                        }
+                        if !fetch_config.jito.enabled {
+                            tips = Tips::default();
+                        } else if let Some(info) = &epoch_info {
+                            let watched = watchlist.all(&fetch_config);
+                            let vote_pubkeys: Vec<&str> = watched.iter().map(|watched| watched.vote_pubkey.as_str()).collect();
+                            heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+                            let refresh = tips.refresh(&endpoint.client, &vote_pubkeys, info.epoch, &fetch_config.jito);
+                            if timeout(Duration::from_secs_f64(rpc.timeout), refresh).await.is_err() {
+                                warn!("Tip distribution accounts request to {} timed out", endpoint.label);
+                            }
+                        }
+                        if !fetch_config.performance.enabled {
+                            performance = Performance::default();
+                        } else {
+                            heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+                            let refresh = performance.refresh(&endpoint.client, &fetch_config.performance);
+                            if timeout(Duration::from_secs_f64(rpc.timeout), refresh).await.is_err() {
+                                warn!("Performance samples request to {} timed out", endpoint.label);
+                            }
+                        }
+                        if !fetch_config.vote_fees.enabled {
+                            vote_fees = VoteFees::default();
+                        } else {
+                            heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+                            if timeout(Duration::from_secs_f64(rpc.timeout), vote_fees.refresh(&endpoint.client, &fetch_config.vote_fees)).await.is_err() {
+                                warn!("Vote fee request to {} timed out", endpoint.label);
+                            }
+                        }
+                    }
+                    answers.push(Answer {
+                        endpoint: endpoint.label.clone(),
+                        vote_accounts,
+                        epoch_info,
+                    });
+                }
+                Ok(Err(e)) => {
+                    warn!("RPC request to {} failed: {}", endpoint.label, e);
+                    endpoint.record_failure(rpc, false, RateLimited::of(e.as_ref()).as_ref());
+                    timed_out = false;
+                }
+                Err(_) => {
+                    warn!("RPC request to {} timed out", endpoint.label);
+                    endpoint.record_failure(rpc, true, None);
+                    timed_out = true;
+                }
+            }
+        }
+        let mut disagreement = None;
+        let fetched = if rpc.quorum > 1 && !answers.is_empty() {
+            let (vote_accounts, epoch_info, found) = quorum::reconcile(answers, rpc.quorum_tolerance);
+            if found.validators > 0 {
+                debug!("Endpoints of cluster {} disagree on {} validators", cluster, found.validators);
+            }
+            disagreement = Some(found);
+            Some(calculate_metrics(&vote_accounts, epoch_info, fetch_config.metrics.include_zero_credit))
+        } else {
+            answers
+                .pop()
+                .map(|answer| calculate_metrics(&answer.vote_accounts, answer.epoch_info, fetch_config.metrics.include_zero_credit))
+        };
+
+        // Whether the endpoints' nodes are healthy and keep up, failing or not
+        heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+        pool.check_health(rpc).await;
+        heartbeat.expect_within(Duration::from_secs_f64(rpc.timeout));
+        pool.check_slots(rpc).await;
+
+        // validators.app only matters with fresh data to label
+        let app = &fetch_config.validators_app;
+        if !app.enabled {
+            listing = Listing::default();
+        } else if let (true, Some(network)) = (fetched.is_some(), app.network(&cluster)) {
+            heartbeat.expect_within(Duration::from_secs_f64(app.timeout));
+            listing.refresh(app, network).await;
+        }
+
+        let keybase = &fetch_config.keybase;
+        if !keybase.enabled {
+            verifications = Verifications::default();
+        } else if fetched.is_some() {
+            heartbeat.expect_within(Duration::from_secs_f64(keybase.timeout) * 2);
+            verifications.refresh(keybase, &directory).await;
+        }
+
+        // Back off while every endpoint keeps failing, resume the normal cadence on success
+        let succeeded = fetched.is_some();
+        if succeeded {
+            backoff.reset();
+        } else {
+            backoff.fail(&fetch_config.poll.backoff);
+        }
+
+        let no_nodes = HashMap::new();
+        let known = Enrichment {
+            directory: &directory,
+            app: &listing.validators,
+            keybase: &verifications,
+            locations: &geoip.locations,
+            nodes: if fetch_config.versions.enabled { &gossip.nodes } else { &no_nodes },
+        };
+        // When every endpoint failed, report whether the last attempt was a timeout
+        let new_data = match &fetched {
+            Some(data) => {
+                if data.epoch_info.is_some() {
+                    epoch_info = data.epoch_info.clone();
+                }
+                let mut trackers = trackers.lock().await;
+                let Trackers { start_epoch, start_ranks, commissions, delinquent_since } = &mut *trackers;
+                let epoch = epoch_info.as_ref().map(|info| info.epoch);
+                if epoch != *start_epoch {
+                    if let (Some(previous), Some(epoch)) = (*start_epoch, epoch) {
+                        info!("Cluster {} moved from epoch {} to {}", cluster, previous, epoch);
+                        rollovers += 1;
+                    }
+                    *start_epoch = epoch;
+                    start_ranks.clear();
+                }
+                let report = RpcReport {
+                    poll_interval: period,
+                    status: 1,
+                    durations: &durations,
+                    timeout: 0,
+                    last_endpoint: last_endpoint.as_deref(),
+                    endpoints: &pool.endpoints,
+                    backoff: &backoff,
+                    restarts: &restarts,
+                };
+                // Watched validators without a name in the watchlist go by the published one
+                let mut watched = watchlist.all(&fetch_config);
+                for validator in watched.iter_mut().filter(|validator| validator.name.is_none()) {
+                    let node = data.nodes.get(&validator.vote_pubkey).map_or("", String::as_str);
+                    validator.name = known.name(&validator.vote_pubkey, node).map(str::to_string);
+                }
+                let probing = &fetch_config.probe;
+                if !probing.enabled {
+                    prober = Prober::default();
+                } else {
+                    let nodes: Vec<(&str, &Node)> = watched
+                        .iter()
+                        .filter_map(|watched| {
+                            let identity = data.nodes.get(&watched.vote_pubkey)?;
+                            Some((identity.as_str(), gossip.nodes.get(identity)?))
+                        })
+                        .collect();
+                    heartbeat.expect_within(Duration::from_secs_f64(probing.timeout) * 2);
+                    prober.refresh(&nodes, probing).await;
+                }
+                let stakewiz = &fetch_config.stakewiz;
+                if stakewiz.enabled && stakewiz.cluster == cluster {
+                    heartbeat.expect_within(Duration::from_secs_f64(stakewiz.timeout).saturating_mul(watched.len() as u32));
+                    scores.refresh(stakewiz, &watched).await;
+                } else {
+                    scores = Scores::default();
+                }
+                let mut active_count = data.active_count;
+                if fetch_config.metrics.active_includes_delinquent {
+                    active_count += data.validators.iter().filter(|validator| validator.delinquent && validator.credits_earned > 0).count();
+                }
+                let mut output = export_prometheus_metrics(
+                    &data.validators,
+                    active_count,
+                    &watched,
+                    &known,
+                    &fetch_config.metrics,
+                    &report,
+                );
+                export_watched_metrics(&mut output, data, &watched, &fetch_config.metrics, start_ranks);
+                commissions.observe(&data.commissions, epoch_info.as_ref().map(epoch_progress), fetch_config.metrics.commission_late_epoch);
+                export_commission_metrics(&mut output, data, commissions, &known);
+                export_stake_metrics(&mut output, data);
+                let zero_credit = output.family(
+                    "solana_validator_zero_credit",
+                    "Vote accounts that earned no credits this epoch, exported or not",
+                    MetricType::Gauge,
+                );
+                zero_credit.sample(&[("status", "current")], data.zero_credit_current as f64);
+                zero_credit.sample(&[("status", "delinquent")], data.zero_credit_delinquent as f64);
+                // Forgotten once the validator votes again, so a new outage starts from 0
+                delinquent_since.retain(|vote_pubkey, _| data.nodes.contains_key(vote_pubkey) && !data.current.contains(vote_pubkey));
+                for vote_pubkey in data.nodes.keys().filter(|vote_pubkey| !data.current.contains(*vote_pubkey)) {
+                    delinquent_since.entry(vote_pubkey.clone()).or_insert_with(Instant::now);
+                }
+                export_delinquency_metrics(&mut output, data, delinquent_since, &known);
+                if fetch_config.sfdp.enabled {
+                    export_sfdp_metrics(&mut output, data, &watched, delinquent_since, &fetch_config.sfdp);
+                }
+                if fetch_config.block_production.enabled {
+                    export_block_production_metrics(&mut output, data, &production, &known);
+                }
+                if let Some(disagreement) = &disagreement {
+                    export_quorum_metrics(&mut output, disagreement);
+                }
+                if let Some(rates) = &performance.rates {
+                    export_performance_metrics(&mut output, rates);
+                }
+                if fetch_config.inflation_rewards.enabled {
+                    export_reward_metrics(&mut output, data, &watched, &rewards);
+                }
+                if fetch_config.jito.enabled {
+                    export_jito_metrics(&mut output, data, &watched, &tips);
+                }
+                if fetch_config.supply.enabled {
+                    export_supply_metrics(&mut output, &inflation);
+                }
+                if let (true, Some(info)) = (fetch_config.apy.enabled, &data.epoch_info) {
+                    export_apy_metrics(&mut output, data, &watched, &inflation, info);
+                }
+                if let Some(lamports) = vote_fees.lamports {
+                    export_vote_fee_metrics(&mut output, data, &watched, lamports, performance.rates.as_ref());
+                }
+                if let (true, Some(info)) = (fetch_config.leader_schedule.enabled, &data.epoch_info) {
+                    export_leader_schedule_metrics(&mut output, data, &watched, &schedule, info);
+                }
+                if probing.enabled {
+                    export_probe_metrics(&mut output, data, &watched, &prober);
+                }
+                if stakewiz.enabled && stakewiz.cluster == cluster {
+                    export_stakewiz_metrics(&mut output, data, &watched, &scores);
+                }
+                output
+            }
+            None => {
+                let report = RpcReport {
+                    poll_interval: period,
+                    status: 0,
+                    durations: &durations,
+                    timeout: timed_out as u8,
+                    last_endpoint: last_endpoint.as_deref(),
+                    endpoints: &pool.endpoints,
+                    backoff: &backoff,
+                    restarts: &restarts,
+                };
+                export_prometheus_metrics(&[], 0, &[], &known, &fetch_config.metrics, &report)
+            }
+        };
+
+        let mut new_data = new_data;
+        let rollover = new_data.family("solana_epoch_rollover_total", "Epoch changes seen since the fetch task started", MetricType::Counter);
+        rollover.sample(&[], rollovers as f64);
+        rollover.created(&[], rollovers_created);
+
+        let new_data = new_data.with_prefix("solana_validator_", &fetch_config.metrics.prefix);
+
+        // Update the cache outside the main loop to minimize the lock time
+        {
+            let mut cache = cache.lock().await;
+            let cache = cache.entry(cluster.clone()).or_insert_with(MetricsCache::new);
+            cache.data = new_data;
+            cache.attempted = Some(started);
+            if let Some(data) = fetched {
+                cache.polled = Some(started);
+                cache.updated = Some(Instant::now());
+                cache.updated_at = Some(SystemTime::now());
+                cache.validators = data.validators;
+                cache.epoch_info = epoch_info.clone();
+            }
+        }
+        // Nobody may be listening
+        let _ = updates.send(cluster.clone());
+
+        // While backing off, wait out the delay first; the poll then starts on
+        // the next tick, or at once when one is asked for meanwhile
+        if backoff.failures() > 0 {
+            heartbeat.expect_within(backoff.delay());
+            tokio::select! {
+                _ = sleep(backoff.delay()) => {}
+                _ = refresh_requested(&mut refresh, &cluster) => refresh_pending = true,
+            }
+        }
+    }
+}
+
+// Resolves once `/-/reload-data` asks for `cluster` to be polled
+async fn refresh_requested(refresh: &mut broadcast::Receiver<String>, cluster: &str) {
+    loop {
+        match refresh.recv().await {
+            Ok(name) if name == cluster => return,
+            Ok(_) => {}
+            // Better one poll too many than a request left waiting
+            Err(RecvError::Lagged(_)) => return,
+            Err(RecvError::Closed) => std::future::pending().await,
+        }
+    }
+}
+
+// Keep a supervised fetch loop running for every configured cluster. Loops of
+// removed clusters stop by themselves on their next poll.
+async fn run_clusters(mut config_rx: watch::Receiver<Arc<Config>>, shared: LoopShared) {
+    let mut running: BTreeMap<String, task::JoinHandle<()>> = BTreeMap::new();
+    loop {
+        running.retain(|_, supervisor| !supervisor.is_finished());
+        let config = config_rx.borrow_and_update().clone();
+        for (name, _) in config.clusters() {
+            if running.contains_key(name) {
+                continue;
+            }
+            let heartbeat = Arc::new(Heartbeat::new());
+            let restarts = Arc::new(RestartCounter::new());
+            let trackers = Arc::new(Mutex::new(Trackers::default()));
+            let cluster = name.to_string();
+            let (loop_config_rx, loop_heartbeat, loop_restarts, loop_shared) =
+                (config_rx.clone(), Arc::clone(&heartbeat), Arc::clone(&restarts), shared.clone());
+            let supervisor = task::spawn(watchdog::supervise(
+                cluster.clone(),
+                move || {
+                    task::spawn(fetch_loop(
+                        cluster.clone(),
+                        loop_config_rx.clone(),
+                        Arc::clone(&loop_heartbeat),
+                        Arc::clone(&loop_restarts),
+                        Arc::clone(&trackers),
+                        loop_shared.clone(),
+                    ))
+                },
+                heartbeat,
+                restarts,
+                config_rx.clone(),
+            ));
+            running.insert(name.to_string(), supervisor);
+        }
+        if config_rx.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+// Accept connections forever, plain or HTTPS; each one gets its TLS handshake
+// and HTTP connection in its own task, so a slow client cannot hold up the others
+async fn serve(listener: TcpListener, tls: Option<Arc<ReloadingTls>>, state: HttpState) -> Result<(), Box<dyn Error + Send + Sync>> {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                error!("Failed to accept connection: {}", e);
+                sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        // Over the limit the connection is closed before reading anything
+        let max_connections = state.config.borrow().server.limits.max_connections;
+        let Some(slot) = state.connections.try_acquire(max_connections) else {
+            continue;
+        };
+        let acceptor = tls.as_ref().map(|tls| tls.acceptor());
+        let state = state.clone();
+        task::spawn(async move {
+            let _slot = slot;
+            match acceptor {
+                Some(acceptor) => {
+                    // Failed handshakes are the client's problem (scanners, wrong CA); not worth a log line each
+                    if let Ok(Ok(stream)) = timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        serve_connection(stream, peer, state).await;
+                    }
+                }
+                None => serve_connection(stream, peer, state).await,
+            }
+        });
+    }
+}
+
+// Answer the requests of one connection from `peer`, each within
+// `server.limits.request_timeout` counted from its first byte
+async fn serve_connection<S>(stream: S, peer: SocketAddr, state: HttpState)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let request_timeout = Duration::from_secs_f64(state.config.borrow().server.limits.request_timeout);
+    let service = service_fn(move |req| {
+        let state = state.clone();
+        async move {
+            let started = Instant::now();
+            let (method, path) = (req.method().clone(), req.uri().path().to_string());
+            let access_log = state.config.borrow().server.access_log;
+            let response = match timeout(request_timeout, serve_metrics(req, peer, state)).await {
+                Ok(Ok(response)) => response,
+                Err(_) => Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .body(Body::from("503 Service Unavailable: request timed out\n"))
+                    .unwrap(),
+            };
+            let status = response.status();
+            let logged = match access_log {
+                AccessLog::Off => false,
+                AccessLog::Errors => status.is_client_error() || status.is_server_error(),
+                AccessLog::All => true,
+            };
+            if logged {
+                info!(
+                    target: ACCESS_LOG_TARGET,
+                    "{} {} {} {} {:.1}ms",
+                    peer,
+                    method,
+                    path,
+                    status.as_u16(),
+                    started.elapsed().as_secs_f64() * 1000.0
+                );
+            }
+            Ok::<_, Infallible>(response)
         }
     });
+    // Connection errors (clients going away, header timeouts) are not logged
+    let _ = Http::new()
+        .http1_header_read_timeout(request_timeout)
+        .serve_connection(stream, service)
+        .with_upgrades()
+        .await;
+}
+
+// `--check-config`: report every problem with the configuration and exit
+async fn check_config(args: &Args) -> ! {
+    let config = match args.load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let problems = config.check_endpoints().await;
+    if !problems.is_empty() {
+        eprintln!("invalid configuration:");
+        for problem in problems {
+            eprintln!("  - {}", problem);
+        }
+        process::exit(1);
+    }
+
+    println!("Configuration OK");
+    process::exit(0);
+}
 
-    // Serve metrics on 127.0.0.1:59872 only for `/metrics` route
-    let addr = ([127, 0, 0, 1], 59872).into();
-    let make_svc = make_service_fn(move |_conn| {
-        let cache = Arc::clone(&cache);
-        async move { Ok::<_, Infallible>(service_fn(move |req| {
-            let cache = Arc::clone(&cache);
-            async move { serve_metrics(req, cache).await }  // Pass `req` and `cache`
-        })) }
+// Main function to run the exporter
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let args = Args::from_command_line();
+    if args.check_config {
+        check_config(&args).await;
+    }
+
+    let config = match args.load_config() {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    logging::init(config.log_level);
+    let (config_tx, config_rx) = watch::channel(Arc::clone(&config));
+
+    let cache: SharedCache = Arc::new(Mutex::new(BTreeMap::new()));
+    // Loaded once by the config validation already; the file is re-read on every reload
+    let watchlist = Arc::new(Watchlist::new(match &config.watchlist_file {
+        Some(path) => watchlist::load(path)?,
+        None => Vec::new(),
+    }));
+
+    // One fetch loop per cluster, started and stopped as clusters come and go with reloads
+    let (updates, _) = broadcast::channel(16);
+    let (refresh, _) = broadcast::channel(16);
+    let shared = LoopShared {
+        cache: Arc::clone(&cache),
+        updates: updates.clone(),
+        refresh: refresh.clone(),
+        watchlist: Arc::clone(&watchlist),
+    };
+    task::spawn(run_clusters(config_rx.clone(), shared));
+
+    // Re-read the config file on SIGHUP and swap it in for the fetch loop and HTTP handler
+    let mut hangup = signal(SignalKind::hangup())?;
+    let reload_watchlist = Arc::clone(&watchlist);
+    task::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match args.load_config() {
+                Ok(mut new_config) => {
+                    let old_config = Arc::clone(&config_tx.borrow());
+                    // A level set through `/-/loglevel` stays until the file's own changes
+                    if new_config.log_level != old_config.log_level {
+                        logging::set_level(new_config.log_level);
+                    }
+                    // The sockets are bound once, so the running ones stay in the config
+                    if new_config.server.grpc_listen != old_config.server.grpc_listen {
+                        warn!("gRPC listen address changes require a restart, keeping {:?}", old_config.server.grpc_listen);
+                        new_config.server.grpc_listen = old_config.server.grpc_listen;
+                    }
+                    if new_config.server.listen != old_config.server.listen {
+                        warn!("Listen address changes require a restart, keeping {:?}", old_config.server.listen);
+                        new_config.server.listen = old_config.server.listen.clone();
+                    }
+                    // Other TLS changes (new certificate paths, client CAs) apply on reload
+                    if new_config.server.tls.is_some() != old_config.server.tls.is_some() {
+                        warn!("Switching between HTTP and HTTPS requires a restart");
+                        new_config.server.tls = old_config.server.tls.clone();
+                    }
+                    match &new_config.watchlist_file {
+                        // Valid, the config validation has just read it
+                        Some(path) => reload_watchlist.replace(watchlist::load(path).unwrap_or_default()),
+                        None => reload_watchlist.replace(Vec::new()),
+                    }
+                    config_tx.send_replace(Arc::new(new_config));
+                    info!("Configuration reloaded");
+                }
+                Err(e) => error!("Configuration reload failed, keeping previous settings: {}", e),
+            }
+        }
     });
-    let server = Server::bind(&addr).serve(make_svc);
 
-    println!("Serving metrics on http://127.0.0.1:59872/metrics");
-    server.await?;
+    // Use the sockets systemd passed in when socket activated, otherwise bind
+    // every listen address before serving, so a taken port fails the start
+    let listeners = match systemd::listeners() {
+        Some(listeners) => {
+            info!("Using {} socket(s) from systemd, ignoring server.listen", listeners.len());
+            listeners
+        }
+        None => {
+            let mut listeners = Vec::new();
+            for addr in &config.server.listen {
+                match std::net::TcpListener::bind(addr) {
+                    Ok(listener) => listeners.push(listener),
+                    Err(e) => {
+                        error!("Cannot listen on {}: {}", addr, e);
+                        process::exit(1);
+                    }
+                }
+            }
+            listeners
+        }
+    };
+
+    // The gRPC API always gets its own socket
+    let grpc_listener = match config.server.grpc_listen {
+        Some(addr) => match std::net::TcpListener::bind(addr) {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                error!("Cannot listen on {} for gRPC: {}", addr, e);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let tls = match &config.server.tls {
+        Some(tls_config) => {
+            let tls = Arc::new(ReloadingTls::new(tls_config)?);
+            task::spawn(Arc::clone(&tls).watch(config_rx.clone()));
+            Some(tls)
+        }
+        None => None,
+    };
+
+    let state = HttpState {
+        cache: Arc::clone(&cache),
+        config: config_rx.clone(),
+        connections: Arc::new(InFlight::default()),
+        scrapes: Arc::new(InFlight::default()),
+        updates,
+        refresh,
+        watchlist,
+        htpasswd: Arc::new(Htpasswd::default()),
+    };
+
+    // Serve metrics on every listener, only for the metrics routes
+    let mut servers = JoinSet::new();
+    for listener in listeners {
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        info!("Serving metrics on {}://{}{}", scheme, addr, config.metrics.path);
+        servers.spawn(serve(TcpListener::from_std(listener)?, tls.clone(), state.clone()));
+    }
+    if let Some(listener) = grpc_listener {
+        listener.set_nonblocking(true)?;
+        let scheme = if tls.is_some() { "TLS" } else { "plaintext" };
+        info!("Serving gRPC on {} over {}", listener.local_addr()?, scheme);
+        servers.spawn(grpc::serve(TcpListener::from_std(listener)?, tls.clone(), state.clone()));
+    }
+    systemd::notify("READY=1");
+    task::spawn(systemd::watchdog_pings());
+
+    // Servers only return on fatal errors
+    while let Some(result) = servers.join_next().await {
+        result??;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_validator(software_client: Option<&str>, jito: Option<bool>) -> AppValidator {
+        AppValidator {
+            software_client: software_client.map(str::to_string),
+            jito,
+            ..AppValidator::default()
+        }
+    }
+
+    fn node(version: Option<&str>) -> Node {
+        Node {
+            version: version.map(str::to_string),
+            ..Node::default()
+        }
+    }
+
+    // The client detected for a validator listed on validators.app as `listed`
+    // whose node advertises `version`
+    fn client(listed: Option<AppValidator>, version: Option<&str>) -> &'static str {
+        let (directory, keybase, locations) = (Directory::new(), Verifications::default(), HashMap::new());
+        let app: HashMap<String, AppValidator> = listed.into_iter().map(|listed| ("vote".to_string(), listed)).collect();
+        let nodes = HashMap::from([("node".to_string(), node(version))]);
+        let known = Enrichment { directory: &directory, app: &app, keybase: &keybase, locations: &locations, nodes: &nodes };
+        known.client("vote", "node")
+    }
+
+    #[test]
+    fn client_follows_validators_app() {
+        assert_eq!(client(Some(app_validator(Some("JitoLabs"), None)), Some("2.0.15")), "jito");
+        assert_eq!(client(Some(app_validator(Some("Agave"), Some(true))), Some("2.0.15")), "agave");
+        assert_eq!(client(Some(app_validator(Some("Frankendancer"), None)), Some("2.0.15")), "firedancer");
+        assert_eq!(client(Some(app_validator(Some("SolanaLabs"), None)), None), "solana-labs");
+    }
+
+    #[test]
+    fn client_falls_back_to_the_version() {
+        assert_eq!(client(None, Some("0.305.20111")), "firedancer");
+        assert_eq!(client(None, Some("1.17.34")), "solana-labs");
+        assert_eq!(client(None, Some("1.18.22")), "agave");
+        assert_eq!(client(None, Some("2.0.15")), "agave");
+        // Only validators.app tells Jito apart
+        assert_eq!(client(Some(app_validator(None, Some(true))), Some("2.0.15")), "jito");
+        assert_eq!(client(Some(app_validator(Some("unknown"), Some(false))), Some("2.0.15")), "agave");
+    }
+
+    #[test]
+    fn client_without_a_version_is_empty() {
+        assert_eq!(client(None, None), "");
+        assert_eq!(client(None, Some("unknown")), "");
+        assert_eq!(client(Some(app_validator(None, Some(true))), None), "");
+    }
+}
@@ -0,0 +1,328 @@
+// Reader for MaxMind DB files (GeoLite2 / GeoIP2 `.mmdb`), as much as lookups
+// need: the binary search tree over the address bits, and the data section
+// decoded into JSON values. Format: https://maxmind.github.io/MaxMind-DB/
+
+use serde_json::{Map, Value};
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+const METADATA_MARKER: &[u8] = b"\xab\xcd\xefMaxMind.com";
+
+// Maps, arrays and pointers a value may be nested in, as in libmaxminddb, so a
+// corrupt file with a pointer loop fails instead of overflowing the stack
+const MAX_DEPTH: usize = 512;
+
+pub struct Database {
+    data: Vec<u8>,
+    node_count: usize,
+    // Bits per record, two records per node
+    record_size: usize,
+    ip_version: u64,
+    // Offset of the data section
+    data_start: usize,
+    // Node IPv4 lookups start from: below ::/96 in an IPv6 tree
+    ipv4_start: usize,
+}
+
+impl Database {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Self::parse(data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn parse(data: Vec<u8>) -> Result<Self, String> {
+        let marker = data
+            .windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .ok_or("not a MaxMind DB file")?;
+        let metadata_start = marker + METADATA_MARKER.len();
+        let (metadata, _) = decode(&data, metadata_start, metadata_start, 0)?;
+        let field = |name: &str| metadata[name].as_u64().ok_or(format!("metadata without {}", name));
+        let node_count = field("node_count")? as usize;
+        let record_size = field("record_size")? as usize;
+        let ip_version = field("ip_version")?;
+        if ![24, 28, 32].contains(&record_size) {
+            return Err(format!("unsupported record size {}", record_size));
+        }
+        let data_start = node_count * record_size / 4 + 16;
+        if data_start > marker {
+            return Err("search tree larger than the file".to_string());
+        }
+
+        let mut database = Self {
+            data,
+            node_count,
+            record_size,
+            ip_version,
+            data_start,
+            ipv4_start: 0,
+        };
+        if ip_version == 6 {
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= node_count {
+                    break;
+                }
+                node = database.record(node, 0)?;
+            }
+            database.ipv4_start = node;
+        }
+        Ok(database)
+    }
+
+    // The left (`bit` 0) or right (1) record of `node`
+    fn record(&self, node: usize, bit: u128) -> Result<usize, String> {
+        let node_size = self.record_size / 4;
+        let bytes = self.data.get(node * node_size..(node + 1) * node_size).ok_or("corrupt search tree")?;
+        Ok(match (self.record_size, bit) {
+            (24, 0) => be(&bytes[0..3]),
+            (24, _) => be(&bytes[3..6]),
+            (28, 0) => (usize::from(bytes[3] & 0xf0) << 20) | be(&bytes[0..3]),
+            (28, _) => (usize::from(bytes[3] & 0x0f) << 24) | be(&bytes[4..7]),
+            (_, 0) => be(&bytes[0..4]),
+            (_, _) => be(&bytes[4..8]),
+        })
+    }
+
+    // The record for the network containing `ip`, None when the database has none
+    pub fn lookup(&self, ip: IpAddr) -> Result<Option<Value>, String> {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            ip => ip,
+        };
+        let (address, bits, mut node) = match ip {
+            IpAddr::V4(v4) => (u128::from(u32::from(v4)), 32, self.ipv4_start),
+            IpAddr::V6(_) if self.ip_version == 4 => return Ok(None),
+            IpAddr::V6(v6) => (u128::from(v6), 128, 0),
+        };
+        for i in (0..bits).rev() {
+            if node >= self.node_count {
+                break;
+            }
+            node = self.record(node, (address >> i) & 1)?;
+        }
+        if node == self.node_count {
+            return Ok(None);
+        }
+        if node < self.node_count {
+            return Err("search tree deeper than the address".to_string());
+        }
+        // Records between the node count and the data section point into the
+        // 16 separator bytes, which only a corrupt file has
+        let offset = node.checked_sub(self.node_count + 16).ok_or("search tree record points before the data section")?;
+        decode(&self.data, self.data_start + offset, self.data_start, 0).map(|(value, _)| Some(value))
+    }
+}
+
+fn be(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |value, &byte| value << 8 | usize::from(byte))
+}
+
+// The value at `offset` and where the next one starts; pointers count from
+// `base`, and `depth` is how deep the value is nested already
+fn decode(data: &[u8], offset: usize, base: usize, depth: usize) -> Result<(Value, usize), String> {
+    if depth > MAX_DEPTH {
+        return Err("data nested too deep".to_string());
+    }
+    let slice = |from: usize, len: usize| data.get(from..from + len).ok_or_else(|| "truncated data".to_string());
+    let control = slice(offset, 1)?[0];
+    let mut at = offset + 1;
+    let mut kind = control >> 5;
+
+    // Pointers carry their size in the control byte
+    if kind == 1 {
+        let size = usize::from((control >> 3) & 3) + 1;
+        let bytes = slice(at, size)?;
+        let high = usize::from(control & 7);
+        let pointer = match size {
+            1 => (high << 8) | be(bytes),
+            2 => ((high << 16) | be(bytes)) + 2048,
+            3 => ((high << 24) | be(bytes)) + 526_336,
+            _ => be(bytes),
+        };
+        let (value, _) = decode(data, base + pointer, base, depth + 1)?;
+        return Ok((value, at + size));
+    }
+    if kind == 0 {
+        kind = 7 + slice(at, 1)?[0];
+        at += 1;
+    }
+    let mut size = usize::from(control & 0x1f);
+    if size >= 29 {
+        let extra = size - 28;
+        let value = be(slice(at, extra)?);
+        size = [29, 285, 65_821][extra - 1] + value;
+        at += extra;
+    }
+
+    match kind {
+        // UTF-8 string
+        2 => Ok((Value::from(String::from_utf8_lossy(slice(at, size)?)), at + size)),
+        // double
+        3 => {
+            let bytes: [u8; 8] = slice(at, 8)?.try_into().unwrap();
+            Ok((Value::from(f64::from_be_bytes(bytes)), at + 8))
+        }
+        // bytes, not needed for lookups
+        4 => Ok((Value::Null, at + size)),
+        // uint16, uint32, uint64, uint128
+        5 | 6 | 9 | 10 => {
+            let bytes = slice(at, size)?;
+            let value = bytes.iter().fold(0u128, |value, &byte| value << 8 | u128::from(byte));
+            let value = u64::try_from(value).map_or_else(|_| Value::from(value.to_string()), Value::from);
+            Ok((value, at + size))
+        }
+        // map
+        7 => {
+            let mut map = Map::new();
+            for _ in 0..size {
+                let (key, next) = decode(data, at, base, depth + 1)?;
+                let (value, next) = decode(data, next, base, depth + 1)?;
+                let Value::String(key) = key else {
+                    return Err("map key is not a string".to_string());
+                };
+                map.insert(key, value);
+                at = next;
+            }
+            Ok((Value::Object(map), at))
+        }
+        // int32
+        8 => {
+            let bytes = slice(at, size)?;
+            let value = bytes.iter().fold(0u32, |value, &byte| value << 8 | u32::from(byte)) as i32;
+            Ok((Value::from(value), at + size))
+        }
+        // array
+        11 => {
+            let mut array = Vec::with_capacity(size);
+            for _ in 0..size {
+                let (value, next) = decode(data, at, base, depth + 1)?;
+                array.push(value);
+                at = next;
+            }
+            Ok((Value::Array(array), at))
+        }
+        // boolean, the value is the size
+        14 => Ok((Value::from(size != 0), at)),
+        // float
+        15 => {
+            let bytes: [u8; 4] = slice(at, 4)?.try_into().unwrap();
+            Ok((Value::from(f32::from_be_bytes(bytes)), at + 4))
+        }
+        other => Err(format!("unsupported data type {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Where a record pointing at data section offset 0 points in the one-node
+    // trees below
+    const DATA: usize = 1 + 16;
+
+    fn control(kind: u8, size: usize) -> Vec<u8> {
+        assert!(size < 29);
+        if kind <= 7 {
+            vec![kind << 5 | size as u8]
+        } else {
+            vec![size as u8, kind - 7]
+        }
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut encoded = control(2, value.len());
+        encoded.extend_from_slice(value.as_bytes());
+        encoded
+    }
+
+    fn uint32(value: u32) -> Vec<u8> {
+        let bytes: Vec<u8> = value.to_be_bytes().into_iter().skip_while(|&byte| byte == 0).collect();
+        let mut encoded = control(6, bytes.len());
+        encoded.extend(bytes);
+        encoded
+    }
+
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut encoded = control(7, entries.len());
+        for (key, value) in entries {
+            encoded.extend(string(key));
+            encoded.extend_from_slice(value);
+        }
+        encoded
+    }
+
+    fn array(values: &[Vec<u8>]) -> Vec<u8> {
+        let mut encoded = control(11, values.len());
+        values.iter().for_each(|value| encoded.extend_from_slice(value));
+        encoded
+    }
+
+    fn pointer(offset: usize) -> Vec<u8> {
+        assert!(offset < 2048);
+        vec![0x20 | (offset >> 8) as u8, offset as u8]
+    }
+
+    // An IPv4 database of one node: 0.0.0.0/1 goes to `left`, 128.0.0.0/1 to
+    // `right`, each a node number or `DATA` plus an offset in `data`
+    fn database(left: usize, right: usize, data: &[u8]) -> Result<Database, String> {
+        let mut file = Vec::new();
+        for record in [left, right] {
+            file.extend_from_slice(&(record as u32).to_be_bytes()[1..]);
+        }
+        file.extend_from_slice(&[0; 16]);
+        file.extend_from_slice(data);
+        file.extend_from_slice(METADATA_MARKER);
+        file.extend(map(&[("node_count", uint32(1)), ("record_size", uint32(24)), ("ip_version", uint32(4))]));
+        Database::parse(file)
+    }
+
+    // "DE" at offset 0, then the record of the lower half pointing back at it
+    fn country() -> Database {
+        let mut data = string("DE");
+        let record = DATA + data.len();
+        data.extend(map(&[
+            ("country", map(&[("iso_code", pointer(0))])),
+            ("asn", uint32(64_512)),
+            ("tags", array(&[string("a"), string("b")])),
+        ]));
+        database(record, 1, &data).unwrap()
+    }
+
+    #[test]
+    fn looks_up_records() {
+        let database = country();
+        let expected = json!({ "country": { "iso_code": "DE" }, "asn": 64_512, "tags": ["a", "b"] });
+        assert_eq!(database.lookup("1.2.3.4".parse().unwrap()).unwrap(), Some(expected.clone()));
+        // IPv4-mapped addresses are looked up as IPv4
+        assert_eq!(database.lookup("::ffff:1.2.3.4".parse().unwrap()).unwrap(), Some(expected));
+    }
+
+    #[test]
+    fn misses_addresses_without_a_record() {
+        let database = country();
+        assert_eq!(database.lookup("200.1.1.1".parse().unwrap()).unwrap(), None);
+        assert_eq!(database.lookup("2001:db8::1".parse().unwrap()).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_records_into_the_separator() {
+        let database = database(1 + 5, 1, &string("DE")).unwrap();
+        assert_eq!(database.lookup("1.2.3.4".parse().unwrap()).unwrap_err(), "search tree record points before the data section");
+    }
+
+    #[test]
+    fn rejects_pointer_loops() {
+        let database = database(DATA, 1, &pointer(0)).unwrap();
+        assert_eq!(database.lookup("1.2.3.4".parse().unwrap()).unwrap_err(), "data nested too deep");
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(Database::parse(b"not a database".to_vec()).err().unwrap(), "not a MaxMind DB file");
+        let truncated = database(DATA + 100, 1, &string("DE")).unwrap();
+        assert_eq!(truncated.lookup("1.2.3.4".parse().unwrap()).unwrap_err(), "truncated data");
+    }
+}
@@ -0,0 +1,55 @@
+// Transactions and slots the cluster processes per second, from
+// `getRecentPerformanceSamples`. The RPC node takes a sample every minute, so
+// they are fetched every `performance.refresh_interval` and averaged over the
+// last `performance.samples` of them.
+
+use crate::config::PerformanceConfig;
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rates {
+    pub transactions: f64,
+    // None from nodes that do not count them separately
+    pub non_vote_transactions: Option<f64>,
+    pub slots: f64,
+}
+
+#[derive(Default)]
+pub struct Performance {
+    fetched: Option<Instant>,
+    // Per second over the samples, None until they are fetched
+    pub rates: Option<Rates>,
+}
+
+impl Performance {
+    // Fetch the samples when due; a failure is logged and keeps the rates
+    // from before
+    pub async fn refresh(&mut self, client: &RpcClient, config: &PerformanceConfig) {
+        if self.fetched.is_some_and(|fetched| fetched.elapsed().as_secs_f64() < config.refresh_interval) {
+            return;
+        }
+        match client.get_recent_performance_samples(Some(config.samples)).await {
+            Ok(samples) => {
+                let seconds: u64 = samples.iter().map(|sample| u64::from(sample.sample_period_secs)).sum();
+                if seconds == 0 {
+                    warn!("No performance samples to average");
+                    return;
+                }
+                let seconds = seconds as f64;
+                let transactions: u64 = samples.iter().map(|sample| sample.num_transactions).sum();
+                let non_vote: Option<u64> = samples.iter().map(|sample| sample.num_non_vote_transactions).sum();
+                let slots: u64 = samples.iter().map(|sample| sample.num_slots).sum();
+                self.rates = Some(Rates {
+                    transactions: transactions as f64 / seconds,
+                    non_vote_transactions: non_vote.map(|count| count as f64 / seconds),
+                    slots: slots as f64 / seconds,
+                });
+                debug!("Fetched {} performance samples over {}s", samples.len(), seconds);
+                self.fetched = Some(Instant::now());
+            }
+            Err(e) => warn!("Performance samples request failed: {}", e),
+        }
+    }
+}
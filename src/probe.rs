@@ -0,0 +1,133 @@
+// Whether the ports the watched validators advertise in gossip can be reached
+// from the exporter, every `probe.interval`. TCP ports are connected to: the
+// RPC port, and the gossip port, where validators also answer the TCP ip echo
+// requests of nodes that join. The TPU ports only speak UDP, so they are sent
+// a datagram that is too short to be a transaction; a port that is closed
+// answers with an ICMP port unreachable, anything else counts as reachable.
+
+use crate::config::ProbeConfig;
+use crate::gossip::Node;
+use futures_util::future::join_all;
+use log::{debug, info};
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::timeout;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Port {
+    Gossip,
+    Tpu,
+    TpuQuic,
+    Rpc,
+}
+
+impl Port {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Port::Gossip => "gossip",
+            Port::Tpu => "tpu",
+            Port::TpuQuic => "tpu_quic",
+            Port::Rpc => "rpc",
+        }
+    }
+
+    fn address(&self, node: &Node) -> Option<SocketAddr> {
+        match self {
+            Port::Gossip => node.gossip,
+            Port::Tpu => node.tpu,
+            Port::TpuQuic => node.tpu_quic,
+            Port::Rpc => node.rpc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Probe {
+    pub reachable: bool,
+    // Seconds the TCP connection took, None for UDP ports and failures
+    pub latency: Option<f64>,
+}
+
+#[derive(Default)]
+pub struct Prober {
+    checked: Option<Instant>,
+    // The last probe of every advertised port, by node identity and port
+    pub results: BTreeMap<(String, Port), Probe>,
+}
+
+impl Prober {
+    // Probe the ports of `nodes` (identity and what it advertises) when due
+    pub async fn refresh(&mut self, nodes: &[(&str, &Node)], config: &ProbeConfig) {
+        if self.checked.is_some_and(|checked| checked.elapsed().as_secs_f64() < config.interval) {
+            return;
+        }
+        let limit = Duration::from_secs_f64(config.timeout);
+        let targets: Vec<(&str, Port, SocketAddr)> = nodes
+            .iter()
+            .flat_map(|&(identity, node)| {
+                [Port::Gossip, Port::Tpu, Port::TpuQuic, Port::Rpc]
+                    .into_iter()
+                    .filter_map(move |port| Some((identity, port, port.address(node)?)))
+            })
+            .collect();
+        let probes = join_all(targets.iter().map(|&(_, port, address)| probe(port, address, limit))).await;
+
+        let mut results = BTreeMap::new();
+        for ((identity, port, address), probe) in targets.into_iter().zip(probes) {
+            let key = (identity.to_string(), port);
+            let was_reachable = self.results.get(&key).map(|previous| previous.reachable);
+            if was_reachable != Some(probe.reachable) && was_reachable.is_some() {
+                let state = if probe.reachable { "reachable again" } else { "unreachable" };
+                info!("The {} port of {} at {} is {}", port.label(), identity, address, state);
+            }
+            results.insert(key, probe);
+        }
+        debug!("Probed {} ports", results.len());
+        self.results = results;
+        self.checked = Some(Instant::now());
+    }
+}
+
+async fn probe(port: Port, address: SocketAddr, limit: Duration) -> Probe {
+    match port {
+        Port::Gossip | Port::Rpc => {
+            let start = Instant::now();
+            match timeout(limit, TcpStream::connect(address)).await {
+                Ok(Ok(_)) => Probe {
+                    reachable: true,
+                    latency: Some(start.elapsed().as_secs_f64()),
+                },
+                _ => Probe {
+                    reachable: false,
+                    latency: None,
+                },
+            }
+        }
+        Port::Tpu | Port::TpuQuic => Probe {
+            reachable: probe_udp(address, limit).await,
+            latency: None,
+        },
+    }
+}
+
+// Unreachable only when the host says so: with a connected socket the ICMP
+// port unreachable surfaces as a refused connection on the next receive
+async fn probe_udp(address: SocketAddr, limit: Duration) -> bool {
+    let local: SocketAddr = if address.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let Ok(socket) = UdpSocket::bind(local).await else {
+        return false;
+    };
+    if socket.connect(address).await.is_err() || socket.send(&[0]).await.is_err() {
+        return false;
+    }
+    let mut buffer = [0; 64];
+    let refused = |e: &Error| e.kind() == ErrorKind::ConnectionRefused;
+    match timeout(limit, socket.recv(&mut buffer)).await {
+        Ok(Err(e)) => !refused(&e),
+        // The error is not always reported as readiness, so ask for it
+        _ => !matches!(socket.take_error(), Ok(Some(e)) if refused(&e)),
+    }
+}
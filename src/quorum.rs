@@ -0,0 +1,215 @@
+// Vote accounts from several endpoints merged into one view, for
+// `rpc.quorum` above 1. A provider that falls behind reports fewer credits,
+// never more, so each vote account is taken from the answer with the most
+// credits; the endpoints that reported materially fewer are counted, so a
+// stale provider shows up instead of quietly lowering the numbers.
+
+use solana_client::rpc_response::{RpcVoteAccountInfo, RpcVoteAccountStatus};
+use solana_sdk::epoch_info::EpochInfo;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// What one endpoint answered
+pub struct Answer {
+    pub endpoint: String,
+    pub vote_accounts: RpcVoteAccountStatus,
+    pub epoch_info: Option<EpochInfo>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Disagreement {
+    // Endpoints whose answers were merged
+    pub answers: usize,
+    // Validators whose credits differ between the endpoints by more than the tolerance
+    pub validators: usize,
+    // How many validators each endpoint reported too few credits for, or did not list
+    pub behind: BTreeMap<String, usize>,
+}
+
+// Latest epoch of a vote account and its credits in it, to compare the
+// account across answers
+type Progress = (u64, u64);
+
+fn progress(account: &RpcVoteAccountInfo) -> Progress {
+    account.epoch_credits.last().map_or((0, 0), |&(epoch, credits, _)| (epoch, credits))
+}
+
+// One answer with the furthest along entry of every vote account, and where the
+// endpoints disagreed by more than `tolerance` credits. `answers` must not be
+// empty; the first one wins ties.
+pub fn reconcile(answers: Vec<Answer>, tolerance: u64) -> (RpcVoteAccountStatus, Option<EpochInfo>, Disagreement) {
+    let mut disagreement = Disagreement {
+        answers: answers.len(),
+        ..Disagreement::default()
+    };
+    for answer in &answers {
+        disagreement.behind.insert(answer.endpoint.clone(), 0);
+    }
+
+    // The best entry of every vote account, whether it was current there, and
+    // which endpoints reported what
+    let mut best: HashMap<&str, (&RpcVoteAccountInfo, bool)> = HashMap::new();
+    let mut reported: HashMap<&str, Vec<(&str, Progress)>> = HashMap::new();
+    for answer in &answers {
+        let accounts = answer
+            .vote_accounts
+            .current
+            .iter()
+            .map(|account| (account, true))
+            .chain(answer.vote_accounts.delinquent.iter().map(|account| (account, false)));
+        for (account, current) in accounts {
+            let vote_pubkey = account.vote_pubkey.as_str();
+            reported.entry(vote_pubkey).or_default().push((&answer.endpoint, progress(account)));
+            match best.get(vote_pubkey) {
+                Some((known, _)) if progress(known) >= progress(account) => {}
+                _ => {
+                    best.insert(vote_pubkey, (account, current));
+                }
+            }
+        }
+    }
+
+    for (vote_pubkey, reports) in &reported {
+        let highest = progress(best[vote_pubkey].0);
+        let mut disagreed = false;
+        for (endpoint, progress) in reports {
+            // An entry of an earlier epoch is behind by the whole epoch
+            if progress.0 < highest.0 || highest.1 - progress.1 > tolerance {
+                *disagreement.behind.get_mut(*endpoint).unwrap() += 1;
+                disagreed = true;
+            }
+        }
+        disagreed |= reports.len() < answers.len();
+        disagreement.validators += disagreed as usize;
+    }
+    // Vote accounts an endpoint did not list at all
+    for answer in &answers {
+        let listed: HashSet<&str> = answer
+            .vote_accounts
+            .current
+            .iter()
+            .chain(&answer.vote_accounts.delinquent)
+            .map(|account| account.vote_pubkey.as_str())
+            .collect();
+        *disagreement.behind.get_mut(&answer.endpoint).unwrap() += reported.keys().filter(|vote_pubkey| !listed.contains(*vote_pubkey)).count();
+    }
+
+    let mut merged = RpcVoteAccountStatus {
+        current: Vec::new(),
+        delinquent: Vec::new(),
+    };
+    // In the order of the first answer, then of the others, as a single endpoint would list them
+    let mut seen = HashSet::new();
+    for answer in &answers {
+        for account in answer.vote_accounts.current.iter().chain(&answer.vote_accounts.delinquent) {
+            if !seen.insert(account.vote_pubkey.as_str()) {
+                continue;
+            }
+            let (account, current) = best[account.vote_pubkey.as_str()];
+            if current {
+                merged.current.push(account.clone());
+            } else {
+                merged.delinquent.push(account.clone());
+            }
+        }
+    }
+    let epoch_info = answers.iter().filter_map(|answer| answer.epoch_info.clone()).max_by_key(|info| info.absolute_slot);
+    (merged, epoch_info, disagreement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(vote_pubkey: &str, epoch: u64, credits: u64, commission: u8) -> RpcVoteAccountInfo {
+        RpcVoteAccountInfo {
+            vote_pubkey: vote_pubkey.to_string(),
+            node_pubkey: format!("node-{}", vote_pubkey),
+            activated_stake: 1_000,
+            commission,
+            epoch_vote_account: true,
+            epoch_credits: vec![(epoch, credits, 0)],
+            last_vote: 0,
+            root_slot: 0,
+        }
+    }
+
+    fn answer(endpoint: &str, current: Vec<RpcVoteAccountInfo>, delinquent: Vec<RpcVoteAccountInfo>) -> Answer {
+        Answer {
+            endpoint: endpoint.to_string(),
+            vote_accounts: RpcVoteAccountStatus { current, delinquent },
+            epoch_info: None,
+        }
+    }
+
+    fn epoch_info(absolute_slot: u64) -> EpochInfo {
+        EpochInfo { epoch: 10, slot_index: 0, slots_in_epoch: 432_000, absolute_slot, block_height: 0, transaction_count: None }
+    }
+
+    fn behind(disagreement: &Disagreement) -> Vec<(&str, usize)> {
+        disagreement.behind.iter().map(|(endpoint, count)| (endpoint.as_str(), *count)).collect()
+    }
+
+    #[test]
+    fn highest_credits_win() {
+        let answers = vec![
+            answer("a", vec![account("v1", 10, 100, 5), account("v2", 10, 300, 5)], vec![]),
+            answer("b", vec![account("v1", 10, 150, 5), account("v2", 10, 295, 5)], vec![]),
+        ];
+        let (merged, _, disagreement) = reconcile(answers, 10);
+        let credits: Vec<u64> = merged.current.iter().map(|account| progress(account).1).collect();
+        assert_eq!(credits, vec![150, 300]);
+        // v2 differs by less than the tolerance
+        assert_eq!(behind(&disagreement), vec![("a", 1), ("b", 0)]);
+        assert_eq!((disagreement.answers, disagreement.validators), (2, 1));
+    }
+
+    #[test]
+    fn earlier_epoch_counts_as_behind() {
+        let answers = vec![
+            answer("a", vec![account("v1", 9, 400_000, 5)], vec![]),
+            answer("b", vec![account("v1", 10, 20, 5)], vec![]),
+        ];
+        let (merged, _, disagreement) = reconcile(answers, 1_000);
+        assert_eq!(progress(&merged.current[0]), (10, 20));
+        assert_eq!(behind(&disagreement), vec![("a", 1), ("b", 0)]);
+        assert_eq!(disagreement.validators, 1);
+    }
+
+    #[test]
+    fn missing_account_counts_as_behind() {
+        let answers = vec![
+            answer("a", vec![account("v1", 10, 100, 5)], vec![]),
+            answer("b", vec![account("v1", 10, 100, 5)], vec![account("v2", 10, 50, 5)]),
+        ];
+        let (merged, _, disagreement) = reconcile(answers, 0);
+        assert_eq!(merged.current.len(), 1);
+        assert_eq!(merged.delinquent[0].vote_pubkey, "v2");
+        assert_eq!(behind(&disagreement), vec![("a", 1), ("b", 0)]);
+        assert_eq!(disagreement.validators, 1);
+    }
+
+    #[test]
+    fn ties_go_to_the_first_answer() {
+        let answers = vec![
+            answer("a", vec![], vec![account("v1", 10, 100, 5)]),
+            answer("b", vec![account("v1", 10, 100, 7)], vec![]),
+        ];
+        let (merged, _, disagreement) = reconcile(answers, 0);
+        assert!(merged.current.is_empty());
+        assert_eq!(merged.delinquent[0].commission, 5);
+        assert_eq!(behind(&disagreement), vec![("a", 0), ("b", 0)]);
+        assert_eq!(disagreement.validators, 0);
+    }
+
+    #[test]
+    fn merged_order_and_epoch_follow_the_answers() {
+        let mut first = answer("a", vec![account("v2", 10, 100, 5), account("v1", 10, 100, 5)], vec![]);
+        first.epoch_info = Some(epoch_info(1_000));
+        let mut second = answer("b", vec![account("v3", 10, 100, 5), account("v1", 10, 100, 5)], vec![]);
+        second.epoch_info = Some(epoch_info(1_200));
+        let (merged, epoch, _) = reconcile(vec![first, second, answer("c", vec![], vec![])], 0);
+        let order: Vec<&str> = merged.current.iter().map(|account| account.vote_pubkey.as_str()).collect();
+        assert_eq!(order, vec!["v2", "v1", "v3"]);
+        assert_eq!(epoch.map(|info| info.absolute_slot), Some(1_200));
+    }
+}
@@ -0,0 +1,66 @@
+// The inflation rewards the watched vote accounts were paid for the last
+// completed epoch, from `getInflationReward`. The rewards are paid in the
+// first block of the next epoch and never change, so they are fetched once
+// after each epoch change, and again when the watched validators change.
+
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Reward {
+    // Epoch the reward was earned in
+    pub epoch: u64,
+    // Lamports credited to the vote account, its commission on the stakers' rewards
+    pub lamports: u64,
+    // Commission in effect when the reward was paid, None from older nodes
+    pub commission: Option<u8>,
+}
+
+#[derive(Default)]
+pub struct Rewards {
+    // Epoch and vote accounts the rewards were fetched for
+    epoch: Option<u64>,
+    asked: BTreeSet<String>,
+    // Rewards by vote pubkey; accounts that got none are missing
+    pub by_vote: HashMap<String, Reward>,
+}
+
+impl Rewards {
+    // Fetch the rewards of `vote_pubkeys` for the epoch before `epoch` unless
+    // they are known already; a failure is logged and retried on the next poll
+    pub async fn refresh(&mut self, client: &RpcClient, vote_pubkeys: &[&str], epoch: u64) {
+        let Some(rewarded) = epoch.checked_sub(1) else {
+            return;
+        };
+        let asked: BTreeSet<String> = vote_pubkeys.iter().map(|vote_pubkey| vote_pubkey.to_string()).collect();
+        if self.epoch == Some(rewarded) && self.asked == asked {
+            return;
+        }
+        // The watchlist only holds valid pubkeys
+        let addresses: Vec<Pubkey> = asked.iter().filter_map(|vote_pubkey| Pubkey::from_str(vote_pubkey).ok()).collect();
+        match client.get_inflation_reward(&addresses, Some(rewarded)).await {
+            Ok(rewards) => {
+                self.by_vote = asked
+                    .iter()
+                    .zip(rewards)
+                    .filter_map(|(vote_pubkey, reward)| {
+                        let reward = reward?;
+                        let reward = Reward {
+                            epoch: reward.epoch,
+                            lamports: reward.amount,
+                            commission: reward.commission,
+                        };
+                        Some((vote_pubkey.clone(), reward))
+                    })
+                    .collect();
+                debug!("Fetched inflation rewards of epoch {}: {} of {} vote accounts rewarded", rewarded, self.by_vote.len(), asked.len());
+                self.epoch = Some(rewarded);
+                self.asked = asked;
+            }
+            Err(e) => warn!("Inflation reward request for epoch {} failed: {}", rewarded, e),
+        }
+    }
+}
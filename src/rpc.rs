@@ -0,0 +1,615 @@
+use crate::config::{BackoffConfig, CircuitBreakerConfig, EndpointConfig, RpcConfig, RpcMode};
+use crate::sender::{header_map, HttpSender, RateLimited, Throttle};
+use futures_util::future::join_all;
+use log::{debug, info, warn};
+use rand::Rng;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::RpcClientConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::time::timeout;
+use url::Url;
+
+// One configured RPC endpoint, the client talking to it and its request accounting
+pub struct Endpoint {
+    pub config: EndpointConfig,
+    transport: Transport,
+    pub label: String,
+    pub client: RpcClient,
+    // Fetch attempts by outcome
+    pub successes: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    // Response time of the last successful request, in seconds
+    pub last_duration: Option<f64>,
+    pub breaker: CircuitBreaker,
+    pub throttle: Arc<Throttle>,
+    // Whether the last `getHealth` succeeded, None until one was answered or
+    // with health checks off
+    pub healthy: Option<bool>,
+    health_checked: Option<Instant>,
+    // Slot the node was at in the last `getSlot`, None until one was answered,
+    // after a failed one or with slot checks off
+    pub slot: Option<u64>,
+    slot_checked: Option<Instant>,
+    // Set after a rate-limited response, no requests until then
+    paused_until: Option<Instant>,
+    last_used: Option<Instant>,
+    // When the request counters of this endpoint started
+    pub created: SystemTime,
+}
+
+impl Endpoint {
+    fn new(config: &EndpointConfig, transport: Transport, label: String) -> Self {
+        let throttle = Arc::new(Throttle::default());
+        Self {
+            config: config.clone(),
+            client: build_client(&config.url, &transport, &throttle),
+            transport,
+            label,
+            successes: 0,
+            errors: 0,
+            timeouts: 0,
+            last_duration: None,
+            breaker: CircuitBreaker::default(),
+            throttle,
+            healthy: None,
+            health_checked: None,
+            slot: None,
+            slot_checked: None,
+            paused_until: None,
+            last_used: None,
+            created: SystemTime::now(),
+        }
+    }
+
+    // Time left until the endpoint's rate limit and circuit breaker allow another request
+    fn wait_time(&self, now: Instant) -> Duration {
+        self.rate_limit_wait(now).max(self.breaker.wait_time(now))
+    }
+
+    // Time left until `min_interval` and a rate-limit pause allow another request
+    fn rate_limit_wait(&self, now: Instant) -> Duration {
+        let min_interval = Duration::from_secs_f64(self.config.min_interval);
+        let rate_limit = match self.last_used {
+            Some(last_used) => min_interval.saturating_sub(now.duration_since(last_used)),
+            None => Duration::ZERO,
+        };
+        let paused = match self.paused_until {
+            Some(until) => until.saturating_duration_since(now),
+            None => Duration::ZERO,
+        };
+        rate_limit.max(paused)
+    }
+
+    // Whether a check last made at `checked` is due again after `interval`
+    // seconds and the rate limit allows a request
+    fn check_due(&self, now: Instant, checked: Option<Instant>, interval: f64) -> bool {
+        checked.is_none_or(|checked| now.duration_since(checked).as_secs_f64() >= interval) && self.rate_limit_wait(now).is_zero()
+    }
+
+    pub fn mark_used(&mut self) {
+        self.last_used = Some(Instant::now());
+    }
+
+    pub fn record_success(&mut self, duration: f64) {
+        self.successes += 1;
+        self.last_duration = Some(duration);
+        self.breaker.record_success();
+    }
+
+    // A rate-limited failure pauses the endpoint for the provider's Retry-After
+    // (or `rate_limit_pause` seconds) instead of counting towards its breaker;
+    // `rate_limited` comes with the error of the failed request
+    pub fn record_failure(&mut self, rpc: &RpcConfig, timed_out: bool, rate_limited: Option<&RateLimited>) {
+        if timed_out {
+            self.timeouts += 1;
+        } else {
+            self.errors += 1;
+        }
+        if let Some(rate_limited) = rate_limited {
+            let pause = rate_limited.retry_after.unwrap_or_else(|| Duration::from_secs_f64(rpc.rate_limit_pause));
+            self.paused_until = Some(Instant::now() + pause);
+            warn!("RPC endpoint {} is rate limiting, pausing requests to it for {:?}", self.label, pause);
+            return;
+        }
+        let config = &rpc.circuit_breaker;
+        if self.breaker.record_failure(config) {
+            warn!("RPC endpoint {} is failing, pausing requests to it for {}s", self.label, config.cooldown);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    // Requests flow normally
+    Closed,
+    // Too many consecutive failures, no requests until the instant given
+    Open(Instant),
+    // Cooldown is over, the next request is a probe deciding between closed and open
+    HalfOpen,
+}
+
+impl BreakerState {
+    // Numeric value for the state gauge
+    pub fn value(&self) -> u8 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::Open(_) => 1,
+            BreakerState::HalfOpen => 2,
+        }
+    }
+}
+
+// Stops requests to an endpoint after repeated failures until a cooldown passes
+pub struct CircuitBreaker {
+    pub state: BreakerState,
+    consecutive_failures: u32,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+        }
+    }
+}
+
+impl CircuitBreaker {
+    fn wait_time(&self, now: Instant) -> Duration {
+        match self.state {
+            BreakerState::Open(until) => until.saturating_duration_since(now),
+            _ => Duration::ZERO,
+        }
+    }
+
+    // Let a probe through once the cooldown of an open breaker is over
+    fn refresh(&mut self, now: Instant) {
+        if let BreakerState::Open(until) = self.state {
+            if now >= until {
+                self.state = BreakerState::HalfOpen;
+            }
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.state = BreakerState::Closed;
+    }
+
+    // Returns true when this failure opened the breaker
+    fn record_failure(&mut self, config: &CircuitBreakerConfig) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        let trips = match self.state {
+            BreakerState::HalfOpen => true,
+            BreakerState::Closed => config.failure_threshold > 0 && self.consecutive_failures >= config.failure_threshold,
+            BreakerState::Open(_) => false,
+        };
+        if trips {
+            self.state = BreakerState::Open(Instant::now() + Duration::from_secs_f64(config.cooldown));
+        }
+        trips
+    }
+}
+
+// HTTP settings of an endpoint client; the client is rebuilt when they change
+#[derive(Debug, Clone, PartialEq)]
+struct Transport {
+    // Global and endpoint headers merged, as sent with every request
+    headers: BTreeMap<String, String>,
+    proxy: Option<String>,
+}
+
+fn build_client(url: &str, transport: &Transport, throttle: &Arc<Throttle>) -> RpcClient {
+    // Headers and proxy are checked when the configuration is validated
+    let mut builder = reqwest::Client::builder().default_headers(header_map(&transport.headers).unwrap_or_default());
+    if let Some(proxy) = &transport.proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy).expect("valid proxy url"));
+    }
+    let http_client = builder.build().expect("build rpc http client");
+    RpcClient::new_sender(HttpSender::new(url, http_client, Arc::clone(throttle)), RpcClientConfig::default())
+}
+
+// Name of an endpoint in metrics and logs: host plus non-default port only, so
+// API tokens carried in the URL path or query never end up in a time series
+pub fn endpoint_label(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) => host_label(&parsed).unwrap_or_else(|| url.to_string()),
+        Err(_) => url.to_string(),
+    }
+}
+
+// Host plus non-default port of a URL, without its credentials, path and query
+pub fn host_label(url: &Url) -> Option<String> {
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => Some(format!("{}:{}", host, port)),
+        (Some(host), None) => Some(host.to_string()),
+        _ => None,
+    }
+}
+
+// Slots each endpoint that answered the last slot check is behind the furthest
+// of them
+pub fn slot_lags(endpoints: &[Endpoint]) -> Vec<(&str, u64)> {
+    let Some(highest) = endpoints.iter().filter_map(|endpoint| endpoint.slot).max() else {
+        return Vec::new();
+    };
+    endpoints
+        .iter()
+        .filter_map(|endpoint| Some((endpoint.label.as_str(), highest - endpoint.slot?)))
+        .collect()
+}
+
+// The configured endpoints and the rotation state for round-robin mode
+#[derive(Default)]
+pub struct EndpointPool {
+    pub endpoints: Vec<Endpoint>,
+    cursor: usize,
+}
+
+impl EndpointPool {
+    // Rebuild the endpoint list when the configuration changes, keeping the
+    // accounting of endpoints that are still configured
+    pub fn sync(&mut self, rpc: &RpcConfig) {
+        let transports: Vec<Transport> = rpc
+            .endpoints
+            .iter()
+            .map(|config| {
+                let mut headers = rpc.headers.clone();
+                headers.extend(config.headers.clone());
+                Transport {
+                    headers,
+                    proxy: rpc.proxy.clone(),
+                }
+            })
+            .collect();
+        let unchanged = self.endpoints.len() == rpc.endpoints.len()
+            && self.endpoints.iter().zip(rpc.endpoints.iter().zip(&transports)).all(|(endpoint, (config, transport))| {
+                &endpoint.config == config && &endpoint.transport == transport
+            });
+        if unchanged {
+            return;
+        }
+
+        let mut previous = std::mem::take(&mut self.endpoints);
+        for (i, (config, transport)) in rpc.endpoints.iter().zip(transports).enumerate() {
+            let mut label = endpoint_label(&config.url);
+            // Several endpoints on one host still need distinct labels
+            if self.endpoints.iter().any(|endpoint| endpoint.label == label) {
+                label = format!("{}#{}", label, i + 1);
+            }
+            let endpoint = match previous.iter().position(|endpoint| endpoint.config.url == config.url) {
+                Some(pos) => {
+                    let mut endpoint = previous.swap_remove(pos);
+                    if endpoint.transport != transport {
+                        endpoint.client = build_client(&config.url, &transport, &endpoint.throttle);
+                        endpoint.transport = transport;
+                    }
+                    endpoint.config = config.clone();
+                    endpoint.label = label;
+                    endpoint
+                }
+                None => Endpoint::new(config, transport, label),
+            };
+            self.endpoints.push(endpoint);
+        }
+        self.cursor = 0;
+    }
+
+    // Indices of the endpoints to try for this poll, in order, skipping the ones
+    // whose rate limit does not allow a request yet
+    pub fn attempt_order(&mut self, mode: RpcMode) -> Vec<usize> {
+        let count = self.endpoints.len();
+        let start = match mode {
+            RpcMode::Failover => 0,
+            RpcMode::RoundRobin => {
+                let start = self.cursor % count.max(1);
+                self.cursor = self.cursor.wrapping_add(1);
+                start
+            }
+        };
+
+        let now = Instant::now();
+        for endpoint in &mut self.endpoints {
+            endpoint.breaker.refresh(now);
+        }
+        (0..count)
+            .map(|offset| (start + offset) % count)
+            .filter(|&i| self.endpoints[i].wait_time(now).is_zero())
+            .collect()
+    }
+
+    // Ask every endpoint due for it whether its node is healthy, at the same
+    // time; endpoints that may not take a request yet are asked on a later
+    // poll. Unlike a fetch this bypasses the circuit breaker, as a failing
+    // endpoint is what the check is for.
+    pub async fn check_health(&mut self, rpc: &RpcConfig) {
+        if rpc.health_interval == 0.0 {
+            for endpoint in &mut self.endpoints {
+                endpoint.healthy = None;
+                endpoint.health_checked = None;
+            }
+            return;
+        }
+        let now = Instant::now();
+        let due: Vec<usize> = (0..self.endpoints.len())
+            .filter(|&i| self.endpoints[i].check_due(now, self.endpoints[i].health_checked, rpc.health_interval))
+            .collect();
+        let limit = Duration::from_secs_f64(rpc.timeout);
+        let endpoints = &self.endpoints;
+        let results = join_all(due.iter().map(|&i| timeout(limit, endpoints[i].client.get_health()))).await;
+
+        for (i, result) in due.into_iter().zip(results) {
+            let endpoint = &mut self.endpoints[i];
+            endpoint.mark_used();
+            endpoint.health_checked = Some(Instant::now());
+            let healthy = match result {
+                Ok(Ok(())) => true,
+                Ok(Err(e)) => {
+                    if endpoint.healthy != Some(false) {
+                        warn!("RPC endpoint {} is unhealthy: {}", endpoint.label, e);
+                    }
+                    false
+                }
+                Err(_) => {
+                    if endpoint.healthy != Some(false) {
+                        warn!("Health check of RPC endpoint {} timed out", endpoint.label);
+                    }
+                    false
+                }
+            };
+            if healthy && endpoint.healthy == Some(false) {
+                info!("RPC endpoint {} is healthy again", endpoint.label);
+            }
+            endpoint.healthy = Some(healthy);
+        }
+    }
+
+    // Ask every endpoint due for it which slot its node is at, at the same time
+    // so the answers are comparable; like health checks past the circuit breaker
+    pub async fn check_slots(&mut self, rpc: &RpcConfig) {
+        if rpc.slot_interval == 0.0 {
+            for endpoint in &mut self.endpoints {
+                endpoint.slot = None;
+                endpoint.slot_checked = None;
+            }
+            return;
+        }
+        let now = Instant::now();
+        let due: Vec<usize> = (0..self.endpoints.len())
+            .filter(|&i| self.endpoints[i].check_due(now, self.endpoints[i].slot_checked, rpc.slot_interval))
+            .collect();
+        let limit = Duration::from_secs_f64(rpc.timeout);
+        let endpoints = &self.endpoints;
+        let results = join_all(
+            due.iter()
+                .map(|&i| timeout(limit, endpoints[i].client.get_slot_with_commitment(CommitmentConfig::processed()))),
+        )
+        .await;
+
+        for (i, result) in due.into_iter().zip(results) {
+            let endpoint = &mut self.endpoints[i];
+            endpoint.mark_used();
+            endpoint.slot_checked = Some(Instant::now());
+            endpoint.slot = match result {
+                Ok(Ok(slot)) => Some(slot),
+                Ok(Err(e)) => {
+                    debug!("Slot request to {} failed: {}", endpoint.label, e);
+                    None
+                }
+                Err(_) => {
+                    debug!("Slot request to {} timed out", endpoint.label);
+                    None
+                }
+            };
+        }
+    }
+
+    // Time until the first rate-limited, throttled or paused endpoint becomes available again
+    pub fn next_available_in(&self) -> Duration {
+        let now = Instant::now();
+        self.endpoints.iter().map(|endpoint| endpoint.wait_time(now)).min().unwrap_or(Duration::ZERO)
+    }
+}
+
+// Exponential backoff between polls while every endpoint is failing
+#[derive(Default)]
+pub struct Backoff {
+    failures: u32,
+    delay: Duration,
+}
+
+impl Backoff {
+    pub fn reset(&mut self) {
+        self.failures = 0;
+        self.delay = Duration::ZERO;
+    }
+
+    // Register another failed poll and pick the delay before the next one:
+    // initial * multiplier^(failures - 1), capped at max, with random jitter
+    pub fn fail(&mut self, config: &BackoffConfig) {
+        self.failures = self.failures.saturating_add(1);
+        let exponent = (self.failures - 1).min(i32::MAX as u32) as i32;
+        let base = (config.initial * config.multiplier.powi(exponent)).min(config.max);
+        let jitter = if config.jitter > 0.0 {
+            rand::thread_rng().gen_range(-config.jitter..=config.jitter)
+        } else {
+            0.0
+        };
+        self.delay = Duration::from_secs_f64((base * (1.0 + jitter)).clamp(0.0, config.max));
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn backoff_config(jitter: f64) -> BackoffConfig {
+        BackoffConfig { initial: 2.0, multiplier: 2.0, max: 10.0, jitter }
+    }
+
+    fn rpc_config(urls: &[&str]) -> RpcConfig {
+        RpcConfig { endpoints: urls.iter().map(|url| EndpointConfig::new(url)).collect(), ..RpcConfig::default() }
+    }
+
+    fn pool(count: usize) -> EndpointPool {
+        let urls: Vec<String> = (1..=count).map(|port| format!("http://127.0.0.1:{}", port)).collect();
+        let mut pool = EndpointPool::default();
+        pool.sync(&rpc_config(&urls.iter().map(String::as_str).collect::<Vec<_>>()));
+        pool
+    }
+
+    // A node answering every JSON-RPC request with `"result": "ok"`
+    async fn healthy_node() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request).await;
+                let body = r#"{"jsonrpc":"2.0","result":"ok","id":0}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn backoff_grows_up_to_the_cap() {
+        let mut backoff = Backoff::default();
+        let delays: Vec<f64> = (0..5)
+            .map(|_| {
+                backoff.fail(&backoff_config(0.0));
+                backoff.delay().as_secs_f64()
+            })
+            .collect();
+        assert_eq!(delays, vec![2.0, 4.0, 8.0, 10.0, 10.0]);
+        assert_eq!(backoff.failures(), 5);
+    }
+
+    #[test]
+    fn backoff_jitter_stays_under_the_cap() {
+        let mut backoff = Backoff::default();
+        backoff.fail(&backoff_config(0.5));
+        assert!((1.0..=3.0).contains(&backoff.delay().as_secs_f64()));
+        for _ in 0..10 {
+            backoff.fail(&backoff_config(0.5));
+            assert!(backoff.delay().as_secs_f64() <= 10.0);
+        }
+    }
+
+    #[test]
+    fn backoff_reset_starts_over() {
+        let mut backoff = Backoff::default();
+        for _ in 0..3 {
+            backoff.fail(&backoff_config(0.0));
+        }
+        backoff.reset();
+        assert_eq!((backoff.failures(), backoff.delay()), (0, Duration::ZERO));
+        backoff.fail(&backoff_config(0.0));
+        assert_eq!(backoff.delay(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn breaker_opens_at_the_threshold_and_probes_after_the_cooldown() {
+        let config = CircuitBreakerConfig { failure_threshold: 3, cooldown: 30.0 };
+        let mut breaker = CircuitBreaker::default();
+        assert!(!breaker.record_failure(&config));
+        assert!(!breaker.record_failure(&config));
+        assert!(breaker.record_failure(&config));
+        let BreakerState::Open(until) = breaker.state else {
+            panic!("breaker not open: {:?}", breaker.state);
+        };
+
+        breaker.refresh(until - Duration::from_secs(1));
+        assert_eq!(breaker.state, BreakerState::Open(until));
+        breaker.refresh(until);
+        assert_eq!(breaker.state, BreakerState::HalfOpen);
+
+        // A failed probe opens it again right away, a good one closes it
+        assert!(breaker.record_failure(&config));
+        assert!(matches!(breaker.state, BreakerState::Open(_)));
+        breaker.refresh(Instant::now() + Duration::from_secs(31));
+        breaker.record_success();
+        assert_eq!(breaker.state, BreakerState::Closed);
+        assert!(!breaker.record_failure(&config));
+    }
+
+    #[test]
+    fn breaker_threshold_zero_never_opens() {
+        let config = CircuitBreakerConfig { failure_threshold: 0, cooldown: 30.0 };
+        let mut breaker = CircuitBreaker::default();
+        for _ in 0..10 {
+            assert!(!breaker.record_failure(&config));
+        }
+        assert_eq!(breaker.state, BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn attempt_order_rotates_in_round_robin_mode() {
+        let mut pool = pool(3);
+        assert_eq!(pool.attempt_order(RpcMode::Failover), vec![0, 1, 2]);
+        assert_eq!(pool.attempt_order(RpcMode::RoundRobin), vec![0, 1, 2]);
+        assert_eq!(pool.attempt_order(RpcMode::RoundRobin), vec![1, 2, 0]);
+        assert_eq!(pool.attempt_order(RpcMode::RoundRobin), vec![2, 0, 1]);
+        assert_eq!(pool.attempt_order(RpcMode::RoundRobin), vec![0, 1, 2]);
+        assert_eq!(EndpointPool::default().attempt_order(RpcMode::RoundRobin), Vec::<usize>::new());
+    }
+
+    #[tokio::test]
+    async fn attempt_order_skips_open_breakers_and_paused_endpoints() {
+        let mut pool = pool(3);
+        let now = Instant::now();
+        pool.endpoints[0].breaker.state = BreakerState::Open(now + Duration::from_secs(60));
+        pool.endpoints[2].paused_until = Some(now + Duration::from_secs(60));
+        assert_eq!(pool.attempt_order(RpcMode::Failover), vec![1]);
+        assert_eq!(pool.attempt_order(RpcMode::RoundRobin), vec![1]);
+
+        // Once the cooldown is over the endpoint is tried again, as a probe
+        pool.endpoints[0].breaker.state = BreakerState::Open(now);
+        assert_eq!(pool.attempt_order(RpcMode::Failover), vec![0, 1]);
+        assert_eq!(pool.endpoints[0].breaker.state, BreakerState::HalfOpen);
+    }
+
+    #[tokio::test]
+    async fn check_health_asks_every_due_endpoint() {
+        let healthy = healthy_node().await;
+        let mut rpc = rpc_config(&[&healthy, "http://127.0.0.1:1"]);
+        rpc.health_interval = 60.0;
+        rpc.timeout = 2.0;
+        let mut pool = EndpointPool::default();
+        pool.sync(&rpc);
+        // An open breaker does not stop the check
+        pool.endpoints[0].breaker.state = BreakerState::Open(Instant::now() + Duration::from_secs(60));
+
+        pool.check_health(&rpc).await;
+        assert_eq!(pool.endpoints[0].healthy, Some(true));
+        assert_eq!(pool.endpoints[1].healthy, Some(false));
+
+        // Not due again before the interval, and off with an interval of 0
+        pool.endpoints[1].healthy = None;
+        pool.check_health(&rpc).await;
+        assert_eq!(pool.endpoints[1].healthy, None);
+        rpc.health_interval = 0.0;
+        pool.check_health(&rpc).await;
+        assert_eq!(pool.endpoints[0].healthy, None);
+    }
+}
@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_request::{RpcError, RpcRequest, RpcResponseErrorData};
+use std::collections::BTreeMap;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+// Convert configured headers, failing on names or values HTTP does not allow
+pub fn header_map(headers: &BTreeMap<String, String>) -> Result<reqwest::header::HeaderMap, String> {
+    let mut map = reqwest::header::HeaderMap::new();
+    for (name, value) in headers {
+        let header_name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("{:?} is not a valid header name", name))?;
+        let mut header_value = reqwest::header::HeaderValue::from_str(value)
+            .map_err(|_| format!("value of header {:?} is not a valid header value", name))?;
+        // Keep credentials out of debug output
+        header_value.set_sensitive(true);
+        map.insert(header_name, header_value);
+    }
+    Ok(map)
+}
+
+// Rate-limit responses seen by a sender, shared with its endpoint for the
+// rate-limited requests counter
+#[derive(Default)]
+pub struct Throttle {
+    hits: AtomicU64,
+}
+
+impl Throttle {
+    // Rate-limited responses seen so far
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+}
+
+// The error of a rate-limited request, with the provider's Retry-After if it
+// sent one; it travels with the request's error, so only the failure it caused
+// pauses the endpoint
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    pub retry_after: Option<Duration>,
+    message: String,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rate limited: {}", self.message)
+    }
+}
+
+impl Error for RateLimited {}
+
+impl RateLimited {
+    // The client error of the request, counted on `throttle`
+    fn error(throttle: &Throttle, retry_after: Option<Duration>, message: String) -> ClientError {
+        throttle.hits.fetch_add(1, Ordering::Relaxed);
+        io::Error::other(Self { retry_after, message }).into()
+    }
+
+    // The rate limit behind an error of a request through `HttpSender`, if any;
+    // the client flattens errors of its node version query into a message, which
+    // keeps the text but not the Retry-After
+    pub fn of(error: &(dyn Error + 'static)) -> Option<Self> {
+        match &error.downcast_ref::<ClientError>()?.kind {
+            ClientErrorKind::Io(error) => error.get_ref()?.downcast_ref::<Self>().cloned(),
+            ClientErrorKind::RpcError(RpcError::RpcRequestError(message)) => {
+                let (_, message) = message.split_once("rate limited: ")?;
+                Some(Self { retry_after: None, message: message.to_string() })
+            }
+            _ => None,
+        }
+    }
+}
+
+// Delay a rate-limited response asks for. Only the delay-seconds form is
+// understood; an HTTP-date falls back to the configured default pause.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?;
+    value.to_str().ok()?.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+// JSON-RPC transport over HTTP for the endpoint clients, sending the
+// configured extra headers (API keys, bearer tokens) with every request
+pub struct HttpSender {
+    client: reqwest::Client,
+    url: String,
+    request_id: AtomicU64,
+    stats: RwLock<RpcTransportStats>,
+    throttle: Arc<Throttle>,
+}
+
+impl HttpSender {
+    pub fn new(url: &str, client: reqwest::Client, throttle: Arc<Throttle>) -> Self {
+        Self {
+            client,
+            url: url.to_string(),
+            request_id: AtomicU64::new(0),
+            stats: RwLock::new(RpcTransportStats::default()),
+            throttle,
+        }
+    }
+}
+
+#[async_trait]
+impl RpcSender for HttpSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let start = Instant::now();
+        let request_id = self.request_id.fetch_add(1, Ordering::Relaxed);
+        let body = request.build_request_json(request_id, params).to_string();
+
+        let result = async {
+            // Errors leave out the URL, it may carry an API token
+            let response = self
+                .client
+                .post(&self.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| e.without_url())?;
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                return Err(RateLimited::error(&self.throttle, retry_after(&response), "HTTP 429 Too Many Requests".to_string()));
+            }
+            let response = response.error_for_status().map_err(|e| e.without_url())?;
+
+            let mut json = response.json::<Value>().await.map_err(|e| e.without_url())?;
+            if json["error"].is_object() {
+                // A few providers signal quota pressure with a JSON-RPC error code of 429 instead of HTTP 429
+                if json["error"]["code"].as_i64() == Some(429) {
+                    let message = json["error"]["message"].as_str().unwrap_or_default().to_string();
+                    return Err(RateLimited::error(&self.throttle, None, message));
+                }
+                return Err(RpcError::RpcResponseError {
+                    code: json["error"]["code"].as_i64().unwrap_or_default(),
+                    message: json["error"]["message"].as_str().unwrap_or_default().to_string(),
+                    data: RpcResponseErrorData::Empty,
+                }
+                .into());
+            }
+            Ok(json["result"].take())
+        }
+        .await;
+
+        let mut stats = self.stats.write().unwrap();
+        stats.request_count += 1;
+        stats.elapsed_time += start.elapsed();
+        result
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.stats.read().unwrap().clone()
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
@@ -0,0 +1,84 @@
+// Wiz scores and ranks of the watched validators from the Stakewiz API, one
+// request per validator, so credits can be charted next to a third-party
+// ranking. Refreshed every `stakewiz.refresh_interval`; Stakewiz only rates
+// mainnet validators.
+
+use crate::config::StakewizConfig;
+use crate::watchlist::WatchedValidator;
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Until a failed request is tried again
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Score {
+    pub rank: Option<u64>,
+    pub wiz_score: Option<f64>,
+}
+
+struct Entry {
+    // None for a validator Stakewiz does not list
+    score: Option<Score>,
+    due: Instant,
+}
+
+// The latest scores, by vote pubkey
+#[derive(Default)]
+pub struct Scores {
+    entries: HashMap<String, Entry>,
+}
+
+impl Scores {
+    pub fn get(&self, vote_pubkey: &str) -> Option<&Score> {
+        self.entries.get(vote_pubkey)?.score.as_ref()
+    }
+
+    // Fetch the scores that are due; a failure keeps the previous score
+    pub async fn refresh(&mut self, config: &StakewizConfig, watched: &[WatchedValidator]) {
+        self.entries.retain(|pubkey, _| watched.iter().any(|validator| &validator.vote_pubkey == pubkey));
+        let client = reqwest::Client::new();
+        for validator in watched {
+            let now = Instant::now();
+            if self.entries.get(&validator.vote_pubkey).is_some_and(|entry| now < entry.due) {
+                continue;
+            }
+            match fetch(&client, config, &validator.vote_pubkey).await {
+                Ok(score) => {
+                    debug!("Fetched the Stakewiz score of {}: {:?}", validator.vote_pubkey, score);
+                    let due = now + Duration::from_secs_f64(config.refresh_interval);
+                    self.entries.insert(validator.vote_pubkey.clone(), Entry { score, due });
+                }
+                Err(e) => {
+                    warn!("Stakewiz request for {} failed: {}", validator.vote_pubkey, e);
+                    let previous = self.entries.remove(&validator.vote_pubkey).and_then(|entry| entry.score);
+                    self.entries.insert(validator.vote_pubkey.clone(), Entry { score: previous, due: now + RETRY_INTERVAL });
+                }
+            }
+        }
+    }
+}
+
+async fn fetch(client: &reqwest::Client, config: &StakewizConfig, vote_pubkey: &str) -> Result<Option<Score>, String> {
+    let url = format!("{}/validator/{}", config.url.trim_end_matches('/'), vote_pubkey);
+    let response = client
+        .get(&url)
+        .timeout(Duration::from_secs_f64(config.timeout))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status));
+    }
+    // Validators it does not list come back as `false`
+    let body: Value = response.json().await.map_err(|e| format!("invalid response: {}", e))?;
+    if !body.is_object() {
+        return Ok(None);
+    }
+    Score::deserialize(body).map(Some).map_err(|e| format!("invalid response: {}", e))
+}
@@ -0,0 +1,127 @@
+// Statistics over the credits and stake of the validators, computed once per poll
+
+// The value below which a share `q` (0 to 1) of `sorted` lies, interpolating
+// linearly between neighbours; `sorted` must be in ascending order. None when
+// it is empty or `q` is out of range.
+pub fn quantile(sorted: &[u64], q: f64) -> Option<f64> {
+    if sorted.is_empty() || !(0.0..=1.0).contains(&q) {
+        return None;
+    }
+    let position = q * (sorted.len() - 1) as f64;
+    let (below, above) = (position.floor() as usize, position.ceil() as usize);
+    let fraction = position - below as f64;
+    Some(sorted[below] as f64 + (sorted[above] as f64 - sorted[below] as f64) * fraction)
+}
+
+// None without values
+pub fn mean(values: &[u64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().map(|&value| value as f64).sum::<f64>() / values.len() as f64)
+}
+
+// Population standard deviation: the active validators are the whole set, not a sample
+pub fn stddev(values: &[u64]) -> Option<f64> {
+    let mean = mean(values)?;
+    let variance = values.iter().map(|&value| (value as f64 - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    Some(variance.sqrt())
+}
+
+// Mean of `sorted` without the lowest and highest share `trim` (0 to below 0.5)
+// of the values, so a few validators with nearly no credits do not drag it down;
+// None when the trim leaves no values
+pub fn trimmed_mean(sorted: &[u64], trim: f64) -> Option<f64> {
+    if !(0.0..0.5).contains(&trim) {
+        return None;
+    }
+    let cut = (sorted.len() as f64 * trim) as usize;
+    mean(sorted.get(cut..sorted.len().checked_sub(cut)?)?)
+}
+
+// Mean of the values weighted by their weights, as (value, weight) pairs; None
+// when the weights are all 0
+pub fn weighted_mean(pairs: &[(u64, u64)]) -> Option<f64> {
+    let total_weight: f64 = pairs.iter().map(|&(_, weight)| weight as f64).sum();
+    if total_weight == 0.0 {
+        return None;
+    }
+    Some(pairs.iter().map(|&(value, weight)| value as f64 * weight as f64).sum::<f64>() / total_weight)
+}
+
+// Herfindahl-Hirschman index of `values`: the sum of the squared shares of
+// their total, from 1/N when all are equal to 1 when one holds everything; 0
+// for no total
+pub fn hhi(values: &[u64]) -> f64 {
+    let total: f64 = values.iter().map(|&value| value as f64).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    values.iter().map(|&value| (value as f64 / total).powi(2)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantile_interpolates_between_neighbours() {
+        let sorted = [10, 20, 30, 40, 50];
+        assert_eq!(quantile(&sorted, 0.0), Some(10.0));
+        assert_eq!(quantile(&sorted, 0.5), Some(30.0));
+        assert_eq!(quantile(&sorted, 0.625), Some(35.0));
+        assert_eq!(quantile(&sorted, 1.0), Some(50.0));
+        assert_eq!(quantile(&[7], 0.75), Some(7.0));
+    }
+
+    #[test]
+    fn quantile_of_nothing_or_out_of_range_is_none() {
+        assert_eq!(quantile(&[], 0.5), None);
+        assert_eq!(quantile(&[1, 2], -0.1), None);
+        assert_eq!(quantile(&[1, 2], 1.5), None);
+        assert_eq!(quantile(&[1, 2], f64::NAN), None);
+    }
+
+    #[test]
+    fn mean_and_stddev() {
+        assert_eq!(mean(&[2, 4, 4, 4, 5, 5, 7, 9]), Some(5.0));
+        assert_eq!(stddev(&[2, 4, 4, 4, 5, 5, 7, 9]), Some(2.0));
+        assert_eq!(stddev(&[3]), Some(0.0));
+        assert_eq!(mean(&[]), None);
+        assert_eq!(stddev(&[]), None);
+    }
+
+    #[test]
+    fn trimmed_mean_drops_both_ends() {
+        let sorted = [0, 10, 10, 10, 10, 10, 10, 10, 10, 1000];
+        assert_eq!(trimmed_mean(&sorted, 0.1), Some(10.0));
+        assert_eq!(trimmed_mean(&sorted, 0.0), mean(&sorted));
+        // Too few values for any to be cut
+        assert_eq!(trimmed_mean(&[1, 3], 0.4), Some(2.0));
+    }
+
+    #[test]
+    fn trimmed_mean_without_values_left_is_none() {
+        assert_eq!(trimmed_mean(&[], 0.1), None);
+        assert_eq!(trimmed_mean(&[1, 2, 3, 4], 0.5), None);
+        assert_eq!(trimmed_mean(&[1, 2, 3, 4], 0.9), None);
+        assert_eq!(trimmed_mean(&[1, 2, 3, 4], -0.1), None);
+    }
+
+    #[test]
+    fn weighted_mean_follows_the_weights() {
+        assert_eq!(weighted_mean(&[(10, 1), (40, 3)]), Some(32.5));
+        assert_eq!(weighted_mean(&[(10, 0), (40, 5)]), Some(40.0));
+        assert_eq!(weighted_mean(&[(10, 0), (40, 0)]), None);
+        assert_eq!(weighted_mean(&[]), None);
+    }
+
+    #[test]
+    fn hhi_ranges_from_even_to_concentrated() {
+        assert_eq!(hhi(&[5, 5, 5, 5]), 0.25);
+        assert_eq!(hhi(&[0, 100, 0]), 1.0);
+        assert_eq!(hhi(&[1, 3]), 0.625);
+        assert_eq!(hhi(&[0, 0]), 0.0);
+        assert_eq!(hhi(&[]), 0.0);
+    }
+}
@@ -0,0 +1,102 @@
+// The `/status` page: the cached validators of every cluster as HTML tables,
+// sorted on the server by following the column header links, no JavaScript
+
+use crate::landing::escape_html;
+use crate::{MetricsCache, ValidatorMetrics};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Rank,
+    Pubkey,
+    Credits,
+    RootDistance,
+    VoteDistance,
+}
+
+const COLUMNS: [(Column, &str, &str); 5] = [
+    (Column::Rank, "rank", "Rank"),
+    (Column::Pubkey, "pubkey", "Vote pubkey"),
+    (Column::Credits, "credits", "Credits"),
+    (Column::RootDistance, "root_distance", "Root distance"),
+    (Column::VoteDistance, "vote_distance", "Vote distance"),
+];
+
+// `query` may hold `sort=<column>`, `order=asc|desc` and `cluster=<name>`
+pub fn page(cache: &BTreeMap<String, MetricsCache>, query: Option<&str>, stale_after: f64) -> String {
+    let param = |name: &str| {
+        query.and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('=')))
+    };
+    let sort = COLUMNS
+        .iter()
+        .find(|(_, key, _)| Some(*key) == param("sort"))
+        .map(|(column, _, _)| *column)
+        .unwrap_or(Column::Rank);
+    let descending = param("order") == Some("desc");
+    let cluster = param("cluster");
+
+    let mut body = String::new();
+    for (name, cluster_cache) in cache.iter().filter(|(name, _)| cluster.is_none_or(|cluster| cluster == name.as_str())) {
+        let freshness = match cluster_cache.age() {
+            Some(age) if cluster_cache.is_stale(stale_after) => format!("stale, last updated {:.0}s ago", age),
+            Some(age) => format!("updated {:.0}s ago", age),
+            None => "no data yet".to_string(),
+        };
+        let epoch = match &cluster_cache.epoch_info {
+            Some(info) => format!(", epoch {}", info.epoch),
+            None => String::new(),
+        };
+        let _ = writeln!(body, "<h2>{}</h2>\n<p>{}{}, {} validators</p>", escape_html(name), freshness, epoch, cluster_cache.validators.len());
+
+        // Each header sorts by its column, a second click reverses the order
+        let cluster_param = cluster.map(|cluster| format!("&amp;cluster={}", escape_html(cluster))).unwrap_or_default();
+        body.push_str("<table>\n<tr>");
+        for (column, key, title) in COLUMNS {
+            let order = if column == sort && !descending { "desc" } else { "asc" };
+            let arrow = match (column == sort, descending) {
+                (true, false) => " &#9650;",
+                (true, true) => " &#9660;",
+                (false, _) => "",
+            };
+            let _ = write!(body, "<th><a href=\"?sort={}&amp;order={}{}\">{}</a>{}</th>", key, order, cluster_param, title, arrow);
+        }
+        body.push_str("</tr>\n");
+
+        let mut validators: Vec<&ValidatorMetrics> = cluster_cache.validators.iter().collect();
+        validators.sort_by(|a, b| {
+            let ordering = match sort {
+                Column::Rank => a.rank.cmp(&b.rank),
+                Column::Pubkey => a.vote_pubkey.cmp(&b.vote_pubkey),
+                Column::Credits => a.credits_earned.cmp(&b.credits_earned),
+                Column::RootDistance => a.root_distance.cmp(&b.root_distance),
+                Column::VoteDistance => a.vote_distance.cmp(&b.vote_distance),
+            };
+            if descending { ordering.reverse() } else { ordering }
+        });
+        for validator in validators {
+            let _ = writeln!(
+                body,
+                "<tr><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                validator.rank,
+                escape_html(&validator.vote_pubkey),
+                validator.credits_earned,
+                validator.root_distance,
+                validator.vote_distance
+            );
+        }
+        body.push_str("</table>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head><meta charset=\"utf-8\"><title>Validator status</title>
+<style>table {{ border-collapse: collapse; }} td, th {{ padding: 2px 8px; text-align: right; }}</style></head>
+<body>
+<h1>Validator status</h1>
+{body}</body>
+</html>
+"
+    )
+}
@@ -0,0 +1,80 @@
+// The `/stream` endpoint: server-sent events carrying the JSON snapshot of a
+// cluster (the `/api/v1/validators` shape) every time its fetch loop updates
+// the cache, so live dashboards do not have to poll
+
+use crate::config::Config;
+use crate::{api, SharedCache};
+use hyper::body::{Bytes, Sender};
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE};
+use hyper::{Body, Response};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+use tokio::task;
+use tokio::time::interval;
+
+// Comment lines sent while nothing changes, so proxies keep the stream open
+// and clients that went away are noticed
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// Start streaming to a new client, beginning with the current data of every
+// cluster (or of `cluster` only); `updates` names each cluster as it is updated
+pub fn response(
+    cache: SharedCache,
+    config: watch::Receiver<Arc<Config>>,
+    mut updates: broadcast::Receiver<String>,
+    cluster: Option<String>,
+) -> Response<Body> {
+    let (mut sender, body) = Body::channel();
+    task::spawn(async move {
+        let initial: Vec<String> = cache
+            .lock()
+            .await
+            .keys()
+            .filter(|name| cluster.as_ref().is_none_or(|cluster| cluster == *name))
+            .cloned()
+            .collect();
+        for name in initial {
+            if !send_snapshot(&mut sender, &cache, &config, &name).await {
+                return;
+            }
+        }
+
+        let mut keep_alive = interval(KEEP_ALIVE_INTERVAL);
+        keep_alive.tick().await;
+        loop {
+            let sent = tokio::select! {
+                update = updates.recv() => match update {
+                    Ok(name) if cluster.as_ref().is_none_or(|cluster| *cluster == name) => {
+                        send_snapshot(&mut sender, &cache, &config, &name).await
+                    }
+                    Ok(_) => true,
+                    // A slow client misses snapshots, the next one is complete anyway
+                    Err(RecvError::Lagged(_)) => true,
+                    Err(RecvError::Closed) => return,
+                },
+                _ = keep_alive.tick() => sender.send_data(Bytes::from_static(b": keep-alive\n\n")).await.is_ok(),
+            };
+            if !sent {
+                return;
+            }
+        }
+    });
+    Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(body)
+        .unwrap()
+}
+
+// Whether the client is still there
+async fn send_snapshot(sender: &mut Sender, cache: &SharedCache, config: &watch::Receiver<Arc<Config>>, cluster: &str) -> bool {
+    let stale_after = config.borrow().metrics.stale_after;
+    let json = api::validators(&*cache.lock().await, Some(cluster), stale_after);
+    let Some(json) = json else {
+        // Removed from the configuration meanwhile
+        return true;
+    };
+    sender.send_data(Bytes::from(format!("event: snapshot\ndata: {}\n\n", json))).await.is_ok()
+}
@@ -0,0 +1,75 @@
+// The two bits of the systemd protocol the exporter speaks, without libsystemd:
+// sockets passed in by socket activation, and sd_notify status messages.
+
+use log::warn;
+use std::env;
+use std::net::TcpListener;
+use std::os::fd::FromRawFd;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::process;
+use std::time::Duration;
+use tokio::time::interval;
+
+// First file descriptor passed by socket activation
+const LISTEN_FDS_START: i32 = 3;
+
+// Listening sockets handed over by systemd (`LISTEN_FDS`), if the exporter was
+// socket activated; they replace `server.listen`
+pub fn listeners() -> Option<Vec<TcpListener>> {
+    let pid: u32 = env::var("LISTEN_PID").ok()?.parse().ok()?;
+    if pid != process::id() {
+        return None;
+    }
+    let count: i32 = env::var("LISTEN_FDS").ok()?.parse().ok()?;
+    // The variables are left set: changing the environment while the runtime's
+    // threads read it is undefined behaviour, and child processes ignore them
+    // anyway as `LISTEN_PID` is not theirs. Only called once, at startup.
+    // Safety: systemd passes `count` open sockets starting at fd 3, owned by this process from now on
+    let listeners = (LISTEN_FDS_START..LISTEN_FDS_START + count)
+        .map(|fd| unsafe { TcpListener::from_raw_fd(fd) })
+        .collect();
+    Some(listeners)
+}
+
+// Send a status line such as "READY=1" to systemd; does nothing when not
+// started by a unit with `Type=notify` or `WatchdogSec=`
+pub fn notify(state: &str) {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let path = path.to_string_lossy().into_owned();
+    // A leading '@' names a socket in the abstract namespace
+    let addr = match path.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+        None => SocketAddr::from_pathname(&path),
+    };
+    let sent = addr.and_then(|addr| UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &addr));
+    if let Err(e) = sent {
+        warn!("Failed to notify systemd ({}): {}", state, e);
+    }
+}
+
+// Interval of the systemd watchdog (`WatchdogSec=`) if it is enabled for this process
+fn watchdog_timeout() -> Option<Duration> {
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Some(pid) = env::var("WATCHDOG_PID").ok().and_then(|pid| pid.parse::<u32>().ok()) {
+        if pid != process::id() {
+            return None;
+        }
+    }
+    Some(Duration::from_micros(usec))
+}
+
+// Ping the systemd watchdog at half its timeout. The pings come from the async
+// runtime, so a wedged runtime stops them and systemd restarts the exporter.
+pub async fn watchdog_pings() {
+    let Some(timeout) = watchdog_timeout() else {
+        return;
+    };
+    let mut ping = interval(timeout / 2);
+    loop {
+        ping.tick().await;
+        notify("WATCHDOG=1");
+    }
+}
@@ -0,0 +1,148 @@
+use crate::config::{Config, TlsConfig};
+use log::{error, info};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+use tokio::time::interval;
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+// How often the certificate, key and client CA files are checked for changes
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// Build the rustls settings for the HTTPS listener from PEM files
+pub fn load(tls: &TlsConfig) -> Result<ServerConfig, String> {
+    let certs = read_certs(&tls.cert)?;
+    let key = read_key(&tls.key)?;
+    let builder = ServerConfig::builder().with_safe_defaults();
+    // With a client CA, only clients presenting a certificate it signed get through the handshake
+    let builder = match &tls.client_ca {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(path)? {
+                roots
+                    .add(&cert)
+                    .map_err(|e| format!("cannot use client CA certificate from {}: {}", path.display(), e))?;
+            }
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("certificate {} does not match key {}: {}", tls.cert.display(), tls.key.display(), e))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn read_certs(path: &PathBuf) -> Result<Vec<Certificate>, String> {
+    let file = File::open(path).map_err(|e| format!("cannot read certificate {}: {}", path.display(), e))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .map_err(|e| format!("cannot parse certificate {}: {}", path.display(), e))?;
+    if certs.is_empty() {
+        return Err(format!("no PEM certificate found in {}", path.display()));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn read_key(path: &PathBuf) -> Result<PrivateKey, String> {
+    let file = File::open(path).map_err(|e| format!("cannot read private key {}: {}", path.display(), e))?;
+    let items = rustls_pemfile::read_all(&mut BufReader::new(file))
+        .map_err(|e| format!("cannot parse private key {}: {}", path.display(), e))?;
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| format!("no PEM private key found in {}", path.display()))
+}
+
+// What the current settings were loaded from: the TLS section and the file
+// modification times, so a renewed certificate or CA bundle is picked up
+type Source = (TlsConfig, Vec<Option<SystemTime>>);
+
+fn source(tls: &TlsConfig) -> Source {
+    let modified = |path: &PathBuf| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let files = [Some(&tls.cert), Some(&tls.key), tls.client_ca.as_ref()];
+    (tls.clone(), files.into_iter().flatten().map(modified).collect())
+}
+
+// The same settings for the gRPC listener, which speaks HTTP/2 only
+fn for_grpc(config: &ServerConfig) -> ServerConfig {
+    let mut config = config.clone();
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    config
+}
+
+// TLS settings of the HTTPS and gRPC listeners, swapped when the certificate
+// files or their paths change; connections in progress keep the settings they
+// began with
+pub struct ReloadingTls {
+    current: RwLock<Arc<ServerConfig>>,
+    grpc: RwLock<Arc<ServerConfig>>,
+    loaded_from: Mutex<Source>,
+}
+
+impl ReloadingTls {
+    pub fn new(tls: &TlsConfig) -> Result<Self, String> {
+        let loaded_from = source(tls);
+        let config = load(tls)?;
+        Ok(Self {
+            grpc: RwLock::new(Arc::new(for_grpc(&config))),
+            current: RwLock::new(Arc::new(config)),
+            loaded_from: Mutex::new(loaded_from),
+        })
+    }
+
+    pub fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(Arc::clone(&self.current.read().unwrap()))
+    }
+
+    pub fn grpc_acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(Arc::clone(&self.grpc.read().unwrap()))
+    }
+
+    // Reload when anything changed since the last load; a failed load keeps the
+    // previous certificate in use
+    fn reload_if_changed(&self, tls: &TlsConfig) {
+        let latest = source(tls);
+        let mut loaded_from = self.loaded_from.lock().unwrap();
+        if *loaded_from == latest {
+            return;
+        }
+        match load(tls) {
+            Ok(config) => {
+                *self.grpc.write().unwrap() = Arc::new(for_grpc(&config));
+                *self.current.write().unwrap() = Arc::new(config);
+                info!("TLS certificate reloaded from {}", tls.cert.display());
+            }
+            Err(e) => error!("TLS certificate reload failed, keeping the previous one: {}", e),
+        }
+        // Remember the attempt either way, so a broken file is reported once
+        *loaded_from = latest;
+    }
+
+    // Check the configured files periodically and after every config reload
+    pub async fn watch(self: Arc<Self>, mut config_rx: watch::Receiver<Arc<Config>>) {
+        let mut check = interval(RELOAD_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = check.tick() => {}
+                changed = config_rx.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                }
+            }
+            let tls = config_rx.borrow_and_update().server.tls.clone();
+            if let Some(tls) = tls {
+                self.reload_if_changed(&tls);
+            }
+        }
+    }
+}
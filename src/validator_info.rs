@@ -0,0 +1,69 @@
+// The names validators publish on chain with `solana validator-info publish`:
+// accounts of the config program, found with one `getProgramAccounts` request
+// and decoded by the RPC node (`jsonParsed`). Fetched again every
+// `validator_info.refresh_interval`, names rarely change.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use std::collections::HashMap;
+
+const CONFIG_PROGRAM_ID: &str = "Config1111111111111111111111111111111111111";
+// First key of every validator info account, right after the key count
+const VALIDATOR_INFO_ID: &str = "Va1idator1nfo111111111111111111111111111111";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ValidatorInfo {
+    pub name: String,
+    pub website: String,
+    pub details: String,
+    #[serde(rename = "keybaseUsername")]
+    pub keybase_username: String,
+}
+
+// The published info by node identity pubkey
+pub type Directory = HashMap<String, ValidatorInfo>;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParsedInfo {
+    keys: Vec<ConfigKey>,
+    config_data: ValidatorInfo,
+}
+
+#[derive(Deserialize)]
+struct ConfigKey {
+    pubkey: String,
+    signer: bool,
+}
+
+pub async fn fetch(client: &RpcClient) -> Result<Directory, String> {
+    let params = json!([
+        CONFIG_PROGRAM_ID,
+        {
+            "encoding": "jsonParsed",
+            "filters": [{ "memcmp": { "offset": 1, "bytes": VALIDATOR_INFO_ID } }],
+        }
+    ]);
+    let accounts: Vec<Value> = client.send(RpcRequest::GetProgramAccounts, params).await.map_err(|e| e.to_string())?;
+
+    let mut directory = Directory::new();
+    for account in &accounts {
+        let Some(parsed) = account.pointer("/account/data/parsed") else {
+            continue;
+        };
+        if parsed["type"] != "validatorInfo" {
+            continue;
+        }
+        let Ok(info) = ParsedInfo::deserialize(&parsed["info"]) else {
+            continue;
+        };
+        // The validator signs its info with its identity key
+        if let Some(identity) = info.keys.iter().find(|key| key.signer) {
+            directory.insert(identity.pubkey.clone(), info.config_data);
+        }
+    }
+    Ok(directory)
+}
@@ -0,0 +1,140 @@
+// Names, data centers and scores from the validators.app API, for operators
+// with an API key. Its validator list is large and the API rate-limited, so
+// it is fetched every `validators_app.refresh_interval` and kept in
+// `validators_app.cache_dir`, which also spares the request after a restart.
+
+use crate::config::ValidatorsAppConfig;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Until a failed request is tried again
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+// One validator as the API lists it; the fields used here, all optional there
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppValidator {
+    pub vote_account: Option<String>,
+    pub name: Option<String>,
+    pub data_center_key: Option<String>,
+    pub total_score: Option<i64>,
+    // Validator client as validators.app detected it, e.g. "Agave" or "JitoLabs"
+    pub software_client: Option<String>,
+    // Whether it runs the Jito client
+    pub jito: Option<bool>,
+}
+
+// Layout of the files in `cache_dir`
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    // Unix time of the request
+    fetched_at: f64,
+    validators: Vec<AppValidator>,
+}
+
+// The listing of one network, by vote pubkey
+#[derive(Default)]
+pub struct Listing {
+    pub validators: HashMap<String, AppValidator>,
+    fetched_at: Option<SystemTime>,
+    // Set by a failed request, none until then
+    retry_at: Option<Instant>,
+}
+
+impl Listing {
+    // Fetch the listing of `network` when it is older than the refresh
+    // interval, after trying `cache_dir` when nothing was loaded yet. Failures
+    // are logged and keep the listing from before.
+    pub async fn refresh(&mut self, config: &ValidatorsAppConfig, network: &str) {
+        let path = config.cache_dir.as_ref().map(|dir| cache_path(dir, network));
+        if self.fetched_at.is_none() {
+            if let Some(path) = &path {
+                match load(path) {
+                    Ok(Some(file)) => {
+                        debug!("Loaded {} validators.app entries from {}", file.validators.len(), path.display());
+                        self.set(file.validators, UNIX_EPOCH + Duration::from_secs_f64(file.fetched_at));
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("{}", e),
+                }
+            }
+        }
+        let refresh_interval = Duration::from_secs_f64(config.refresh_interval);
+        let age = self.fetched_at.map(|at| at.elapsed().unwrap_or_default());
+        if age.is_some_and(|age| age < refresh_interval) || self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return;
+        }
+
+        match fetch(config, network).await {
+            Ok(validators) => {
+                debug!("Fetched {} validators.app entries for {}", validators.len(), network);
+                let now = SystemTime::now();
+                if let Some(path) = &path {
+                    if let Err(e) = save(path, &validators, now) {
+                        warn!("{}", e);
+                    }
+                }
+                self.set(validators, now);
+                self.retry_at = None;
+            }
+            Err(e) => {
+                warn!("validators.app request for {} failed: {}", network, e);
+                self.retry_at = Some(Instant::now() + RETRY_INTERVAL);
+            }
+        }
+    }
+
+    fn set(&mut self, validators: Vec<AppValidator>, fetched_at: SystemTime) {
+        self.validators = validators
+            .into_iter()
+            .filter_map(|validator| Some((validator.vote_account.clone()?, validator)))
+            .collect();
+        self.fetched_at = Some(fetched_at);
+    }
+}
+
+async fn fetch(config: &ValidatorsAppConfig, network: &str) -> Result<Vec<AppValidator>, String> {
+    let url = format!("{}/validators/{}.json", config.url.trim_end_matches('/'), network);
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("Token", config.api_key.as_deref().unwrap_or_default())
+        .timeout(Duration::from_secs_f64(config.timeout))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status));
+    }
+    response.json().await.map_err(|e| format!("invalid response: {}", e))
+}
+
+fn cache_path(dir: &Path, network: &str) -> PathBuf {
+    dir.join(format!("validators-app-{}.json", network))
+}
+
+fn load(path: &Path) -> Result<Option<CacheFile>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+    };
+    serde_json::from_str(&contents).map(Some).map_err(|e| format!("cannot parse {}: {}", path.display(), e))
+}
+
+// Written next to the file and renamed over it, like the watchlist
+fn save(path: &Path, validators: &[AppValidator], fetched_at: SystemTime) -> Result<(), String> {
+    let file = CacheFile {
+        fetched_at: fetched_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64(),
+        validators: validators.to_vec(),
+    };
+    let contents = serde_json::to_string(&file).expect("validators.app listing serializes");
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, contents).map_err(|e| format!("cannot write {}: {}", Path::new(&temporary).display(), e))?;
+    fs::rename(&temporary, path).map_err(|e| format!("cannot replace {}: {}", path.display(), e))
+}
@@ -0,0 +1,42 @@
+// What a vote transaction costs, from `getFeeForMessage`. Votes pay the base
+// fee only, per signature, and are signed by the node's identity alone unless
+// the vote account has a separate authorized voter. The fee needs a recent
+// blockhash, so one is fetched with it, every `vote_fees.refresh_interval`.
+
+use crate::config::VoteFeesConfig;
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use std::time::Instant;
+
+#[derive(Default)]
+pub struct VoteFees {
+    fetched: Option<Instant>,
+    // Lamports of a transaction with one signature, None until fetched
+    pub lamports: Option<u64>,
+}
+
+impl VoteFees {
+    // Fetch the fee when due; a failure is logged and keeps the fee from before
+    pub async fn refresh(&mut self, client: &RpcClient, config: &VoteFeesConfig) {
+        if self.fetched.is_some_and(|fetched| fetched.elapsed().as_secs_f64() < config.refresh_interval) {
+            return;
+        }
+        match fetch(client).await {
+            Ok(lamports) => {
+                debug!("Fetched the vote fee: {} lamports", lamports);
+                self.lamports = Some(lamports);
+                self.fetched = Some(Instant::now());
+            }
+            Err(e) => warn!("Vote fee request failed: {}", e),
+        }
+    }
+}
+
+async fn fetch(client: &RpcClient) -> Result<u64, String> {
+    let blockhash = client.get_latest_blockhash().await.map_err(|e| e.to_string())?;
+    // The fee only depends on the signatures, so any payer does
+    let message = Message::new_with_blockhash(&[], Some(&Pubkey::default()), &blockhash);
+    client.get_fee_for_message(&message).await.map_err(|e| e.to_string())
+}
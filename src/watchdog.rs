@@ -0,0 +1,97 @@
+use crate::config::Config;
+use log::{error, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+// How often the watchdog looks at the heartbeat
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Progress signal of the fetch loop: before every step that may wait, the loop
+// announces how long that step is allowed to take
+pub struct Heartbeat {
+    deadline: Mutex<Instant>,
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self {
+            deadline: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn expect_within(&self, duration: Duration) {
+        *self.deadline.lock().unwrap() = Instant::now() + duration;
+    }
+
+    // How far past its announced deadline the loop is, if at all
+    fn overdue(&self, now: Instant) -> Option<Duration> {
+        let deadline = *self.deadline.lock().unwrap();
+        now.checked_duration_since(deadline)
+    }
+}
+
+// Restarts of one cluster's fetch task, kept across the restarts themselves
+pub struct RestartCounter {
+    count: AtomicU64,
+    pub created: SystemTime,
+}
+
+impl RestartCounter {
+    pub fn new() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            created: SystemTime::now(),
+        }
+    }
+
+    pub fn get(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+// Run the fetch task of `cluster` built by `spawn` and start a fresh one whenever
+// it panics or misses its heartbeat deadline by more than `poll.watchdog_grace`
+// seconds; a task that returns is done and is not restarted
+pub async fn supervise<F>(
+    cluster: String,
+    spawn: F,
+    heartbeat: Arc<Heartbeat>,
+    restarts: Arc<RestartCounter>,
+    config: watch::Receiver<Arc<Config>>,
+) where
+    F: Fn() -> JoinHandle<()>,
+{
+    loop {
+        // A new task gets a full poll period plus the grace before it is considered stuck
+        heartbeat.expect_within(config.borrow().poll.period(None));
+        let mut task = spawn();
+        let mut check = interval(CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                result = &mut task => {
+                    match result {
+                        Ok(()) => return,
+                        Err(e) if e.is_panic() => error!("Fetch task of cluster {} panicked, restarting it", cluster),
+                        Err(_) => warn!("Fetch task of cluster {} was cancelled, restarting it", cluster),
+                    }
+                    break;
+                }
+                _ = check.tick() => {
+                    let grace = Duration::from_secs_f64(config.borrow().poll.watchdog_grace);
+                    if let Some(overdue) = heartbeat.overdue(Instant::now()) {
+                        if overdue > grace {
+                            warn!("Fetch task of cluster {} made no progress for {:?}, restarting it", cluster, overdue);
+                            task.abort();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        restarts.count.fetch_add(1, Ordering::Relaxed);
+    }
+}
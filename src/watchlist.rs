@@ -0,0 +1,183 @@
+// The vote pubkeys the operator watches: the `watchlist` of the config file,
+// plus those kept in `watchlist_file` and managed at runtime through
+// `/api/v1/watchlist`, so adding a validator needs neither a restart nor an
+// edit on the host
+
+use crate::limits::read_body;
+use crate::config::Config;
+use crate::{admin, HttpState};
+use hyper::header::{ALLOW, CONTENT_TYPE};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use log::info;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+// Plenty for a few hundred entries
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchedValidator {
+    pub vote_pubkey: String,
+    // Human-readable label, e.g. the validator's moniker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+// Layout of the file and of the API's answers
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WatchlistFile {
+    validators: Vec<WatchedValidator>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Change {
+    // New entries, or new names for entries already on the list
+    add: Vec<WatchedValidator>,
+    // Vote pubkeys to drop; ones not on the list are ignored
+    remove: Vec<String>,
+}
+
+pub struct Watchlist {
+    validators: Mutex<Vec<WatchedValidator>>,
+}
+
+impl Watchlist {
+    pub fn new(validators: Vec<WatchedValidator>) -> Self {
+        Self { validators: Mutex::new(validators) }
+    }
+
+    pub fn replace(&self, validators: Vec<WatchedValidator>) {
+        *self.validators.lock().unwrap() = validators;
+    }
+
+    // Everything watched: the config's entries, then the file's; the config
+    // wins for a vote pubkey in both
+    pub fn all(&self, config: &Config) -> Vec<WatchedValidator> {
+        let mut all = config.watchlist.clone();
+        for validator in self.validators.lock().unwrap().iter() {
+            if !all.iter().any(|listed| listed.vote_pubkey == validator.vote_pubkey) {
+                all.push(validator.clone());
+            }
+        }
+        all
+    }
+}
+
+// A vote pubkey as Solana writes it: 32 bytes in base58
+pub fn check_pubkey(pubkey: &str) -> Result<(), String> {
+    Pubkey::from_str(pubkey).map(|_| ()).map_err(|_| format!("{:?} is not a valid base58 vote pubkey", pubkey))
+}
+
+pub fn check(validators: &[WatchedValidator]) -> Result<(), String> {
+    for (i, validator) in validators.iter().enumerate() {
+        check_pubkey(&validator.vote_pubkey)?;
+        if validator.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+            return Err(format!("{}: name must not be empty", validator.vote_pubkey));
+        }
+        if validators[..i].iter().any(|other| other.vote_pubkey == validator.vote_pubkey) {
+            return Err(format!("{} is listed twice", validator.vote_pubkey));
+        }
+    }
+    Ok(())
+}
+
+// The watched validators in `path`; a file that does not exist yet is an empty
+// list, it is created with the first change
+pub fn load(path: &Path) -> Result<Vec<WatchedValidator>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+    };
+    let file: WatchlistFile = toml::from_str(&contents).map_err(|e| format!("cannot parse {}: {}", path.display(), e))?;
+    check(&file.validators).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(file.validators)
+}
+
+// Written next to the file and renamed over it, so a crash cannot leave half a list
+fn save(path: &Path, validators: &[WatchedValidator]) -> Result<(), String> {
+    let file = WatchlistFile { validators: validators.to_vec() };
+    let contents = toml::to_string(&file).map_err(|e| format!("cannot serialize the watchlist: {}", e))?;
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, contents).map_err(|e| format!("cannot write {}: {}", Path::new(&temporary).display(), e))?;
+    fs::rename(&temporary, path).map_err(|e| format!("cannot replace {}: {}", path.display(), e))
+}
+
+// `GET` lists the watched validators, `POST` changes the list and answers with
+// the new one. Changes are held to the same rule as the admin endpoints.
+pub async fn handle(req: Request<Body>, peer: SocketAddr, state: &HttpState) -> Response<Body> {
+    if req.method() == Method::GET {
+        return json(&state.watchlist.all(&state.config.borrow()));
+    }
+    if req.method() != Method::POST {
+        return Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(ALLOW, "GET, POST")
+            .body(Body::from("405 Method Not Allowed\n"))
+            .unwrap();
+    }
+    if !admin::allowed(peer, state) {
+        return admin::forbidden();
+    }
+    let Some(path) = state.config.borrow().watchlist_file.clone() else {
+        return error(StatusCode::CONFLICT, "watchlist_file is not configured, changes could not be kept".to_string());
+    };
+    let change: Change = match read_body(req.into_body(), MAX_REQUEST_SIZE).await {
+        Ok(body) => match serde_json::from_slice(&body) {
+            Ok(change) => change,
+            Err(e) => return error(StatusCode::BAD_REQUEST, format!("invalid request: {}", e)),
+        },
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+    if let Err(e) = check(&change.add) {
+        return error(StatusCode::BAD_REQUEST, e);
+    }
+    let config = state.config.borrow().clone();
+    if let Some(listed) = change.remove.iter().find(|pubkey| config.watchlist.iter().any(|validator| &&validator.vote_pubkey == pubkey)) {
+        return error(StatusCode::BAD_REQUEST, format!("{} is in the watchlist of the config file, remove it there", listed));
+    }
+
+    // Held while saving, so concurrent changes cannot overwrite each other
+    let mut validators = state.watchlist.validators.lock().unwrap();
+    let mut updated = validators.clone();
+    updated.retain(|validator| !change.remove.contains(&validator.vote_pubkey));
+    for validator in change.add {
+        match updated.iter_mut().find(|existing| existing.vote_pubkey == validator.vote_pubkey) {
+            Some(existing) => existing.name = validator.name,
+            None => updated.push(validator),
+        }
+    }
+    if let Err(e) = save(&path, &updated) {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, e);
+    }
+    info!("Watchlist changed by {}, now {} validator(s)", peer, updated.len());
+    *validators = updated;
+    drop(validators);
+    json(&state.watchlist.all(&config))
+}
+
+fn json(validators: &[WatchedValidator]) -> Response<Body> {
+    let body = serde_json::to_string(&WatchlistFile { validators: validators.to_vec() }).expect("watchlist serializes");
+    Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn error(status: StatusCode, message: String) -> Response<Body> {
+    let body = serde_json::json!({ "error": message });
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
@@ -0,0 +1,210 @@
+// The `/ws` endpoint: a WebSocket that sends the current validators of each
+// cluster once, then after every poll only what moved (rank changes, credit
+// deltas, validators that joined or left), for bots that react to rank movement
+
+use crate::config::Config;
+use crate::limits::{InFlight, Slot};
+use crate::{SharedCache, ValidatorMetrics};
+use futures_util::{SinkExt, StreamExt};
+use hyper::header::{CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE};
+use hyper::upgrade::Upgraded;
+use hyper::{Body, Request, Response, StatusCode};
+use log::warn;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+use tokio::task;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event<'a> {
+    // Sent once per cluster after connecting
+    Snapshot {
+        cluster: &'a str,
+        epoch: Option<u64>,
+        validators: &'a [ValidatorMetrics],
+    },
+    // Sent after a poll that changed anything
+    Delta {
+        cluster: &'a str,
+        epoch: Option<u64>,
+        changes: Vec<Change<'a>>,
+        removed: Vec<&'a str>,
+    },
+}
+
+#[derive(Serialize)]
+struct Change<'a> {
+    vote_pubkey: &'a str,
+    rank: usize,
+    // None for a validator that was not in the previous poll
+    previous_rank: Option<usize>,
+    credits_earned: u64,
+    // Never across an epoch boundary, where a new snapshot is sent instead
+    credits_delta: i64,
+}
+
+// Rank and credits by vote pubkey, as last sent to the client
+type Standings = HashMap<String, (usize, u64)>;
+// The epoch and standings last sent of each cluster
+type Sent = HashMap<String, (Option<u64>, Standings)>;
+
+// Answer the upgrade request and stream to the client once the connection is
+// handed over. The upgraded connection no longer counts as an HTTP connection,
+// so it takes its own slot in `connections`.
+pub fn upgrade(
+    req: Request<Body>,
+    cache: SharedCache,
+    config: watch::Receiver<Arc<Config>>,
+    updates: broadcast::Receiver<String>,
+    connections: Arc<InFlight>,
+    cluster: Option<String>,
+) -> Response<Body> {
+    let is_upgrade = req
+        .headers()
+        .get(UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    let Some(key) = req.headers().get(SEC_WEBSOCKET_KEY).filter(|_| is_upgrade) else {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from("400 Bad Request: expected a WebSocket upgrade\n"))
+            .unwrap();
+    };
+    let max_connections = config.borrow().server.limits.max_connections;
+    let Some(slot) = connections.try_acquire(max_connections) else {
+        return Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body(Body::from("503 Service Unavailable: too many connections\n"))
+            .unwrap();
+    };
+    let accept = derive_accept_key(key.as_bytes());
+
+    task::spawn(async move {
+        match hyper::upgrade::on(req).await {
+            Ok(upgraded) => {
+                let socket = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+                stream(socket, slot, cache, updates, cluster).await;
+            }
+            Err(e) => warn!("WebSocket upgrade failed: {}", e),
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(UPGRADE, "websocket")
+        .header(CONNECTION, "upgrade")
+        .header(SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Body::empty())
+        .unwrap()
+}
+
+async fn stream(
+    mut socket: WebSocketStream<Upgraded>,
+    _slot: Slot,
+    cache: SharedCache,
+    mut updates: broadcast::Receiver<String>,
+    cluster: Option<String>,
+) {
+    let wanted = |name: &str| cluster.as_deref().is_none_or(|cluster| cluster == name);
+    let mut sent: Sent = HashMap::new();
+
+    let initial: Vec<String> = cache.lock().await.keys().filter(|name| wanted(name)).cloned().collect();
+    for name in initial {
+        if !send_update(&mut socket, &cache, &name, &mut sent).await {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(name) if wanted(&name) => {
+                    if !send_update(&mut socket, &cache, &name, &mut sent).await {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                // Deltas are taken against what this client last got, so missed updates lose nothing
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return,
+            },
+            // Only closing is of interest; pings are answered by the library
+            incoming = socket.next() => match incoming {
+                Some(Ok(Message::Close(_))) => {
+                    // Flushes the close reply queued by the library
+                    let _ = socket.close(None).await;
+                    return;
+                }
+                Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+// A snapshot the first time a cluster is sent and with every new epoch, whose
+// credits start over, a delta otherwise (nothing when the poll changed
+// nothing); whether the client is still there
+async fn send_update(
+    socket: &mut WebSocketStream<Upgraded>,
+    cache: &SharedCache,
+    cluster: &str,
+    sent: &mut Sent,
+) -> bool {
+    let message = {
+        let cache = cache.lock().await;
+        let Some(cluster_cache) = cache.get(cluster) else {
+            // Removed from the configuration meanwhile
+            return true;
+        };
+        let epoch = cluster_cache.epoch_info.as_ref().map(|info| info.epoch);
+        let validators = &cluster_cache.validators;
+        let current: Standings = validators
+            .iter()
+            .map(|validator| (validator.vote_pubkey.clone(), (validator.rank, validator.credits_earned)))
+            .collect();
+
+        let event = match sent.get(cluster) {
+            Some((sent_epoch, _)) if *sent_epoch != epoch => Event::Snapshot { cluster, epoch, validators },
+            None => Event::Snapshot { cluster, epoch, validators },
+            Some((_, previous)) => {
+                let changes: Vec<Change> = validators
+                    .iter()
+                    .filter_map(|validator| {
+                        let before = previous.get(&validator.vote_pubkey);
+                        if before == Some(&(validator.rank, validator.credits_earned)) {
+                            return None;
+                        }
+                        Some(Change {
+                            vote_pubkey: &validator.vote_pubkey,
+                            rank: validator.rank,
+                            previous_rank: before.map(|(rank, _)| *rank),
+                            credits_earned: validator.credits_earned,
+                            credits_delta: validator.credits_earned as i64 - before.map_or(0, |(_, credits)| *credits as i64),
+                        })
+                    })
+                    .collect();
+                let removed: Vec<&str> = previous
+                    .keys()
+                    .filter(|pubkey| !current.contains_key(*pubkey))
+                    .map(String::as_str)
+                    .collect();
+                if changes.is_empty() && removed.is_empty() {
+                    return true;
+                }
+                Event::Delta { cluster, epoch, changes, removed }
+            }
+        };
+        let message = serde_json::to_string(&event).expect("websocket event serializes");
+        sent.insert(cluster.to_string(), (epoch, current));
+        message
+    };
+    socket.send(Message::Text(message)).await.is_ok()
+}